@@ -0,0 +1,54 @@
+//! Compares the allocations made while creating people one at a time in a `Context` from `Context::new` against one
+//! from `Context::with_capacity`, which reserves the columns up front.
+//!
+//! Run with `cargo bench --package initialization --bench capacity`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use initialization::Context;
+
+const PEOPLE: usize = 100_000;
+
+/// The system allocator, counting every allocation and reallocation.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Creates `PEOPLE` people one at a time in `context`, returning the allocations made and the time taken.
+fn create(mut context: Context) -> (usize, Duration) {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..PEOPLE {
+        context.create_person().unwrap();
+    }
+    (ALLOCATIONS.load(Ordering::Relaxed) - before, start.elapsed())
+}
+
+fn main() {
+    let (growing, growing_time) = create(Context::new());
+    let (reserved, reserved_time) = create(Context::with_capacity(PEOPLE));
+
+    println!("create_person x {} growing the columns: {} allocations, {:?}", PEOPLE, growing, growing_time);
+    println!("create_person x {} with reserved columns: {} allocations, {:?}", PEOPLE, reserved, reserved_time);
+}
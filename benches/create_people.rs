@@ -0,0 +1,38 @@
+//! Compares creating people one at a time with `Context::create_person` against creating them in one batch with
+//! `Context::create_people`, which initializes the columns of batch-capable plugins in parallel.
+//!
+//! Run with `cargo bench --package initialization --bench create_people`.
+
+use std::time::{Duration, Instant};
+
+use initialization::Context;
+
+const PEOPLE: usize = 1_000_000;
+const RUNS: u32 = 5;
+
+fn time(mut run: impl FnMut()) -> Duration {
+    let mut total = Duration::ZERO;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        run();
+        total += start.elapsed();
+    }
+    total / RUNS
+}
+
+fn main() {
+    let serial = time(|| {
+        let mut context = Context::new();
+        for _ in 0..PEOPLE {
+            context.create_person().unwrap();
+        }
+    });
+
+    let batched = time(|| {
+        let mut context = Context::new();
+        context.create_people(PEOPLE).unwrap();
+    });
+
+    println!("create_person x {}: {:?}", PEOPLE, serial);
+    println!("create_people({}): {:?}", PEOPLE, batched);
+}
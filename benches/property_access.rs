@@ -0,0 +1,45 @@
+//! Compares reading and writing a property by name with `Context::get` and `Context::set` against doing so through a
+//! `PropertyHandle` with `Context::get_by_handle` and `Context::set_by_handle`.
+//!
+//! Run with `cargo bench --package initialization --bench property_access`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use initialization::Context;
+
+const PEOPLE: usize = 100_000;
+const RUNS: u32 = 5;
+
+fn time(mut run: impl FnMut()) -> Duration {
+    let mut total = Duration::ZERO;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        run();
+        total += start.elapsed();
+    }
+    total / RUNS
+}
+
+fn main() {
+    let mut context = Context::new();
+    let people = context.create_people(PEOPLE).unwrap();
+    let age = context.handle::<u32>("Age").unwrap();
+
+    let by_name = time(|| {
+        for person_id in people.clone() {
+            let next = context.get::<u32>("Age", person_id).unwrap() + 1;
+            context.set::<u32>("Age", person_id, black_box(next)).unwrap();
+        }
+    });
+
+    let by_handle = time(|| {
+        for person_id in people.clone() {
+            let next = context.get_by_handle(age, person_id).unwrap() + 1;
+            context.set_by_handle(age, person_id, black_box(next)).unwrap();
+        }
+    });
+
+    println!("get and set by name x {}: {:?}", PEOPLE, by_name);
+    println!("get and set by handle x {}: {:?}", PEOPLE, by_handle);
+}
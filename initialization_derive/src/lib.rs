@@ -0,0 +1,128 @@
+//! `#[derive(Plugin)]`, re-exported by `initialization`, which declares a property plugin from a type. See the
+//! documentation of the re-export there.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, DeriveInput, Error, Expr, LitStr, Result, Type};
+
+#[proc_macro_derive(Plugin, attributes(plugin))]
+pub fn derive_plugin(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+/// The contents of the `#[plugin(...)]` attribute.
+struct Attributes {
+    name: Option<LitStr>,
+    description: Option<LitStr>,
+    unit: Option<LitStr>,
+    value: Option<Type>,
+    default: Option<Expr>,
+    required: bool,
+    dependencies: Vec<LitStr>,
+}
+
+fn parse_attributes(input: &DeriveInput) -> Result<Attributes> {
+    let mut attributes = Attributes {
+        name: None,
+        description: None,
+        unit: None,
+        value: None,
+        default: None,
+        required: false,
+        dependencies: Vec::new(),
+    };
+    for attribute in input.attrs.iter().filter(|attribute| attribute.path().is_ident("plugin")) {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                attributes.name = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("description") {
+                attributes.description = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("unit") {
+                attributes.unit = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("value") {
+                attributes.value = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("default") {
+                attributes.default = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("required") {
+                attributes.required = true;
+            } else if meta.path.is_ident("dependencies") {
+                // Each dependency is a path, so a qualified name is written `namespace::name` without quotes.
+                meta.parse_nested_meta(|dependency| {
+                    let segments: Vec<String> =
+                        dependency.path.segments.iter().map(|segment| segment.ident.to_string()).collect();
+                    let span = dependency.path.segments.last().unwrap().ident.span();
+                    attributes.dependencies.push(LitStr::new(&segments.join("::"), span));
+                    Ok(())
+                })?;
+            } else {
+                return Err(meta.error("unsupported plugin attribute"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(attributes)
+}
+
+fn expand(input: DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let attributes = parse_attributes(&input)?;
+    let ident = &input.ident;
+    let missing = |key: &str| Error::new(Span::call_site(), format!("missing `#[plugin({} = ...)]`", key));
+    let value = attributes.value.ok_or_else(|| missing("value"))?;
+    let default = attributes.default.ok_or_else(|| missing("default"))?;
+    let name = attributes.name.unwrap_or_else(|| LitStr::new(&ident.to_string(), ident.span()));
+    let description = attributes.description.unwrap_or_else(|| LitStr::new("", Span::call_site()));
+    let unit = match attributes.unit {
+        Some(unit) => quote!(Some(#unit)),
+        None => quote!(None),
+    };
+    let required = attributes.required;
+    let dependencies = attributes.dependencies;
+
+    let prefix = upper_snake_case(&ident.to_string());
+    let property = format_ident!("{}_PROPERTY", prefix);
+    let plugin = format_ident!("{}_PLUGIN", prefix);
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics #ident #type_generics #where_clause {
+            /// The name of the plugin declared by `#[derive(Plugin)]`, for looking up its values.
+            pub const NAME: &'static str = #name;
+        }
+
+        static #property: ::initialization::Property<#value> = ::initialization::Property{
+            name: #name,
+            default: #default,
+            initializer: None,
+        };
+
+        ::initialization::register_plugin! {
+            static #plugin: Plugin{
+                name: #name,
+                namespace: env!("CARGO_PKG_NAME"),
+                description: #description,
+                unit: #unit,
+                required: #required,
+                dependencies: &[#(#dependencies),*],
+                initializer: |context, person_id| #property.initialize(context, person_id),
+                batch_initializer: Some(|column, people| #property.initialize_batch(column, people)),
+            };
+            constructor: |context| {
+                #property.construct(context);
+            }
+        }
+    })
+}
+
+/// `HeightInCm` becomes `HEIGHT_IN_CM`, for the names of the generated statics.
+fn upper_snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (index, c) in name.chars().enumerate() {
+        if c.is_uppercase() && index > 0 {
+            snake.push('_');
+        }
+        snake.extend(c.to_uppercase());
+    }
+    snake
+}
@@ -1,31 +1,212 @@
-use linkme::distributed_slice;
-use initialization::{Plugin, PLUGINS};
+use initialization::{register_plugin, Property};
 
-#[distributed_slice(PLUGINS)]
-static WEIGHT_PLUGIN: Plugin = Plugin{
+static WEIGHT: Property<f64> = Property{
   name: "Weight",
-  description: "Weight of the person in lbs",
-  required: true,
-  enabled: true,
-  initializer: |_context, _person_id| {
-    // The default weight.
-    140;
-  },
+  // The default weight.
+  default: 140.0,
+  initializer: None,
+};
+
+register_plugin! {
+  static WEIGHT_PLUGIN: Plugin{
+    name: WEIGHT.name,
+    namespace: env!("CARGO_PKG_NAME"),
+    description: "Weight of the person in lbs",
+    unit: Some("lbs"),
+    required: true,
+    enabled: true,
+    initializer: |context, person_id| WEIGHT.initialize(context, person_id),
+    // Initialized after `Waist` when that is compiled in, but not dependent on it.
+    optional_dependencies: &["Waist"],
+    tags: &["anthropometric"],
+    batch_initializer: Some(|column, people| WEIGHT.initialize_batch(column, people)),
+  };
   constructor: |context| {
-    context.register_plugin(&WEIGHT_PLUGIN);
+    WEIGHT.construct(context);
+    context.register_formatter::<f64>(WEIGHT.name, f64::to_string);
+    context.register_codec::<f64>(WEIGHT.name, f64::to_string, |value| value.parse().ok());
+    context.register_conversion(WEIGHT.name, "kg", |lbs| lbs * 0.453_592_37);
   }
+}
+
+#[cfg(feature = "waist")]
+static WAIST: Property<f64> = Property{
+  name: "Waist",
+  // The default waist circumference.
+  default: 34.0,
+  initializer: None,
 };
 
+// Only registered with the `waist` feature. Without it the plugin is absent from `PLUGINS`.
+#[cfg(feature = "waist")]
+register_plugin! {
+  static WAIST_PLUGIN: Plugin{
+    name: WAIST.name,
+    namespace: env!("CARGO_PKG_NAME"),
+    description: "Waist circumference of the person in inches",
+    unit: Some("in"),
+    initializer: |context, person_id| WAIST.initialize(context, person_id),
+    tags: &["anthropometric"],
+    batch_initializer: Some(|column, people| WAIST.initialize_batch(column, people)),
+  };
+  constructor: |context| {
+    WAIST.construct(context);
+  }
+}
+
 
 #[cfg(test)]
 mod test {
-  use initialization::Context;
+  use std::path::PathBuf;
+  use std::{env, fs, process};
+
+  use initialization::{
+    ConfigError, Context, ContextBuilder, InitError, PersonId, Plugin, Property, RuntimeError, UnknownPlugins, Warning,
+  };
 
   #[test]
    fn it_works() {
     let context = Context::new();
     for plugin in context.plugins.iter() {
-      println!("Plugin: {}", plugin);
+      println!("Plugin: {}", plugin.name);
     }
    } 
-}
\ No newline at end of file
+
+  #[test]
+  fn plugins_of_both_crates_are_registered() {
+    let context = Context::new_for_test(&["Age", "Weight"]);
+    assert_eq!(context.names(), vec!["Age", "Weight"]);
+    assert_eq!(context.require("Age").origin, "initialization::built_in_plugins");
+    assert_eq!(context.require("Weight").origin, "my_plugin");
+  }
+
+  #[test]
+  fn defaults_are_stored_by_type() {
+    let mut context = Context::new();
+    context.create_person().unwrap();
+    assert_eq!(context.property::<u32>("Age"), Some(&vec![42]));
+    assert_eq!(context.property::<f64>("Weight"), Some(&vec![140.0]));
+  }
+
+  static HEIGHT: Property<f64> = Property{ name: "Height", default: 66.0, initializer: None };
+
+  static SHOE_SIZE: Property<u8> = Property{ name: "ShoeSize", default: 9, initializer: None };
+
+  // Neither is `required`, unlike `Age` and `Weight`, so either may be disabled.
+  static OPTIONAL_PLUGINS: [Plugin; 2] = [
+    Plugin::builder(HEIGHT.name)
+      .initializer(|context, person_id| HEIGHT.initialize(context, person_id))
+      .constructor(|context| HEIGHT.construct(context))
+      .build(),
+    Plugin::builder(SHOE_SIZE.name)
+      .initializer(|context, person_id| SHOE_SIZE.initialize(context, person_id))
+      .constructor(|context| SHOE_SIZE.construct(context))
+      .build(),
+  ];
+
+  #[test]
+  fn contexts_are_independent() {
+    let builder = || ContextBuilder::with_plugins(&OPTIONAL_PLUGINS);
+    let mut without_shoes = builder().disable("ShoeSize").build().unwrap();
+    let mut without_height = builder().disable("Height").build().unwrap();
+    assert_eq!(without_shoes.names(), vec!["Height"]);
+    assert_eq!(without_height.names(), vec!["ShoeSize"]);
+
+    let person_id = without_shoes.create_person().unwrap();
+    without_shoes.set::<f64>("Height", person_id, 70.0).unwrap();
+    without_height.create_person().unwrap();
+    without_height.create_person().unwrap();
+
+    assert_eq!(without_shoes.property::<f64>("Height"), Some(&vec![70.0]));
+    assert_eq!(without_shoes.property::<u8>("ShoeSize"), None);
+    assert_eq!(without_height.property::<f64>("Height"), None);
+    assert_eq!(without_height.property::<u8>("ShoeSize"), Some(&vec![9, 9]));
+
+    let mut fresh = Context::with_plugins(&OPTIONAL_PLUGINS);
+    fresh.create_person().unwrap();
+    assert_eq!(fresh.get::<f64>("Height", PersonId(0)), Some(&66.0));
+  }
+
+  /// Writes `contents` to a file in the temporary directory that is unique to this test run.
+  fn write_config(file_name: &str, contents: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("my_plugin-{}-{}", process::id(), file_name));
+    fs::write(&path, contents).unwrap();
+    path
+  }
+
+  #[test]
+  fn weight_is_measured_in_lbs() {
+    let mut context = Context::new();
+    assert_eq!(context.require("Weight").unit, Some("lbs"));
+    assert_eq!(context.describe("Weight").unwrap().unit, Some("lbs"));
+
+    context.create_person().unwrap();
+    let mut csv = Vec::new();
+    context.export_csv(&mut csv).unwrap();
+    let header = String::from_utf8(csv).unwrap().lines().next().unwrap().to_string();
+    assert!(header.split(',').any(|column| column == "Weight (lbs)"), "{}", header);
+  }
+
+  #[test]
+  fn weight_converts_from_lbs_to_kg() {
+    let mut context = Context::new();
+    let person_id = context.create_person().unwrap();
+    let kg = context.get_as::<f64>("Weight", person_id, "kg").unwrap().unwrap();
+    assert!((kg - 63.502_931_8).abs() < 1e-6, "{}", kg);
+    assert_eq!(context.get_as::<f64>("Weight", person_id, "lbs"), Ok(Some(140.0)));
+
+    let error = context.get_as::<f64>("Weight", person_id, "stone").err().unwrap();
+    let unknown = RuntimeError::UnknownConversion { plugin: "Weight", unit: "stone".to_string() };
+    assert_eq!(error, InitError::Runtime(unknown));
+    assert_eq!(error.to_string(), "plugin `Weight` has no conversion to `stone`");
+  }
+
+  // `tests/features.rs` runs the tests named after `waist` with the feature on.
+  #[cfg(feature = "waist")]
+  #[test]
+  fn waist_is_registered_with_its_feature() {
+    let mut context = Context::new();
+    assert_eq!(context.names(), vec!["Age", "Waist", "Weight"]);
+    let order = context.init_order();
+    assert!(order.iter().position(|&name| name == "Waist") < order.iter().position(|&name| name == "Weight"));
+    let person_id = context.create_person().unwrap();
+    assert_eq!(context.get::<f64>("Waist", person_id), Some(&34.0));
+  }
+
+  #[cfg(not(feature = "waist"))]
+  #[test]
+  fn waist_is_absent_without_its_feature() {
+    assert_eq!(ContextBuilder::new().validate_all(), Ok(()));
+    let context = Context::new();
+    assert_eq!(context.names(), vec!["Age", "Weight"]);
+    assert!(context.get_plugin("Waist").is_none());
+    assert!(context.warnings().is_empty());
+  }
+
+  #[test]
+  fn config_file_disables_a_plugin() {
+    let path = write_config("disable-shoe-size.toml", "ShoeSize = false\n");
+    let context = ContextBuilder::with_plugins(&OPTIONAL_PLUGINS).config_file(&path).unwrap().build().unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(context.names(), vec!["Height"]);
+
+    // Like `Context::disable`, a configuration file cannot disable a `required` plugin.
+    let path = write_config("disable-weight.toml", "Weight = false\n");
+    let error = ContextBuilder::from_config(&path).unwrap().build().err().unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(error, InitError::Config(ConfigError::DisableRequired("Weight")));
+  }
+
+  #[test]
+  fn config_file_with_unknown_plugin() {
+    let path = write_config("unknown-plugin.json", r#"{"ShoeSize": false, "Width": true}"#);
+    let builder = || ContextBuilder::with_plugins(&OPTIONAL_PLUGINS).config_file(&path).unwrap();
+    let error = builder().build().err().unwrap();
+    let context = builder().unknown_plugins(UnknownPlugins::Warn).build().unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(error, InitError::Config(ConfigError::UnknownPlugin("Width".to_string())));
+    assert_eq!(context.names(), vec!["Height"]);
+    assert_eq!(context.warnings(), [Warning::UnknownPlugin("Width".to_string())]);
+  }
+}
@@ -0,0 +1,20 @@
+//! `cargo test` builds without the `waist` feature, so this runs the tests that depend on it with the feature on.
+
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn waist_feature_registers_the_plugin() {
+  let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+  let output = Command::new(env!("CARGO"))
+    .args(["test", "--lib", "--features", "waist", "--package", "my_plugin"])
+    .arg("--target-dir")
+    .arg(manifest_dir.join("target").join("waist"))
+    .args(["--", "waist"])
+    .current_dir(manifest_dir)
+    .output()
+    .expect("cargo runs");
+  let stdout = String::from_utf8_lossy(&output.stdout);
+  assert!(output.status.success(), "{}{}", stdout, String::from_utf8_lossy(&output.stderr));
+  assert!(stdout.contains("waist_is_registered_with_its_feature ... ok"), "{}", stdout);
+}
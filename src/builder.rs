@@ -0,0 +1,1452 @@
+//! Configuration of the plugin set before any plugin is initialized.
+
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet, VecDeque};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::{type_name, Any, TypeId};
+#[cfg(feature = "std")]
+use core::cell::Cell;
+#[cfg(feature = "std")]
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::fs;
+#[cfg(feature = "std")]
+use std::io::Read;
+#[cfg(feature = "std")]
+use std::path::Path;
+
+#[cfg(feature = "std")]
+use crate::checkpoint;
+use crate::column::store_mut;
+#[cfg(feature = "std")]
+use crate::config::parse_overrides;
+use crate::{
+    ColumnKey, ConfigError, Context, DefaultOverride, DefaultState, InitError, InitHook, Plugin, PluginInstance, Rng,
+    RuntimeError, SkipReason, Warning, PLUGINS,
+};
+
+/// What `ContextBuilder::build` does with an override naming a plugin or tag that is not registered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnknownPlugins {
+    /// Fail with `ConfigError::UnknownPlugin` or `ConfigError::UnknownTag`.
+    #[default]
+    Error,
+    /// Ignore the override, recording a `Warning` in `Context::warnings`.
+    Warn,
+}
+
+/// Records configuration that must happen before `init()` is called, such as disabling an optional plugin, and then
+/// constructs the `Context`.
+///
+/// ```
+/// use initialization::{ContextBuilder, Plugin};
+///
+/// static PLUGINS: [Plugin; 1] = [Plugin::builder("Height").build()];
+///
+/// let context = ContextBuilder::with_plugins(&PLUGINS).disable("Height").build().unwrap();
+/// assert!(context.plugins.is_empty());
+/// ```
+pub struct ContextBuilder {
+    /// The plugins the `Context` is built from: all of them, unless `only_tags` excluded some.
+    plugins: Vec<&'static Plugin>,
+    /// The plugins `only_tags` left out, which kept plugins must not depend on.
+    excluded: Vec<&'static Plugin>,
+    /// The tags passed to `only_tags`, checked for plugins that carry them like `disabled_tags`.
+    only_tags: BTreeSet<String>,
+    /// Maps a plugin name, qualified or bare, to whether it is enabled, taking precedence over the plugin's static
+    /// `enabled` flag.
+    overrides: BTreeMap<String, bool>,
+    /// Plugins carrying any of these tags are disabled unless enabled by name.
+    disabled_tags: BTreeSet<String>,
+    unknown_plugins: UnknownPlugins,
+    /// Problems with the environment variables read by `apply_env`, reported by `build` along with the other warnings.
+    env_warnings: Vec<Warning>,
+    before_each: Vec<InitHook>,
+    after_each: Vec<InitHook>,
+    /// The plugin name, column type, type name and overriding default of each `set_default` call, in call order.
+    defaults: Vec<(String, TypeId, &'static str, DefaultOverride)>,
+    seed: u64,
+    /// The number of people each property column has room for from the start. See `capacity`.
+    capacity: usize,
+    /// The time `plugin.init` may take before `build` and `Context::enable` warn about it. See `warn_slow`.
+    #[cfg(feature = "std")]
+    slow_threshold: Option<Duration>,
+}
+
+impl Default for ContextBuilder {
+    fn default() -> Self {
+        ContextBuilder::new()
+    }
+}
+
+impl ContextBuilder {
+    /// A builder over the global `PLUGINS` registry.
+    pub fn new() -> Self {
+        ContextBuilder::with_plugins(PLUGINS.static_slice())
+    }
+
+    /// A builder over an arbitrary set of plugins rather than the global `PLUGINS` registry.
+    pub fn with_plugins(plugins: &'static [Plugin]) -> Self {
+        ContextBuilder {
+            plugins: plugins.iter().collect(),
+            excluded: Vec::new(),
+            only_tags: BTreeSet::new(),
+            overrides: BTreeMap::new(),
+            disabled_tags: BTreeSet::new(),
+            unknown_plugins: UnknownPlugins::default(),
+            env_warnings: Vec::new(),
+            before_each: Vec::new(),
+            after_each: Vec::new(),
+            defaults: Vec::new(),
+            seed: 0,
+            capacity: 0,
+            #[cfg(feature = "std")]
+            slow_threshold: None,
+        }
+    }
+
+    /// A builder over the global `PLUGINS` registry with the overrides in the configuration file at `path` applied.
+    /// See `ContextBuilder::config_file`.
+    #[cfg(feature = "std")]
+    pub fn from_config(path: &Path) -> Result<Self, InitError> {
+        ContextBuilder::new().config_file(path)
+    }
+
+    /// Applies the enable/disable overrides in the configuration file at `path`, which maps plugin names to booleans.
+    /// The file is read as JSON if its extension is `.json` and as TOML otherwise. Overrides for unknown plugins are
+    /// handled according to `ContextBuilder::unknown_plugins` when `build` is called.
+    #[cfg(feature = "std")]
+    pub fn config_file(mut self, path: &Path) -> Result<Self, InitError> {
+        let invalid = |message: String| -> InitError {
+            ConfigError::InvalidConfig { path: path.display().to_string(), message }.into()
+        };
+
+        let text = fs::read_to_string(path).map_err(|error| invalid(error.to_string()))?;
+        for (name, enabled) in parse_overrides(path, &text).map_err(invalid)? {
+            self.overrides.insert(name, enabled);
+        }
+        Ok(self)
+    }
+
+    /// Applies enable/disable overrides from the environment variables named `<prefix>_PLUGIN_<NAME>`, set to `1` to
+    /// enable the plugin and to `0` to disable it, for toggling plugins without a configuration file. `<NAME>` is the
+    /// plugin's name, bare or qualified, in upper case with every character other than an ASCII letter or digit
+    /// replaced by `_`, so that the plugin `Body-Mass` in the namespace `health` is toggled by
+    /// `<prefix>_PLUGIN_BODY_MASS` or `<prefix>_PLUGIN_HEALTH__BODY_MASS`. A variable applies to every plugin whose
+    /// name gives the same `<NAME>`.
+    ///
+    /// Variables naming no registered plugin, or with another value, are ignored with a `Warning` in
+    /// `Context::warnings`, regardless of `ContextBuilder::unknown_plugins`.
+    #[cfg(feature = "std")]
+    pub fn apply_env(self, prefix: &str) -> Self {
+        let variables = std::env::vars_os()
+            .filter_map(|(variable, value)| Some((variable.into_string().ok()?, value.into_string().ok()?)));
+        self.apply_env_vars(prefix, variables)
+    }
+
+    /// Like `ContextBuilder::apply_env`, but reads the `(variable, value)` pairs of `variables` rather than the
+    /// environment of the process, such as a snapshot of it taken earlier.
+    pub fn apply_env_vars(mut self, prefix: &str, variables: impl IntoIterator<Item = (String, String)>) -> Self {
+        let marker = format!("{}_PLUGIN_", prefix);
+        let mut variables: Vec<(String, String)> =
+            variables.into_iter().filter(|(variable, _)| variable.starts_with(&marker)).collect();
+        variables.sort_unstable();
+
+        for (variable, value) in variables {
+            let enabled = match value.as_str() {
+                "1" => true,
+                "0" => false,
+                _ => {
+                    self.env_warnings.push(Warning::InvalidEnvValue { variable, value });
+                    continue;
+                }
+            };
+            let name = &variable[marker.len()..];
+            let mut names = BTreeSet::new();
+            for plugin in self.plugins.iter().chain(&self.excluded) {
+                if env_name(plugin.name) == name {
+                    names.insert(plugin.name.to_string());
+                } else if env_name(&plugin.qualified_name()) == name {
+                    names.insert(plugin.qualified_name());
+                }
+            }
+            if names.is_empty() {
+                self.env_warnings.push(Warning::UnknownEnvPlugin(variable));
+            }
+            for name in names {
+                self.overrides.insert(name, enabled);
+            }
+        }
+        self
+    }
+
+    /// Sets what `build` does with an override naming a plugin or tag that is not registered. The default is to fail,
+    /// since a typo would otherwise silently have no effect.
+    pub fn unknown_plugins(mut self, policy: UnknownPlugins) -> Self {
+        self.unknown_plugins = policy;
+        self
+    }
+
+    /// Enables the named plugin regardless of its static `enabled` flag. Overrides the effect of any earlier `disable`.
+    /// The name is either qualified, `"namespace::name"`, or a bare name that no other plugin shares.
+    pub fn enable(mut self, name: &str) -> Self {
+        self.overrides.insert(name.to_string(), true);
+        self
+    }
+
+    /// Disables the named plugin regardless of its static `enabled` flag, so its `init()` is never called. Overrides
+    /// the effect of any earlier `enable`. `build` fails with `ConfigError::DisableRequired` if the plugin is
+    /// `required`, like `Context::disable`.
+    pub fn disable(mut self, name: &str) -> Self {
+        self.overrides.insert(name.to_string(), false);
+        self
+    }
+
+    /// Disables every plugin carrying `tag`, except those explicitly enabled by name with `enable`.
+    pub fn disable_tag(mut self, tag: &str) -> Self {
+        self.disabled_tags.insert(tag.to_string());
+        self
+    }
+
+    /// Builds the `Context` from only the plugins carrying at least one of `tags`, leaving the others out of it
+    /// entirely, for a sub-model that needs only some modules. Each call narrows the plugin set further. Overrides may
+    /// still name the excluded plugins, to no effect.
+    ///
+    /// `build` fails with `ConfigError::ExcludedDependency` if a kept plugin that is enabled depends on an excluded
+    /// one, and treats a tag that no registered plugin carries like one passed to `disable_tag`.
+    pub fn only_tags(mut self, tags: &[&str]) -> Self {
+        let (kept, excluded): (Vec<&'static Plugin>, Vec<&'static Plugin>) =
+            self.plugins.into_iter().partition(|plugin| plugin.tags.iter().any(|tag| tags.contains(tag)));
+        self.plugins = kept;
+        self.excluded.extend(excluded);
+        self.only_tags.extend(tags.iter().map(|tag| tag.to_string()));
+        self
+    }
+
+    /// Gives every person created by the `Context` the value `value` in the column of plugin `name` holding values of
+    /// type `T`, instead of the value computed by the plugin's `initializer`. The initializer still runs, and the value
+    /// is overwritten right after it, so plugins initialized later already see `value`. Overrides the effect of any
+    /// earlier `set_default` for the same plugin and type. Ignored if the plugin is disabled when `build` is called.
+    ///
+    /// `build` fails if no plugin has that name, and with `RuntimeError::TypeMismatch` if the plugin registers no
+    /// column of type `T`.
+    pub fn set_default<T: Clone + Send + Sync + 'static>(mut self, name: &str, value: T) -> Self {
+        let default: DefaultOverride = Box::new(move |column: &mut dyn Any, people| {
+            let column = store_mut::<T>(column).expect("the column has the type of the default");
+            for index in people.start..people.end.min(column.len()) {
+                column.set(index, value.clone());
+            }
+        });
+        self.defaults.push((name.to_string(), TypeId::of::<T>(), type_name::<T>(), default));
+        self
+    }
+
+    /// Seeds the random number generator of the `Context`, `Context::rng`, which is otherwise seeded with `0`.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Records a `Warning::SlowInit` for every `plugin.init` that takes longer than `threshold`, including for plugins
+    /// enabled later with `Context::enable`. An initializer that never returns cannot be interrupted, but one that is
+    /// merely slow is named in `Context::warnings` and `Context::init_report`.
+    #[cfg(feature = "std")]
+    pub fn warn_slow(mut self, threshold: Duration) -> Self {
+        self.slow_threshold = Some(threshold);
+        self
+    }
+
+    /// Reserves room for `capacity` people in every column registered by `Context::register_property`, including the
+    /// columns of plugins enabled later with `Context::enable`, for a simulation that knows its population size up
+    /// front. Creating up to `capacity` people then does not reallocate the columns.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Registers a hook called with the `Context` and the plugin just before each `plugin.init`, including for plugins
+    /// enabled later with `Context::enable`. Hooks run in the order they were registered.
+    pub fn before_each(mut self, hook: InitHook) -> Self {
+        self.before_each.push(hook);
+        self
+    }
+
+    /// Registers a hook called with the `Context` and the plugin just after each successful `plugin.init`, including
+    /// for plugins enabled later with `Context::enable`. Hooks run in the order they were registered.
+    pub fn after_each(mut self, hook: InitHook) -> Self {
+        self.after_each.push(hook);
+        self
+    }
+
+    /// Fails on, or drops and returns warnings about, any override naming a plugin or tag that is not registered,
+    /// according to the `unknown_plugins` policy. When failing and there are several, the alphabetically first plugin
+    /// is reported. Always fails on an override whose bare name is shared by several plugins.
+    fn check_overrides(&mut self) -> Result<Vec<Warning>, InitError> {
+        if let Some(error) = self.override_errors().into_iter().next() {
+            return Err(error);
+        }
+
+        let mut warnings = Vec::new();
+        if self.unknown_plugins == UnknownPlugins::Warn {
+            let (unknown_plugins, unknown_tags) = self.unknown_overrides();
+            for name in unknown_plugins {
+                self.overrides.remove(&name);
+                warnings.push(Warning::UnknownPlugin(name));
+            }
+            for tag in unknown_tags {
+                self.disabled_tags.remove(&tag);
+                warnings.push(Warning::UnknownTag(tag));
+            }
+        }
+        Ok(warnings)
+    }
+
+    /// Every override whose bare name is shared by several plugins, then, if the `unknown_plugins` policy is to fail,
+    /// every override naming a plugin or tag that is not registered. Each group is sorted alphabetically.
+    fn override_errors(&self) -> Vec<InitError> {
+        let mut ambiguous: Vec<&String> = self
+            .overrides
+            .keys()
+            .filter(|name| self.plugins.iter().filter(|plugin| plugin.is_named(name)).count() > 1)
+            .collect();
+        ambiguous.sort_unstable();
+        let mut errors: Vec<InitError> =
+            ambiguous.into_iter().map(|name| InitError::Config(ConfigError::AmbiguousName(name.clone()))).collect();
+
+        if self.unknown_plugins == UnknownPlugins::Error {
+            let (unknown_plugins, unknown_tags) = self.unknown_overrides();
+            errors.extend(unknown_plugins.into_iter().map(|name| InitError::Config(ConfigError::UnknownPlugin(name))));
+            errors.extend(unknown_tags.into_iter().map(|name| InitError::Config(ConfigError::UnknownTag(name))));
+        }
+        errors
+    }
+
+    /// The names of the plugins and the tags that overrides refer to but are not registered, each sorted.
+    fn unknown_overrides(&self) -> (Vec<String>, Vec<String>) {
+        let registered = || self.plugins.iter().chain(&self.excluded);
+        let mut unknown_plugins: Vec<String> = self
+            .overrides
+            .keys()
+            .filter(|name| !registered().any(|plugin| plugin.is_named(name)))
+            .cloned()
+            .collect();
+        unknown_plugins.sort_unstable();
+        let mut unknown_tags: Vec<String> = self
+            .disabled_tags
+            .union(&self.only_tags)
+            .filter(|tag| !registered().any(|plugin| plugin.tags.contains(&tag.as_str())))
+            .cloned()
+            .collect();
+        unknown_tags.sort_unstable();
+        (unknown_plugins, unknown_tags)
+    }
+
+    /// Fails on the alphabetically first `Unconditional` plugin that an override disables by name.
+    fn check_unconditional(&self) -> Result<(), InitError> {
+        first_error(self.unconditional_errors())
+    }
+
+    /// An error for every `Unconditional` plugin that an override disables by name, sorted by plugin name.
+    fn unconditional_errors(&self) -> Vec<InitError> {
+        let mut disabled: Vec<&'static str> = self
+            .plugins
+            .iter()
+            .filter(|plugin| plugin.default_state == DefaultState::Unconditional)
+            .filter(|plugin| self.override_for(plugin) == Some(false))
+            .map(|plugin| plugin.name)
+            .collect();
+        disabled.sort_unstable();
+        disabled.into_iter().map(|name| InitError::Config(ConfigError::CannotDisable(name))).collect()
+    }
+
+    /// Fails on the first `required` plugin that an override disables, by name or by tag. See `required_errors`.
+    fn check_required_overrides(&self) -> Result<(), InitError> {
+        first_error(self.required_errors())
+    }
+
+    /// An error for every `required` plugin that an override disables, by name or by tag, sorted by plugin name, the
+    /// same error `Context::disable` fails with for it.
+    fn required_errors(&self) -> Vec<InitError> {
+        let mut disabled: Vec<&'static str> = self
+            .plugins
+            .iter()
+            .filter(|plugin| plugin.required)
+            .filter(|plugin| matches!(self.skip_reason(plugin), Some(SkipReason::Override | SkipReason::Tag(_))))
+            .map(|plugin| plugin.name)
+            .collect();
+        disabled.sort_unstable();
+        disabled.into_iter().map(|name| InitError::Config(ConfigError::DisableRequired(name))).collect()
+    }
+
+    /// The override for `plugin`, by its qualified name, or else by its bare name, or else by one of its aliases.
+    fn override_for(&self, plugin: &Plugin) -> Option<bool> {
+        self.overrides
+            .get(&plugin.qualified_name())
+            .or_else(|| self.overrides.get(plugin.name))
+            .or_else(|| {
+                self.overrides
+                    .iter()
+                    .find(|(name, _)| plugin.is_named(name))
+                    .map(|(_, enabled)| enabled)
+            })
+            .copied()
+    }
+
+    /// Turns an `ConfigError::MissingDependency` on a plugin that `only_tags` excluded into an
+    /// `ConfigError::ExcludedDependency`, and returns any other error unchanged.
+    fn explain_exclusion(&self, error: InitError) -> InitError {
+        let is_excluded = |name: &str| self.excluded.iter().any(|plugin| plugin.is_named(name));
+        match error {
+            InitError::Config(ConfigError::MissingDependency { plugin, needs }) if is_excluded(needs) => {
+                InitError::Config(ConfigError::ExcludedDependency { plugin, needs })
+            }
+            error => error,
+        }
+    }
+
+    /// Whether `plugin` is enabled once overrides are taken into account.
+    fn is_enabled(&self, plugin: &Plugin) -> bool {
+        self.skip_reason(plugin).is_none()
+    }
+
+    /// Why `plugin` is disabled once overrides are taken into account, or `None` if it is enabled. `Unconditional`
+    /// plugins are always enabled. Otherwise a plugin override takes precedence over a tag override, which takes
+    /// precedence over the plugin's default: its static `enabled` flag for `OptOut` plugins and disabled for `OptIn`
+    /// plugins.
+    fn skip_reason(&self, plugin: &Plugin) -> Option<SkipReason> {
+        if plugin.default_state == DefaultState::Unconditional {
+            return None;
+        }
+        if let Some(enabled) = self.override_for(plugin) {
+            return if enabled { None } else { Some(SkipReason::Override) };
+        }
+        if let Some(&tag) = plugin.tags.iter().find(|&&tag| self.disabled_tags.contains(tag)) {
+            return Some(SkipReason::Tag(tag));
+        }
+        default_skip_reason(plugin)
+    }
+
+    /// The names of the plugins `build` would enable with the current overrides, in the same order as
+    /// `Context::names`, without initializing any of them. This takes default states, tag overrides and `only_tags`
+    /// into account, and leaves out plugins that depend, directly or not, on a plugin that is disabled or missing.
+    /// `build` fails on such plugins instead of leaving them out, so the preview only matches `build` when
+    /// `validate_all` succeeds.
+    pub fn preview_enabled(&self) -> Vec<&'static str> {
+        let mut enabled: Vec<&'static Plugin> =
+            self.plugins.iter().copied().filter(|plugin| self.is_enabled(plugin)).collect();
+        // Leaving out a plugin can break its dependents in turn, so repeat until nothing more is left out.
+        loop {
+            let count = enabled.len();
+            let kept: Vec<&'static Plugin> = enabled
+                .iter()
+                .copied()
+                .filter(|plugin| {
+                    plugin.dependencies.iter().all(|name| enabled.iter().any(|other| other.is_named(name)))
+                })
+                .collect();
+            enabled = kept;
+            if enabled.len() == count {
+                break;
+            }
+        }
+        enabled.sort_unstable_by_key(|plugin| plugin.sort_key());
+        enabled.into_iter().map(|plugin| plugin.name).collect()
+    }
+
+    /// Builds the `Context` saved in a checkpoint by `Context::save`, with the people, columns and random number
+    /// generator it had. The checkpoint decides which plugins are enabled, overriding any `enable` or `disable`, but
+    /// the other configuration, such as hooks and `set_default`, comes from the builder.
+    ///
+    /// Fails with `RuntimeError::InvalidCheckpoint` if the checkpoint cannot be read or parsed, or does not match the
+    /// plugins, for example because it enables a plugin that is not registered. Otherwise fails like `build`.
+    #[cfg(feature = "std")]
+    pub fn load<R: Read>(mut self, mut reader: R) -> Result<Context, InitError> {
+        let mut text = String::new();
+        reader
+            .read_to_string(&mut text)
+            .map_err(|error| InitError::Runtime(RuntimeError::InvalidCheckpoint(error.to_string())))?;
+        let checkpoint = checkpoint::parse(&text)?;
+
+        if let Some(name) = checkpoint
+            .plugins
+            .iter()
+            .find(|name| !self.plugins.iter().any(|plugin| plugin.qualified_name() == **name))
+        {
+            let message = format!("plugin `{}` is not registered", name);
+            return Err(InitError::Runtime(RuntimeError::InvalidCheckpoint(message)));
+        }
+        self.overrides.clear();
+        self.disabled_tags.clear();
+        for plugin in &self.plugins {
+            let name = plugin.qualified_name();
+            let enabled = checkpoint.plugins.contains(&name);
+            self.overrides.insert(name, enabled);
+        }
+
+        let mut context = self.build()?;
+        checkpoint::restore(&mut context, checkpoint)?;
+        Ok(context)
+    }
+
+    /// Checks the configured plugin set like `build`, but reports every problem found instead of only the first, in the
+    /// order `build` checks for them: duplicate names, plugins both `required` and not `enabled`, invalid overrides,
+    /// disabled `Unconditional` plugins, disabled `required` plugins, conflicts, and then unsatisfied dependencies,
+    /// including those too old, and dependency cycles. No plugin is initialized, so no `constructor` runs and no column
+    /// is registered, which makes this a cheap way to lint a configuration, for example in CI. Problems `build` only
+    /// finds during initialization are not reported.
+    pub fn validate_all(&self) -> Result<(), Vec<InitError>> {
+        let mut errors = duplicate_names(self.plugins.iter().copied());
+        errors.extend(required_but_disabled(&self.plugins));
+        errors.extend(self.override_errors());
+        errors.extend(self.unconditional_errors());
+        errors.extend(self.required_errors());
+
+        let enabled: Vec<&'static Plugin> =
+            self.plugins.iter().copied().filter(|plugin| self.is_enabled(plugin)).collect();
+        errors.extend(conflicts(&enabled));
+        errors.extend(dependency_errors(&self.plugins, enabled).into_iter().map(|error| self.explain_exclusion(error)));
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Validates the configured plugin set and initializes the enabled plugins in dependency order.
+    ///
+    /// Fails if two plugins share a name in the same namespace, if a plugin is declared both `required` and not
+    /// `enabled`, if an override names a plugin or tag that is not registered (unless `unknown_plugins` says
+    /// otherwise), if an override disables an `Unconditional` or `required` plugin, if an enabled plugin depends on a
+    /// plugin that is missing or disabled, if the dependencies form a cycle, or if two enabled plugins conflict. No
+    /// plugin is initialized if validation fails. Fails after initialization if a plugin with a `batch_initializer`
+    /// does not register exactly one column. With the `std` feature, fails with `ConfigError::ReentrantBuild` if called
+    /// from a plugin's `constructor`, rather than initializing plugins in the middle of initializing another.
+    pub fn build(mut self) -> Result<Context, InitError> {
+        #[cfg(feature = "std")]
+        if let Some(plugin) = INITIALIZING.get() {
+            return Err(InitError::Config(ConfigError::ReentrantBuild(plugin)));
+        }
+        first_error(duplicate_names(self.plugins.iter().copied()))?;
+        check_required_enabled(&self.plugins)?;
+        let mut warnings = self.check_overrides()?;
+        warnings.append(&mut self.env_warnings);
+        self.check_unconditional()?;
+        self.check_required_overrides()?;
+
+        let plugins = self.plugins.clone();
+        let skip_reasons: Vec<Option<SkipReason>> = plugins.iter().map(|plugin| self.skip_reason(plugin)).collect();
+        let enabled: Vec<&'static Plugin> = plugins
+            .iter()
+            .zip(&skip_reasons)
+            .filter(|(_, reason)| reason.is_none())
+            .map(|(&plugin, _)| plugin)
+            .collect();
+        check_conflicts(&enabled)?;
+        warnings.extend(optional_dependency_warnings(&plugins, &enabled));
+        let order = dependency_order(&plugins, enabled).map_err(|error| self.explain_exclusion(error))?;
+
+        let mut context = Context::default();
+        context.warnings = warnings;
+        context.before_each = self.before_each;
+        context.after_each = self.after_each;
+        context.rng = Rng::new(self.seed);
+        context.capacity = self.capacity;
+        #[cfg(feature = "std")]
+        {
+            context.slow_threshold = self.slow_threshold;
+        }
+        let mut owned_columns: BTreeMap<(&'static str, &'static str), Vec<ColumnKey>> = BTreeMap::new();
+        for (index, &plugin) in order.iter().enumerate() {
+            match context.init_plugin(plugin) {
+                Ok(owned) => {
+                    owned_columns.insert(plugin.sort_key(), owned);
+                }
+                Err(error) => {
+                    // The `Context` has no instances yet, so tear down the plugins initialized so far here, like
+                    // `Context::shutdown` would, rather than on drop.
+                    context.shut_down = true;
+                    for plugin in order[..index].iter().rev() {
+                        if let Some(teardown) = plugin.teardown {
+                            teardown(&mut context);
+                        }
+                    }
+                    return Err(error);
+                }
+            }
+        }
+        context.plugins.sort_unstable_by_key(|plugin| plugin.sort_key());
+        context.active = order;
+
+        context.instances = plugins
+            .iter()
+            .copied()
+            .zip(skip_reasons)
+            .map(|(plugin, skip_reason)| {
+                let mut instance = PluginInstance {
+                    plugin,
+                    enabled: owned_columns.contains_key(&plugin.sort_key()),
+                    columns: Vec::new(),
+                    property_columns: Vec::new(),
+                    skip_reason,
+                };
+                instance.record_columns(&owned_columns.remove(&plugin.sort_key()).unwrap_or_default());
+                instance
+            })
+            .collect();
+        context.instances.sort_unstable_by_key(|instance| instance.plugin.sort_key());
+        context.metrics.seen = self.plugins.len();
+        context.metrics.enabled = context.active.len();
+        context.metrics.skipped = self.plugins.len() - context.active.len();
+
+        for (name, type_id, type_name, default) in self.defaults {
+            let plugin = context.instances[context.resolve_instance(&name)?].plugin;
+            if context.get_plugin(&name).is_none() {
+                continue;
+            }
+            let key = (plugin.sort_key(), type_id);
+            if !context.columns.contains_key(&key) {
+                return Err(InitError::Runtime(RuntimeError::TypeMismatch { plugin: plugin.name, type_name }));
+            }
+            context.defaults.insert(key, default);
+        }
+
+        Ok(context)
+    }
+}
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    /// The plugin whose `init` is running on this thread, if any, so that a constructor cannot build a `Context`.
+    static INITIALIZING: Cell<Option<&'static str>> = const { Cell::new(None) };
+}
+
+/// Marks `plugin` as initializing on this thread until dropped, even by a panic, for `ContextBuilder::build` to
+/// refuse building a `Context` from within a constructor, which would initialize the plugins again in the middle of
+/// initializing them. Only kept with the `std` feature, for its thread locals.
+#[cfg(feature = "std")]
+pub(crate) struct InitGuard(Option<&'static str>);
+
+#[cfg(feature = "std")]
+impl InitGuard {
+    pub(crate) fn enter(plugin: &'static str) -> Self {
+        InitGuard(INITIALIZING.replace(Some(plugin)))
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for InitGuard {
+    fn drop(&mut self) {
+        INITIALIZING.set(self.0);
+    }
+}
+
+/// Why `plugin` is disabled when no override applies to it, or `None` if it is enabled by default.
+pub(crate) fn default_skip_reason(plugin: &Plugin) -> Option<SkipReason> {
+    match plugin.default_state {
+        DefaultState::Unconditional => None,
+        DefaultState::OptOut if plugin.enabled => None,
+        DefaultState::OptOut => Some(SkipReason::Default),
+        DefaultState::OptIn => Some(SkipReason::OptIn),
+    }
+}
+
+/// `name` as it appears in the environment variables read by `ContextBuilder::apply_env`: in upper case, with every
+/// character other than an ASCII letter or digit replaced by `_`.
+fn env_name(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' }).collect()
+}
+
+/// The first of `errors`, if any, as an `Err`.
+fn first_error(errors: Vec<InitError>) -> Result<(), InitError> {
+    match errors.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// Fails on the first plugin that is declared `required` but not `enabled`, which is contradictory.
+fn check_required_enabled(plugins: &[&'static Plugin]) -> Result<(), InitError> {
+    first_error(required_but_disabled(plugins))
+}
+
+/// An error for every plugin that is declared `required` but not `enabled`, in slice order.
+fn required_but_disabled(plugins: &[&'static Plugin]) -> Vec<InitError> {
+    plugins
+        .iter()
+        .filter(|plugin| plugin.required && !plugin.enabled)
+        .map(|plugin| InitError::Config(ConfigError::RequiredButDisabled(plugin.name)))
+        .collect()
+}
+
+/// Fails on a name shared by more than one plugin in the same namespace, counting both their names and their `aliases`.
+/// If there are several, the alphabetically first is reported. Every crate that declares plugins with
+/// `register_plugin!` runs this on `PLUGINS` in a generated test, so that a collision fails `cargo test` rather than
+/// only the eventual `Context::new`.
+pub fn check_unique_names(plugins: &[Plugin]) -> Result<(), InitError> {
+    first_error(duplicate_names(plugins))
+}
+
+/// An error for every name shared by more than one plugin in the same namespace, sorted by name and then namespace.
+pub(crate) fn duplicate_names<'a>(plugins: impl IntoIterator<Item = &'a Plugin>) -> Vec<InitError> {
+    let mut origins: BTreeMap<(&'static str, &'static str), Vec<&'static str>> = BTreeMap::new();
+    for plugin in plugins {
+        let names: BTreeSet<&'static str> = plugin.aliases.iter().copied().chain([plugin.name]).collect();
+        for name in names {
+            origins.entry((name, plugin.namespace)).or_default().push(plugin.origin);
+        }
+    }
+
+    let mut duplicates: Vec<((&'static str, &'static str), Vec<&'static str>)> =
+        origins.into_iter().filter(|(_, origins)| origins.len() > 1).collect();
+    duplicates.sort_unstable();
+    duplicates
+        .into_iter()
+        .map(|((name, namespace), mut origins)| {
+            origins.sort_unstable();
+            InitError::Config(ConfigError::DuplicateName { namespace, name, count: origins.len(), origins })
+        })
+        .collect()
+}
+
+/// A warning for each optional dependency of an `enabled` plugin that is among `plugins` but not enabled, sorted by
+/// plugin and then dependency. Optional dependencies that are not registered at all are expected and not reported.
+pub(crate) fn optional_dependency_warnings(plugins: &[&'static Plugin], enabled: &[&'static Plugin]) -> Vec<Warning> {
+    let is_enabled = |plugin: &Plugin| enabled.iter().any(|other| other.sort_key() == plugin.sort_key());
+    let mut dependents: Vec<&'static Plugin> = enabled.to_vec();
+    dependents.sort_unstable_by_key(|plugin| plugin.sort_key());
+
+    let mut warnings = Vec::new();
+    for plugin in dependents {
+        let mut disabled: Vec<&'static str> = plugin
+            .optional_dependencies
+            .iter()
+            .copied()
+            .filter(|&dependency| {
+                let named: Vec<&&'static Plugin> = plugins.iter().filter(|other| other.is_named(dependency)).collect();
+                !named.is_empty() && !named.iter().any(|other| is_enabled(other))
+            })
+            .collect();
+        disabled.sort_unstable();
+        warnings.extend(
+            disabled
+                .into_iter()
+                .map(|dependency| Warning::OptionalDependencyDisabled { plugin: plugin.name, dependency }),
+        );
+    }
+    warnings
+}
+
+/// Fails if two of the `enabled` plugins conflict, because either lists the other in `conflicts_with`. If there are
+/// several such pairs, the alphabetically first is reported, each pair in alphabetical order.
+pub(crate) fn check_conflicts(enabled: &[&'static Plugin]) -> Result<(), InitError> {
+    first_error(conflicts(enabled))
+}
+
+/// An error for every pair of the `enabled` plugins that conflict, each pair in alphabetical order, sorted.
+fn conflicts(enabled: &[&'static Plugin]) -> Vec<InitError> {
+    let pairs: BTreeSet<(&'static str, &'static str)> = enabled
+        .iter()
+        .flat_map(|plugin| {
+            plugin.conflicts_with.iter().flat_map(move |&name| {
+                enabled
+                    .iter()
+                    .filter(move |other| other.is_named(name) && other.sort_key() != plugin.sort_key())
+                    .map(move |other| (plugin.name.min(other.name), plugin.name.max(other.name)))
+            })
+        })
+        .collect();
+    pairs.into_iter().map(|(plugin, other)| InitError::Config(ConfigError::Conflict { plugin, other })).collect()
+}
+
+/// Computes the order in which the `enabled` subset of `plugins` must be initialized so that every plugin comes after
+/// its dependencies and its enabled optional dependencies. Among plugins not ordered relative to each other by a
+/// dependency, lower `priority` goes first, then the alphabetically first name and namespace, so the result does not
+/// depend on the linker-determined slice order. A dependency is named the same way as in `Context::get_plugin`. Fails
+/// if a dependency does not meet the minimum in `dependency_versions`.
+pub(crate) fn dependency_order(
+    plugins: &[&'static Plugin],
+    enabled: Vec<&'static Plugin>,
+) -> Result<Vec<&'static Plugin>, InitError> {
+    let (enabled, requires, errors) = requirements(plugins, enabled);
+    first_error(errors)?;
+    topological_order(&enabled, &requires)
+}
+
+/// Every error `dependency_order` could fail with: each unsatisfied dependency, then a dependency cycle among the
+/// dependencies that are satisfied.
+fn dependency_errors(plugins: &[&'static Plugin], enabled: Vec<&'static Plugin>) -> Vec<InitError> {
+    let (enabled, requires, mut errors) = requirements(plugins, enabled);
+    errors.extend(topological_order(&enabled, &requires).err());
+    errors
+}
+
+/// Sorts the `enabled` plugins by priority and then name and namespace, and lists for each the indices of the plugins
+/// that must be initialized before it: its hard dependencies, plus those of its optional dependencies that are
+/// enabled. Also returns an error for every dependency that is missing, disabled, ambiguous or too old, in plugin
+/// order; such dependencies are left out of the lists. Those are followed by an error for every plugin that only
+/// reaches a disabled plugin through its enabled dependencies, naming the chain.
+fn requirements(
+    plugins: &[&'static Plugin],
+    mut enabled: Vec<&'static Plugin>,
+) -> (Vec<&'static Plugin>, Vec<Vec<usize>>, Vec<InitError>) {
+    enabled.sort_by_key(|plugin| (plugin.priority, plugin.sort_key()));
+    let index_of: BTreeMap<(&'static str, &'static str), usize> = enabled
+        .iter()
+        .enumerate()
+        .map(|(index, plugin)| (plugin.sort_key(), index))
+        .collect();
+    let named = |name: &str| -> Vec<&'static Plugin> {
+        plugins.iter().copied().filter(|other| other.is_named(name)).collect()
+    };
+
+    let mut requires: Vec<Vec<usize>> = vec![Vec::new(); enabled.len()];
+    // The enabled hard dependencies of each plugin, and the first of its hard dependencies that is disabled, if any.
+    let mut hard: Vec<Vec<usize>> = vec![Vec::new(); enabled.len()];
+    let mut disabled: Vec<Option<&'static str>> = vec![None; enabled.len()];
+    let mut errors = Vec::new();
+    for (index, plugin) in enabled.iter().enumerate() {
+        for &dependency in plugin.dependencies {
+            match named(dependency)[..] {
+                [other] => match index_of.get(&other.sort_key()) {
+                    Some(&dependency_index) => {
+                        hard[index].push(dependency_index);
+                        match check_version(plugin, dependency, other) {
+                            Ok(()) => requires[index].push(dependency_index),
+                            Err(error) => errors.push(error),
+                        }
+                    }
+                    None => {
+                        disabled[index].get_or_insert(dependency);
+                        errors.push(ConfigError::DisabledDependency { plugin: plugin.name, needs: dependency }.into())
+                    }
+                },
+                [] => errors.push(ConfigError::MissingDependency { plugin: plugin.name, needs: dependency }.into()),
+                _ => errors.push(ConfigError::AmbiguousDependency { plugin: plugin.name, needs: dependency }.into()),
+            }
+        }
+        for &dependency in plugin.optional_dependencies {
+            match named(dependency)[..] {
+                [other] => {
+                    if let Some(&dependency_index) = index_of.get(&other.sort_key()) {
+                        match check_version(plugin, dependency, other) {
+                            Ok(()) => requires[index].push(dependency_index),
+                            Err(error) => errors.push(error),
+                        }
+                    }
+                }
+                [] => {}
+                _ => errors.push(ConfigError::AmbiguousDependency { plugin: plugin.name, needs: dependency }.into()),
+            }
+        }
+    }
+    for (index, plugin) in enabled.iter().enumerate() {
+        if disabled[index].is_none()
+            && let Some(chain) = disabled_chain(&enabled, &hard, &disabled, index)
+        {
+            errors.push(ConfigError::DisabledDependencyChain { plugin: plugin.name, chain }.into());
+        }
+    }
+    (enabled, requires, errors)
+}
+
+/// The names along a shortest path of `hard` dependencies from the plugin at `start` to a plugin that depends on a
+/// disabled plugin, followed by the name of the disabled plugin, or `None` if there is no such path.
+fn disabled_chain(
+    enabled: &[&'static Plugin],
+    hard: &[Vec<usize>],
+    disabled: &[Option<&'static str>],
+    start: usize,
+) -> Option<Vec<&'static str>> {
+    // Breadth-first, recording how each plugin was reached so that the path can be walked back.
+    let mut previous: Vec<Option<usize>> = vec![None; enabled.len()];
+    let mut seen = BTreeSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(index) = queue.pop_front() {
+        if let Some(needs) = disabled[index] {
+            let mut chain = vec![needs];
+            let mut current = Some(index);
+            while let Some(step) = current {
+                chain.push(enabled[step].name);
+                current = previous[step];
+            }
+            chain.reverse();
+            return Some(chain);
+        }
+        for &next in &hard[index] {
+            if seen.insert(next) {
+                previous[next] = Some(index);
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+/// Orders the `enabled` plugins so that each comes after the plugins it `requires`, keeping the given order where the
+/// requirements allow. Fails if the requirements form a cycle.
+fn topological_order(
+    enabled: &[&'static Plugin],
+    requires: &[Vec<usize>],
+) -> Result<Vec<&'static Plugin>, InitError> {
+    // `dependents[i]` lists the plugins waiting on plugin `i`; `pending[i]` counts the plugins that plugin `i` waits on
+    // that have not been initialized yet.
+    let mut dependents: Vec<Vec<usize>> = vec![Vec::new(); enabled.len()];
+    let mut pending: Vec<usize> = vec![0; enabled.len()];
+    for (index, required) in requires.iter().enumerate() {
+        for &dependency_index in required {
+            dependents[dependency_index].push(index);
+            pending[index] += 1;
+        }
+    }
+
+    // Kahn's algorithm, always taking the earliest ready plugin in `enabled` order.
+    let mut ready: BTreeSet<usize> = (0..enabled.len()).filter(|&index| pending[index] == 0).collect();
+    let mut order = Vec::with_capacity(enabled.len());
+    while let Some(index) = ready.pop_first() {
+        order.push(enabled[index]);
+        for &dependent in &dependents[index] {
+            pending[dependent] -= 1;
+            if pending[dependent] == 0 {
+                ready.insert(dependent);
+            }
+        }
+    }
+
+    if order.len() < enabled.len() {
+        return Err(InitError::Config(ConfigError::DependencyCycle(find_cycle(enabled, requires, &pending))));
+    }
+
+    Ok(order)
+}
+
+/// Fails if `plugin` requires a minimum version of `dependency`, the name by which it refers to `other`, that
+/// `other` does not meet.
+fn check_version(plugin: &'static Plugin, dependency: &'static str, other: &'static Plugin) -> Result<(), InitError> {
+    match plugin.dependency_versions.iter().find(|&&(name, _)| name == dependency) {
+        Some(&(_, required)) if other.version < required => Err(InitError::Config(ConfigError::DependencyTooOld {
+            plugin: plugin.name,
+            needs: dependency,
+            found: other.version,
+            required,
+        })),
+        _ => Ok(()),
+    }
+}
+
+/// Given the plugins left over by Kahn's algorithm, which all have a dependency that was never initialized, returns
+/// one dependency cycle among them as a path that starts and ends with the same plugin, such as
+/// `["Age", "Weight", "Age"]` when `"Age"` depends on `"Weight"` and `"Weight"` on `"Age"`.
+fn find_cycle(enabled: &[&'static Plugin], requires: &[Vec<usize>], pending: &[usize]) -> Vec<&'static str> {
+    // Every stuck plugin has a stuck dependency, so following those from any stuck plugin must revisit some plugin.
+    let stuck_dependency = |index: usize| -> usize {
+        requires[index]
+            .iter()
+            .copied()
+            .find(|&dependency_index| pending[dependency_index] > 0)
+            .expect("a stuck plugin has a stuck dependency")
+    };
+
+    let start = (0..enabled.len()).find(|&index| pending[index] > 0).expect("some plugin is stuck");
+    let mut path = vec![start];
+    let mut current = stuck_dependency(start);
+    while !path.contains(&current) {
+        path.push(current);
+        current = stuck_dependency(current);
+    }
+
+    // The walk may have entered the cycle partway along the path.
+    let cycle_start = path.iter().position(|&index| index == current).unwrap();
+    let mut cycle: Vec<&'static str> = path[cycle_start..].iter().map(|&index| enabled[index].name).collect();
+    cycle.push(enabled[current].name);
+    cycle
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::{
+        ConfigError, Context, ContextBuilder, DefaultState, InitError, Plugin, RuntimeError, SkipReason, UnknownPlugins,
+        Warning,
+    };
+
+    // Unlike the built-in `Age`, not `required`, so it may be disabled.
+    static HEIGHT_PLUGINS: [Plugin; 1] = [Plugin::builder("Height")
+        .description("Height of the person")
+        .constructor(|context| context.register_property::<u32>("Height"))
+        .build()];
+
+    #[test]
+    fn disabling_a_plugin_skips_its_init() {
+        let context = ContextBuilder::with_plugins(&HEIGHT_PLUGINS).disable("Height").build().unwrap();
+        assert!(context.plugins.is_empty());
+    }
+
+    #[test]
+    fn disabling_a_required_plugin_is_rejected() {
+        let required = InitError::Config(ConfigError::DisableRequired("Age"));
+        assert_eq!(Context::builder().disable("Age").build().err(), Some(required.clone()));
+        assert_eq!(Context::builder().disable_tag("demographic").build().err(), Some(required.clone()));
+        assert_eq!(Context::builder().disable("Age").validate_all(), Err(vec![required.clone()]));
+        assert_eq!(Context::new().disable("Age"), Err(required));
+    }
+
+    #[test]
+    fn later_override_wins() {
+        let context = Context::builder().disable("Age").enable("Age").build().unwrap();
+        assert_eq!(context.names(), vec!["Age"]);
+    }
+
+    #[test]
+    fn disabled_plugin_keeps_its_instance() {
+        let context = ContextBuilder::with_plugins(&HEIGHT_PLUGINS).disable("Height").build().unwrap();
+        let instance = context.instance("Height").unwrap();
+        assert!(!instance.enabled);
+        assert!(instance.columns.is_empty());
+        assert_eq!(instance.plugin.description, "Height of the person");
+        assert_eq!(context.instances().len(), 1);
+
+        let context = Context::with_plugins(&HEIGHT_PLUGINS);
+        let instance = context.instance("Height").unwrap();
+        assert!(instance.enabled);
+        assert_eq!(instance.columns, vec![std::any::TypeId::of::<u32>()]);
+    }
+
+    #[test]
+    fn unknown_override_is_reported() {
+        let error = Context::builder().disable("Agee").build().err().unwrap();
+        assert_eq!(error, InitError::Config(ConfigError::UnknownPlugin("Agee".to_string())));
+    }
+
+    #[test]
+    fn unknown_override_can_be_ignored() {
+        let context = Context::builder()
+            .disable("Agee")
+            .disable_tag("demographics")
+            .unknown_plugins(UnknownPlugins::Warn)
+            .build()
+            .unwrap();
+        assert_eq!(context.names(), vec!["Age"]);
+        assert_eq!(
+            context.warnings(),
+            [Warning::UnknownPlugin("Agee".to_string()), Warning::UnknownTag("demographics".to_string())]
+        );
+    }
+
+    static ENV_PLUGINS: [Plugin; 2] = [
+        Plugin{ name: "Body-Mass", enabled: false, ..Plugin::DEFAULT },
+        Plugin{ name: "Weight", ..Plugin::DEFAULT },
+    ];
+
+    #[test]
+    fn apply_env_toggles_plugins() {
+        let variables = [
+            ("ENV_TEST_PLUGIN_WEIGHT", "0"),
+            ("ENV_TEST_PLUGIN_BODY_MASS", "1"),
+            ("ENV_TEST_PLUGIN_HEIGHT", "0"),
+            ("ENV_TEST_PLUGIN_AGE", "off"),
+            ("OTHER_PLUGIN_WEIGHT", "1"),
+        ]
+        .map(|(variable, value)| (variable.to_string(), value.to_string()));
+        let context = ContextBuilder::with_plugins(&ENV_PLUGINS).apply_env_vars("ENV_TEST", variables).build().unwrap();
+        assert_eq!(context.names(), vec!["Body-Mass"]);
+        assert_eq!(context.instance("Weight").unwrap().skip_reason, Some(SkipReason::Override));
+        assert_eq!(
+            context.warnings(),
+            [
+                Warning::InvalidEnvValue { variable: "ENV_TEST_PLUGIN_AGE".to_string(), value: "off".to_string() },
+                Warning::UnknownEnvPlugin("ENV_TEST_PLUGIN_HEIGHT".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn unreadable_config_file_is_reported() {
+        let path = std::env::temp_dir().join("initialization-missing-config.toml");
+        let error = ContextBuilder::from_config(&path).err().unwrap();
+        assert!(matches!(error, InitError::Config(ConfigError::InvalidConfig { .. })));
+    }
+
+    static OPTIONAL_PLUGINS: [Plugin; 2] = [
+        Plugin{
+            name: "Height",
+            description: "Height of the person",
+            enabled: false,
+            constructor: |context| {
+                context.register_plugin(&OPTIONAL_PLUGINS[0]);
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Bmi",
+            description: "Body mass index, computed from the height",
+            dependencies: &["Height"],
+            constructor: |context| {
+                context.register_plugin(&OPTIONAL_PLUGINS[1]);
+            },
+            ..Plugin::DEFAULT
+        },
+    ];
+
+    #[test]
+    fn enable_overrides_static_flag() {
+        let context = ContextBuilder::with_plugins(&OPTIONAL_PLUGINS).enable("Height").build().unwrap();
+        assert_eq!(context.names(), vec!["Bmi", "Height"]);
+    }
+
+    #[test]
+    fn disabling_a_dependency_is_reported() {
+        let error = ContextBuilder::with_plugins(&OPTIONAL_PLUGINS)
+            .enable("Height")
+            .disable("Height")
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(error, InitError::Config(ConfigError::DisabledDependency { plugin: "Bmi", needs: "Height" }));
+    }
+
+    static INITIALIZED: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+    #[test]
+    fn hooks_observe_init_order() {
+        let context = ContextBuilder::with_plugins(&OPTIONAL_PLUGINS)
+            .enable("Height")
+            .before_each(|context, plugin| assert!(context.get_plugin(plugin.name).is_none()))
+            .after_each(|context, plugin| {
+                assert!(context.get_plugin(plugin.name).is_some());
+                INITIALIZED.lock().unwrap().push(plugin.name);
+            })
+            .build()
+            .unwrap();
+
+        let init_order: Vec<&str> = context.active.iter().map(|plugin| plugin.name).collect();
+        assert_eq!(*INITIALIZED.lock().unwrap(), init_order);
+        assert_eq!(init_order, vec!["Height", "Bmi"]);
+    }
+
+    static TAGGED_PLUGINS: [Plugin; 3] = [
+        Plugin{
+            name: "Income",
+            description: "Yearly income of the person",
+            tags: &["socioeconomic"],
+            constructor: |context| {
+                context.register_plugin(&TAGGED_PLUGINS[0]);
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Education",
+            description: "Highest level of education of the person",
+            tags: &["socioeconomic", "schooling"],
+            constructor: |context| {
+                context.register_plugin(&TAGGED_PLUGINS[1]);
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Sex",
+            description: "Sex of the person",
+            tags: &["demographic"],
+            constructor: |context| {
+                context.register_plugin(&TAGGED_PLUGINS[2]);
+            },
+            ..Plugin::DEFAULT
+        },
+    ];
+
+    #[test]
+    fn disable_tag_disables_the_whole_group() {
+        let context = ContextBuilder::with_plugins(&TAGGED_PLUGINS).disable_tag("socioeconomic").build().unwrap();
+        assert_eq!(context.names(), vec!["Sex"]);
+    }
+
+    #[test]
+    fn enable_by_name_wins_over_disable_tag() {
+        let context = ContextBuilder::with_plugins(&TAGGED_PLUGINS)
+            .disable_tag("socioeconomic")
+            .enable("Income")
+            .build()
+            .unwrap();
+        assert_eq!(context.names(), vec!["Income", "Sex"]);
+    }
+
+    #[test]
+    fn plugins_with_tag_lists_the_group() {
+        let context = Context::with_plugins(&TAGGED_PLUGINS);
+        let names: Vec<&str> = context.plugins_with_tag("socioeconomic").iter().map(|plugin| plugin.name).collect();
+        assert_eq!(names, vec!["Education", "Income"]);
+        assert!(context.plugins_with_tag("anthropometric").is_empty());
+    }
+
+    #[test]
+    fn unknown_tag_is_reported() {
+        let error = ContextBuilder::with_plugins(&TAGGED_PLUGINS).disable_tag("socio").build().err().unwrap();
+        assert_eq!(error, InitError::Config(ConfigError::UnknownTag("socio".to_string())));
+    }
+
+    #[test]
+    fn only_tags_leaves_out_the_other_plugins() {
+        let context = ContextBuilder::with_plugins(&TAGGED_PLUGINS).only_tags(&["demographic"]).build().unwrap();
+        assert_eq!(context.names(), vec!["Sex"]);
+        assert_eq!(context.plugin_count(), 1);
+        assert!(context.get_plugin("Income").is_none());
+
+        let context = ContextBuilder::with_plugins(&TAGGED_PLUGINS)
+            .only_tags(&["socioeconomic", "demographic"])
+            .only_tags(&["schooling"])
+            .disable("Income")
+            .build()
+            .unwrap();
+        assert_eq!(context.names(), vec!["Education"]);
+
+        let error = ContextBuilder::with_plugins(&TAGGED_PLUGINS).only_tags(&["socio"]).build().err().unwrap();
+        assert_eq!(error, InitError::Config(ConfigError::UnknownTag("socio".to_string())));
+    }
+
+    // "Earnings" depends on "Schooling", which carries another tag.
+    static CROSS_TAG_PLUGINS: [Plugin; 2] = [
+        Plugin::builder("Schooling").tags(&["schooling"]).build(),
+        Plugin::builder("Earnings").tags(&["socioeconomic"]).dependencies(&["Schooling"]).build(),
+    ];
+
+    #[test]
+    fn only_tags_rejects_a_dependency_on_an_excluded_plugin() {
+        let builder = || ContextBuilder::with_plugins(&CROSS_TAG_PLUGINS).only_tags(&["socioeconomic"]);
+        let excluded = InitError::Config(ConfigError::ExcludedDependency { plugin: "Earnings", needs: "Schooling" });
+        assert_eq!(builder().build().err(), Some(excluded.clone()));
+        assert_eq!(builder().validate_all(), Err(vec![excluded]));
+        assert_eq!(builder().disable("Earnings").build().unwrap().names(), Vec::<&str>::new());
+    }
+
+    static SLOW_PLUGINS: [Plugin; 2] = [
+        Plugin::builder("Quick").build(),
+        Plugin::builder("Sluggish").constructor(|_context| thread::sleep(Duration::from_millis(20))).build(),
+    ];
+
+    #[test]
+    fn warn_slow_names_plugins_over_the_threshold() {
+        let threshold = Duration::from_millis(10);
+        let context = ContextBuilder::with_plugins(&SLOW_PLUGINS).warn_slow(threshold).build().unwrap();
+        match context.warnings() {
+            [Warning::SlowInit { plugin: "Sluggish", duration, threshold: reported }] => {
+                assert!(*duration >= Duration::from_millis(20));
+                assert_eq!(*reported, threshold);
+            }
+            warnings => panic!("expected one slow-init warning, got {:?}", warnings),
+        }
+        assert!(context.init_report().contains("plugin `Sluggish` took "));
+
+        let context = ContextBuilder::with_plugins(&SLOW_PLUGINS).build().unwrap();
+        assert!(context.warnings().is_empty());
+    }
+
+    // "Bmi" refines its value using "Height" when it is present, but works without it.
+    static OPTIONAL_DEPENDENCY_PLUGINS: [Plugin; 2] = [
+        Plugin{
+            name: "Bmi",
+            description: "Body mass index",
+            optional_dependencies: &["Height", "Waist"],
+            priority: -1,
+            constructor: |context| {
+                context.register_plugin(&OPTIONAL_DEPENDENCY_PLUGINS[0]);
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Height",
+            description: "Height of the person",
+            constructor: |context| {
+                context.register_plugin(&OPTIONAL_DEPENDENCY_PLUGINS[1]);
+            },
+            ..Plugin::DEFAULT
+        },
+    ];
+
+    #[test]
+    fn present_optional_dependency_initializes_first() {
+        let context = Context::with_plugins(&OPTIONAL_DEPENDENCY_PLUGINS);
+        let init_order: Vec<&str> = context.active.iter().map(|plugin| plugin.name).collect();
+        assert_eq!(init_order, vec!["Height", "Bmi"]);
+    }
+
+    #[test]
+    fn absent_optional_dependency_is_not_an_error() {
+        let context = ContextBuilder::with_plugins(&OPTIONAL_DEPENDENCY_PLUGINS).disable("Height").build().unwrap();
+        assert_eq!(context.names(), vec!["Bmi"]);
+    }
+
+    #[test]
+    fn disabled_optional_dependency_is_a_warning() {
+        let context = ContextBuilder::with_plugins(&OPTIONAL_DEPENDENCY_PLUGINS).build().unwrap();
+        assert!(context.warnings().is_empty());
+
+        // `Waist` is not registered at all, so only `Height` is reported.
+        let mut context = ContextBuilder::with_plugins(&OPTIONAL_DEPENDENCY_PLUGINS).disable("Height").build().unwrap();
+        let warning = Warning::OptionalDependencyDisabled { plugin: "Bmi", dependency: "Height" };
+        assert_eq!(context.warnings(), std::slice::from_ref(&warning));
+        assert_eq!(warning.to_string(), "plugin `Bmi` optionally depends on `Height`, which is disabled");
+
+        context.enable("Height").unwrap();
+        assert!(context.warnings().is_empty());
+        context.disable("Height").unwrap();
+        assert_eq!(context.warnings(), [warning]);
+    }
+
+    #[test]
+    fn set_default_overrides_the_initializer() {
+        let mut context = Context::builder().set_default::<u32>("Age", 30).build().unwrap();
+        let person_id = context.create_person().unwrap();
+        assert_eq!(context.get::<u32>("Age", person_id), Some(&30));
+        context.create_people(2).unwrap();
+        assert_eq!(context.iter_property::<u32>("Age").map(|(_, &age)| age).collect::<Vec<_>>(), vec![30, 30, 30]);
+
+        let error = Context::builder().set_default::<i64>("Age", 30).build().err().unwrap();
+        assert_eq!(error, InitError::Runtime(RuntimeError::TypeMismatch { plugin: "Age", type_name: "i64" }));
+    }
+
+    static MISCONFIGURED_PLUGINS: [Plugin; 5] = [
+        Plugin{ name: "Twin", ..Plugin::DEFAULT },
+        Plugin{ name: "Twin", ..Plugin::DEFAULT },
+        Plugin{ name: "Orphan", dependencies: &["Ghost"], ..Plugin::DEFAULT },
+        Plugin{ name: "Cat", conflicts_with: &["Dog"], ..Plugin::DEFAULT },
+        Plugin{ name: "Dog", ..Plugin::DEFAULT },
+    ];
+
+    #[test]
+    fn validate_all_reports_every_problem() {
+        let builder = ContextBuilder::with_plugins(&MISCONFIGURED_PLUGINS);
+        assert_eq!(
+            builder.validate_all(),
+            Err(vec![
+                InitError::Config(ConfigError::DuplicateName {
+                    namespace: "",
+                    name: "Twin",
+                    count: 2,
+                    origins: vec!["", ""],
+                }),
+                InitError::Config(ConfigError::Conflict { plugin: "Cat", other: "Dog" }),
+                InitError::Config(ConfigError::MissingDependency { plugin: "Orphan", needs: "Ghost" }),
+            ])
+        );
+        let duplicate = InitError::Config(ConfigError::DuplicateName {
+            namespace: "",
+            name: "Twin",
+            count: 2,
+            origins: vec!["", ""],
+        });
+        assert_eq!(builder.build().err(), Some(duplicate));
+        assert_eq!(Context::validate_all(), Ok(()));
+    }
+
+    static LAYERED_PLUGINS: [Plugin; 3] = [
+        Plugin{ name: "Report", dependencies: &["Summary"], ..Plugin::DEFAULT },
+        Plugin{ name: "Summary", dependencies: &["Survey"], ..Plugin::DEFAULT },
+        Plugin{ name: "Survey", enabled: false, ..Plugin::DEFAULT },
+    ];
+
+    #[test]
+    fn disabling_a_leaf_breaks_its_dependents_transitively() {
+        let chain =
+            ConfigError::DisabledDependencyChain { plugin: "Report", chain: vec!["Report", "Summary", "Survey"] };
+        assert_eq!(
+            ContextBuilder::with_plugins(&LAYERED_PLUGINS).validate_all(),
+            Err(vec![
+                InitError::Config(ConfigError::DisabledDependency { plugin: "Summary", needs: "Survey" }),
+                InitError::Config(chain.clone()),
+            ])
+        );
+        let message = "plugin `Report` depends on a disabled plugin: Report -> Summary -> Survey (disabled)";
+        assert_eq!(chain.to_string(), message);
+        assert!(ContextBuilder::with_plugins(&LAYERED_PLUGINS).enable("Survey").validate_all().is_ok());
+    }
+
+    static CONSTRUCTED: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+    static LINTED_PLUGINS: [Plugin; 3] = [
+        Plugin{
+            name: "Census",
+            version: (1, 2, 0),
+            constructor: |_context| CONSTRUCTED.lock().unwrap().push("Census"),
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Households",
+            dependencies: &["Census"],
+            dependency_versions: &[("Census", (1, 0, 0))],
+            constructor: |_context| CONSTRUCTED.lock().unwrap().push("Households"),
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Schools",
+            dependencies: &["Census"],
+            dependency_versions: &[("Census", (2, 0, 0))],
+            enabled: false,
+            constructor: |_context| CONSTRUCTED.lock().unwrap().push("Schools"),
+            ..Plugin::DEFAULT
+        },
+    ];
+
+    #[test]
+    fn validate_all_runs_no_constructors() {
+        assert_eq!(ContextBuilder::with_plugins(&LINTED_PLUGINS).validate_all(), Ok(()));
+        assert_eq!(
+            ContextBuilder::with_plugins(&LINTED_PLUGINS).enable("Schools").validate_all(),
+            Err(vec![InitError::Config(ConfigError::DependencyTooOld {
+                plugin: "Schools",
+                needs: "Census",
+                found: (1, 2, 0),
+                required: (2, 0, 0),
+            })])
+        );
+        assert!(CONSTRUCTED.lock().unwrap().is_empty());
+    }
+
+    static PREVIEWED_PLUGINS: [Plugin; 3] = [
+        Plugin{ name: "Census", default_state: DefaultState::OptIn, ..Plugin::DEFAULT },
+        Plugin{ name: "Households", dependencies: &["Census"], ..Plugin::DEFAULT },
+        Plugin{ name: "Schools", ..Plugin::DEFAULT },
+    ];
+
+    #[test]
+    fn preview_enabled_matches_the_built_context() {
+        let builder = || ContextBuilder::with_plugins(&PREVIEWED_PLUGINS);
+        // `Census` is opt-in, which leaves `Households` without its dependency.
+        assert_eq!(builder().preview_enabled(), vec!["Schools"]);
+        assert_eq!(builder().disable("Households").preview_enabled(), vec!["Schools"]);
+
+        for builder in [builder().enable("Census"), builder().enable("Census").disable("Schools")] {
+            let preview = builder.preview_enabled();
+            assert_eq!(preview, builder.build().unwrap().names());
+        }
+        assert_eq!(builder().enable("Census").preview_enabled(), vec!["Census", "Households", "Schools"]);
+
+        let builder = builder().enable("Census").disable("Households");
+        let preview = builder.preview_enabled();
+        assert_eq!(preview, builder.build().unwrap().names());
+    }
+
+    static NESTED: Mutex<Option<InitError>> = Mutex::new(None);
+
+    static NESTING_PLUGINS: [Plugin; 1] = [Plugin::builder("Nesting")
+        .constructor(|_context| *NESTED.lock().unwrap() = Context::try_new().err())
+        .build()];
+
+    #[test]
+    fn a_constructor_cannot_build_a_context() {
+        let context = ContextBuilder::with_plugins(&NESTING_PLUGINS).build().unwrap();
+        let error = NESTED.lock().unwrap().take();
+        assert_eq!(error, Some(InitError::Config(ConfigError::ReentrantBuild("Nesting"))));
+        let message = "the constructor of plugin `Nesting` tried to build a context while being initialized";
+        assert_eq!(error.unwrap().to_string(), message);
+
+        // The guard is lifted once the plugin is initialized.
+        assert_eq!(context.names(), vec!["Nesting"]);
+        assert!(Context::try_new().is_ok());
+    }
+}
@@ -0,0 +1,380 @@
+//! Saving the full state of a `Context` to a checkpoint and restoring it.
+//!
+//! A checkpoint is UTF-8 text with one entry per line:
+//!
+//! ```text
+//! checkpoint 1
+//! rng 12345
+//! people 3
+//! removed 1
+//! plugin initialization::Age
+//! column 3 initialization::Age u32
+//! 42
+//! 30
+//! 42
+//! ```
+//!
+//! `removed` and `unpopulated` lines list the removed people and the plugins waiting for `Context::backfill`. A
+//! `column` line gives the number of values, the owner and the value type, and is followed by that many values,
+//! encoded by the column's codec with backslashes, carriage returns and line feeds escaped. The owner is the plugin, or
+//! for a column of one of its `properties` the plugin and the property, as in `my_plugin::Names/First`. An `unset`
+//! line gives a person, the owner and the value type of a slot unset by `Context::unset`.
+
+use std::any::Any;
+use std::io::Write;
+use std::str::FromStr;
+
+use crate::{ColumnKey, Context, InitError, Plugin, Rng, RuntimeError};
+
+const HEADER: &str = "checkpoint 1";
+
+/// The contents of a checkpoint, as parsed from its text.
+pub(crate) struct Checkpoint {
+    rng: u64,
+    people: usize,
+    removed: Vec<usize>,
+    /// The qualified names of the enabled plugins.
+    pub(crate) plugins: Vec<String>,
+    unpopulated: Vec<String>,
+    /// The owner, the type name and the encoded values of each column.
+    columns: Vec<(String, String, Vec<String>)>,
+    /// The person, the owner and the type name of each unset slot.
+    unset: Vec<(usize, String, String)>,
+}
+
+/// Writes the state of `context` to `writer`. Fails with `RuntimeError::NotSerializable`, before writing anything, if
+/// any enabled plugin has a column without a codec, of its own or of one of its `properties`, naming every such plugin.
+pub(crate) fn save<W: Write>(context: &Context, mut writer: W) -> Result<(), InitError> {
+    let unserializable = context.unserializable_plugins();
+    if !unserializable.is_empty() {
+        return Err(InitError::Runtime(RuntimeError::NotSerializable(unserializable)));
+    }
+    let mut text = format!("{}\nrng {}\npeople {}\n", HEADER, context.rng.state(), context.person_count);
+    for person_id in &context.removed {
+        text.push_str(&format!("removed {}\n", person_id));
+    }
+    for plugin in &context.plugins {
+        text.push_str(&format!("plugin {}\n", plugin.qualified_name()));
+    }
+    for plugin in &context.unpopulated {
+        text.push_str(&format!("unpopulated {}\n", plugin.qualified_name()));
+    }
+    for &plugin in &context.plugins {
+        for key in context.instance_of(plugin).column_keys() {
+            let codec = &context.codecs[&key];
+            let column: &dyn Any = context.columns[&key].as_ref();
+            let values = (codec.encode)(column);
+            let owner = owner(plugin, key);
+            text.push_str(&format!("column {} {} {}\n", values.len(), owner, codec.type_name));
+            for value in values {
+                text.push_str(&escape(&value));
+                text.push('\n');
+            }
+            for person_id in context.unset.get(&key).into_iter().flat_map(|bits| bits.iter()) {
+                text.push_str(&format!("unset {} {} {}\n", person_id, owner, codec.type_name));
+            }
+        }
+    }
+    writer
+        .write_all(text.as_bytes())
+        .map_err(|error| InitError::Runtime(RuntimeError::InvalidCheckpoint(error.to_string())))
+}
+
+/// How a checkpoint names the owner of the column `key` of `plugin`: the plugin's qualified name, followed by `/` and
+/// the property's name for a column of one of its `properties`.
+fn owner(plugin: &Plugin, key: ColumnKey) -> String {
+    if key.0 == plugin.sort_key() {
+        plugin.qualified_name()
+    } else {
+        format!("{}/{}", plugin.qualified_name(), key.0.0)
+    }
+}
+
+/// Parses the text of a checkpoint written by `save`.
+pub(crate) fn parse(text: &str) -> Result<Checkpoint, InitError> {
+    let invalid = |message: String| InitError::Runtime(RuntimeError::InvalidCheckpoint(message));
+    let mut lines = text.lines();
+    if lines.next() != Some(HEADER) {
+        return Err(invalid(format!("the first line is not `{}`", HEADER)));
+    }
+
+    let mut checkpoint = Checkpoint {
+        rng: 0,
+        people: 0,
+        removed: Vec::new(),
+        plugins: Vec::new(),
+        unpopulated: Vec::new(),
+        columns: Vec::new(),
+        unset: Vec::new(),
+    };
+    while let Some(line) = lines.next() {
+        let (entry, value) = line.split_once(' ').ok_or_else(|| invalid(format!("unexpected line `{}`", line)))?;
+        match entry {
+            "rng" => checkpoint.rng = number(value)?,
+            "people" => checkpoint.people = number(value)?,
+            "removed" => checkpoint.removed.push(number(value)?),
+            "plugin" => checkpoint.plugins.push(value.to_string()),
+            "unpopulated" => checkpoint.unpopulated.push(value.to_string()),
+            "column" => {
+                let mut parts = value.splitn(3, ' ');
+                let (Some(count), Some(plugin), Some(type_name)) = (parts.next(), parts.next(), parts.next()) else {
+                    return Err(invalid(format!("unexpected line `{}`", line)));
+                };
+                let values = (0..number(count)?)
+                    .map(|_| lines.next().map(unescape))
+                    .collect::<Option<Vec<String>>>()
+                    .ok_or_else(|| invalid(format!("the column of plugin `{}` is cut short", plugin)))?;
+                checkpoint.columns.push((plugin.to_string(), type_name.to_string(), values));
+            }
+            "unset" => {
+                let mut parts = value.splitn(3, ' ');
+                let (Some(person_id), Some(plugin), Some(type_name)) = (parts.next(), parts.next(), parts.next()) else {
+                    return Err(invalid(format!("unexpected line `{}`", line)));
+                };
+                checkpoint.unset.push((number(person_id)?, plugin.to_string(), type_name.to_string()));
+            }
+            _ => return Err(invalid(format!("unexpected line `{}`", line))),
+        }
+    }
+    Ok(checkpoint)
+}
+
+/// Restores the people of `checkpoint` into `context`, which must have been built with exactly the checkpoint's
+/// enabled plugins and no people.
+pub(crate) fn restore(context: &mut Context, checkpoint: Checkpoint) -> Result<(), InitError> {
+    let invalid = |message: String| InitError::Runtime(RuntimeError::InvalidCheckpoint(message));
+    context.rng = Rng::new(checkpoint.rng);
+    context.person_count = checkpoint.people;
+    context.removed = checkpoint.removed.into_iter().collect();
+
+    let mut restored = Vec::new();
+    for (name, type_name, values) in checkpoint.columns {
+        let key = codec_key(context, &name, &type_name)?;
+        let codec = &context.codecs[&key];
+        let column: &mut dyn Any = context.columns.get_mut(&key).expect("a codec's column exists").as_mut();
+        (codec.decode)(column, &values).map_err(|value| {
+            invalid(format!("`{}` is not a valid value of type `{}` for plugin `{}`", value, type_name, name))
+        })?;
+        restored.push(key);
+    }
+    for &plugin in &context.plugins {
+        for key in context.instance_of(plugin).column_keys() {
+            if !restored.contains(&key) {
+                return Err(invalid(format!("the checkpoint has no values for plugin `{}`", plugin.name)));
+            }
+        }
+    }
+
+    for (person_id, name, type_name) in checkpoint.unset {
+        let key = codec_key(context, &name, &type_name)?;
+        context.unset.entry(key).or_default().insert(person_id);
+    }
+
+    for name in checkpoint.unpopulated {
+        let plugin = context.get_plugin(&name).ok_or_else(|| invalid(format!("plugin `{}` is not enabled", name)))?;
+        context.unpopulated.push(plugin);
+    }
+    context.update_active();
+    Ok(())
+}
+
+/// The key of the column of the owner `name`, as written by `owner`, with values of type `type_name`, which must have a
+/// codec. The owner's plugin must be enabled.
+fn codec_key(context: &Context, name: &str, type_name: &str) -> Result<ColumnKey, InitError> {
+    let invalid = |message: String| InitError::Runtime(RuntimeError::InvalidCheckpoint(message));
+    let (plugin_name, property) = match name.split_once('/') {
+        Some((plugin_name, property)) => (plugin_name, Some(property)),
+        None => (name, None),
+    };
+    let plugin = context
+        .get_plugin(plugin_name)
+        .ok_or_else(|| invalid(format!("plugin `{}` is not enabled", plugin_name)))?;
+    let owner = match property {
+        Some(property) => context
+            .instance_of(plugin)
+            .property_columns
+            .iter()
+            .find(|&&(other, _)| other == property)
+            .map(|&(property, _)| (property, plugin.namespace))
+            .ok_or_else(|| invalid(format!("plugin `{}` has no property `{}`", plugin_name, property)))?,
+        None => plugin.sort_key(),
+    };
+    context
+        .codecs
+        .iter()
+        .find(|(key, codec)| key.0 == owner && codec.type_name == type_name)
+        .map(|(&key, _)| key)
+        .ok_or_else(|| invalid(format!("plugin `{}` has no column of type `{}`", name, type_name)))
+}
+
+fn number<T: FromStr>(value: &str) -> Result<T, InitError> {
+    value
+        .parse()
+        .map_err(|_| InitError::Runtime(RuntimeError::InvalidCheckpoint(format!("`{}` is not a number", value))))
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+fn unescape(line: &str) -> String {
+    let mut value = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('r') => value.push('\r'),
+            Some(other) => value.push(other),
+            None => value.push('\\'),
+        }
+    }
+    value
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{escape, unescape};
+    use crate::{Context, ContextBuilder, InitError, PersonId, Plugin, Property, PropertySpec, RuntimeError};
+
+    #[test]
+    fn round_trip_restores_people() {
+        let mut context = ContextBuilder::new().seed(3).build().unwrap();
+        context.create_people(3).unwrap();
+        context.set::<u32>("Age", PersonId(1), 30).unwrap();
+        context.remove_person(PersonId(2)).unwrap();
+
+        let mut checkpoint = Vec::new();
+        context.save(&mut checkpoint).unwrap();
+        let mut loaded = Context::load(checkpoint.as_slice()).unwrap();
+
+        assert_eq!(loaded.names(), context.names());
+        assert_eq!(loaded.property::<u32>("Age"), context.property::<u32>("Age"));
+        assert_eq!(loaded.get::<u32>("Age", PersonId(2)), None);
+        assert_eq!(loaded.rng().next_u64(), context.rng().next_u64());
+        assert_eq!(loaded.create_person(), Ok(PersonId(2)));
+        assert_eq!(loaded.create_person(), Ok(PersonId(3)));
+    }
+
+    static FIRST: Property<u32> = Property{ name: "First", default: 7, initializer: None };
+
+    static NAMES_PLUGINS: [Plugin; 1] = [Plugin::builder("Names")
+        .properties(&[PropertySpec{
+            name: FIRST.name,
+            constructor: |context| {
+                FIRST.construct(context);
+                context.register_codec::<u32>("First", u32::to_string, |value| value.parse().ok());
+            },
+            initializer: |context, person_id| FIRST.initialize(context, person_id),
+        }])
+        .build()];
+
+    #[test]
+    fn round_trip_restores_the_columns_of_properties() {
+        let mut context = Context::with_plugins(&NAMES_PLUGINS);
+        context.create_people(3).unwrap();
+        context.set::<u32>("First", PersonId(1), 8).unwrap();
+        context.unset::<u32>("First", PersonId(2)).unwrap();
+
+        let mut checkpoint = Vec::new();
+        context.save(&mut checkpoint).unwrap();
+        assert!(String::from_utf8_lossy(&checkpoint).contains("column 3 Names/First u32\n"));
+        let mut loaded = ContextBuilder::with_plugins(&NAMES_PLUGINS).load(checkpoint.as_slice()).unwrap();
+
+        assert_eq!(loaded.property::<u32>("First"), Some(&vec![7, 8, 7]));
+        assert_eq!(loaded.get::<u32>("First", PersonId(2)), None);
+        assert_eq!(loaded.create_person(), Ok(PersonId(3)));
+        assert_eq!(loaded.get::<u32>("First", PersonId(3)), Some(&7));
+    }
+
+    #[test]
+    fn disabled_plugins_stay_disabled() {
+        let context = ContextBuilder::with_plugins(&NAMES_PLUGINS).disable("Names").build().unwrap();
+        let mut checkpoint = Vec::new();
+        context.save(&mut checkpoint).unwrap();
+        let loaded = ContextBuilder::with_plugins(&NAMES_PLUGINS).load(checkpoint.as_slice()).unwrap();
+        assert!(loaded.plugins.is_empty());
+    }
+
+    static OPAQUE_PLUGINS: [Plugin; 1] = [Plugin::builder("Opaque")
+        .constructor(|context| context.register_property::<fn()>("Opaque"))
+        .build()];
+
+    #[test]
+    fn columns_without_a_codec_are_reported() {
+        let context = Context::with_plugins(&OPAQUE_PLUGINS);
+        let error = context.save(Vec::new()).unwrap_err();
+        assert_eq!(error, InitError::Runtime(RuntimeError::NotSerializable(vec!["Opaque"])));
+        assert_eq!(error.plugin(), Some("Opaque"));
+    }
+
+    static MIXED_PLUGINS: [Plugin; 5] = [
+        Plugin::builder("Label")
+            .constructor(|context| {
+                context.register_property::<String>("Label");
+                context.register_codec::<String>("Label", String::clone, |value| Some(value.to_string()));
+            })
+            .build(),
+        Plugin::builder("Opaque")
+            .constructor(|context| context.register_property::<fn()>("Opaque"))
+            .build(),
+        Plugin::builder("Hidden")
+            .enabled(false)
+            .constructor(|context| context.register_property::<fn()>("Hidden"))
+            .build(),
+        Plugin::builder("Plain").build(),
+        Plugin::builder("Tagged")
+            .properties(&[PropertySpec{
+                name: "Tag",
+                constructor: |context| context.register_property::<fn()>("Tag"),
+                initializer: |_context, _person_id| Ok(()),
+            }])
+            .build(),
+    ];
+
+    #[test]
+    fn unserializable_plugins_are_listed_before_saving() {
+        let mut context = Context::with_plugins(&MIXED_PLUGINS);
+        assert_eq!(context.unserializable_plugins(), vec!["Opaque", "Tagged"]);
+        let mut checkpoint = Vec::new();
+        let error = context.save(&mut checkpoint).unwrap_err();
+        assert_eq!(error, InitError::Runtime(RuntimeError::NotSerializable(vec!["Opaque", "Tagged"])));
+        assert_eq!(error.plugin(), None);
+        assert_eq!(
+            error.to_string(),
+            "plugins `Opaque`, `Tagged` have a column without a codec, so they cannot be saved"
+        );
+        assert!(checkpoint.is_empty());
+
+        context.disable("Opaque").unwrap();
+        assert_eq!(context.unserializable_plugins(), vec!["Tagged"]);
+        assert_eq!(context.save(Vec::new()), Err(InitError::Runtime(RuntimeError::NotSerializable(vec!["Tagged"]))));
+
+        context.disable("Tagged").unwrap();
+        assert!(context.unserializable_plugins().is_empty());
+        assert!(context.save(Vec::new()).is_ok());
+    }
+
+    #[test]
+    fn invalid_checkpoints_are_rejected() {
+        let error = Context::load("checkpoint 1\npeople 1\nplugin initialization::Age\n".as_bytes()).err();
+        assert_eq!(
+            error,
+            Some(InitError::Runtime(RuntimeError::InvalidCheckpoint(
+                "the checkpoint has no values for plugin `Age`".to_string()
+            )))
+        );
+        assert!(Context::load("not a checkpoint".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn values_with_line_breaks_survive_escaping() {
+        let value = "two\nlines\\ and\r";
+        assert_eq!(unescape(&escape(value)), value);
+        assert!(!escape(value).contains('\n'));
+    }
+}
@@ -0,0 +1,151 @@
+//! Type-erased storage for a property column.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::any::Any;
+
+use crate::PropertyStore;
+
+/// A column of per-entity values whose element type has been erased, so that the `Context` can keep every column in
+/// one map and still perform operations that don't depend on the element type. Downcast to `Vec<T>` through
+/// `dyn Any` to access the values. Columns are `Send` so that they can be initialized on other threads.
+pub(crate) trait Column: Any + Send {
+    fn len(&self) -> usize;
+
+    /// Drops every value at index `len` or later.
+    fn truncate(&mut self, len: usize);
+
+    /// Moves every value of `other`, which holds values of the same type, to the end of this column.
+    fn append(&mut self, other: Box<dyn Column>);
+
+    /// Drops the values at the indices `keep` rejects, moving the others down in order, and releases the memory
+    /// freed.
+    fn compact(&mut self, keep: &dyn Fn(usize) -> bool);
+}
+
+impl<T: Send + 'static> Column for Vec<T> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn truncate(&mut self, len: usize) {
+        Vec::truncate(self, len);
+    }
+
+    fn append(&mut self, other: Box<dyn Column>) {
+        let other: Box<dyn Any> = other;
+        let mut other = other.downcast::<Vec<T>>().expect("the columns hold values of the same type");
+        Vec::append(self, &mut other);
+    }
+
+    fn compact(&mut self, keep: &dyn Fn(usize) -> bool) {
+        let mut index = 0;
+        self.retain(|_| {
+            index += 1;
+            keep(index - 1)
+        });
+        self.shrink_to_fit();
+    }
+}
+
+/// A column backed by a store registered with `Context::register_property_store`.
+impl<T: 'static> Column for Box<dyn PropertyStore<T> + Send> {
+    fn len(&self) -> usize {
+        PropertyStore::len(self.as_ref())
+    }
+
+    fn truncate(&mut self, len: usize) {
+        PropertyStore::truncate(self.as_mut(), len);
+    }
+
+    fn append(&mut self, mut other: Box<dyn Column>) {
+        let other: &mut dyn Any = other.as_mut();
+        let other = store_mut::<T>(other).expect("the columns hold values of the same type");
+        for value in take_all(other) {
+            self.push(value);
+        }
+    }
+
+    fn compact(&mut self, keep: &dyn Fn(usize) -> bool) {
+        for (index, value) in take_all(self.as_mut()).into_iter().enumerate() {
+            if keep(index) {
+                self.push(value);
+            }
+        }
+    }
+}
+
+/// The values of a column holding `T`s, whether it is a `Vec<T>` or another store, or `None` if it holds another type.
+pub(crate) fn store<T: 'static>(column: &dyn Any) -> Option<&dyn PropertyStore<T>> {
+    if let Some(values) = column.downcast_ref::<Vec<T>>() {
+        return Some(values);
+    }
+    column.downcast_ref::<Box<dyn PropertyStore<T> + Send>>().map(|store| store.as_ref() as &dyn PropertyStore<T>)
+}
+
+/// Like `store`, for changing the values.
+pub(crate) fn store_mut<T: 'static>(column: &mut dyn Any) -> Option<&mut dyn PropertyStore<T>> {
+    if column.is::<Vec<T>>() {
+        return column.downcast_mut::<Vec<T>>().map(|values| values as &mut dyn PropertyStore<T>);
+    }
+    column.downcast_mut::<Box<dyn PropertyStore<T> + Send>>().map(|store| store.as_mut() as &mut dyn PropertyStore<T>)
+}
+
+/// Empties `store`, returning its values in order.
+fn take_all<T>(store: &mut dyn PropertyStore<T>) -> Vec<T> {
+    let mut values = Vec::with_capacity(store.len());
+    while let Some(value) = store.pop() {
+        values.push(value);
+    }
+    values.reverse();
+    values
+}
+
+/// A set of indices stored as one bit per index. The `Context` keeps one per column with unset slots, marking the
+/// people whose slot holds only a placeholder.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Bitset {
+    words: Vec<u64>,
+}
+
+impl Bitset {
+    pub(crate) fn contains(&self, index: usize) -> bool {
+        self.words.get(index / 64).is_some_and(|word| word & (1 << (index % 64)) != 0)
+    }
+
+    pub(crate) fn insert(&mut self, index: usize) {
+        if self.words.len() <= index / 64 {
+            self.words.resize(index / 64 + 1, 0);
+        }
+        self.words[index / 64] |= 1 << (index % 64);
+    }
+
+    pub(crate) fn remove(&mut self, index: usize) {
+        if let Some(word) = self.words.get_mut(index / 64) {
+            *word &= !(1 << (index % 64));
+        }
+    }
+
+    /// The indices in the set, in increasing order.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.words.len() * 64).filter(|&index| self.contains(index))
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::Bitset;
+
+    #[test]
+    fn bitset_tracks_indices_across_words() {
+        let mut bits = Bitset::default();
+        bits.insert(3);
+        bits.insert(64);
+        bits.insert(130);
+        assert!(bits.contains(64) && !bits.contains(63));
+        bits.remove(64);
+        bits.remove(1000);
+        assert_eq!(bits.iter().collect::<Vec<_>>(), vec![3, 130]);
+    }
+}
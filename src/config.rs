@@ -0,0 +1,185 @@
+//! Parsing of plugin enable/disable overrides from a configuration file.
+//!
+//! Two flat formats are understood, each mapping plugin names to booleans. A TOML file of top-level keys:
+//!
+//! ```toml
+//! # Turn off the weight property for this run.
+//! Weight = false
+//! "Age" = true
+//! ```
+//!
+//! or a JSON object:
+//!
+//! ```json
+//! { "Weight": false, "Age": true }
+//! ```
+//!
+//! Only this subset of each format is supported.
+
+use std::path::Path;
+
+/// Parses the overrides in `text`, as JSON if `path` has a `.json` extension and as TOML otherwise. Returns the
+/// overrides in file order, or a message describing the first problem.
+pub(crate) fn parse_overrides(path: &Path, text: &str) -> Result<Vec<(String, bool)>, String> {
+    if path.extension().is_some_and(|extension| extension == "json") {
+        parse_json(text)
+    } else {
+        parse_toml(text)
+    }
+}
+
+fn parse_toml(text: &str) -> Result<Vec<(String, bool)>, String> {
+    let mut overrides = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected `name = true` or `name = false`", index + 1))?;
+        let key = key.trim();
+        let key = key
+            .strip_prefix('"')
+            .and_then(|key| key.strip_suffix('"'))
+            .unwrap_or(key);
+        // Allow a trailing comment after the value.
+        let value = value.split('#').next().unwrap().trim();
+        let enabled = match value {
+            "true" => true,
+            "false" => false,
+            _ => return Err(format!("line {}: `{}` must be `true` or `false`", index + 1, key)),
+        };
+        overrides.push((key.to_string(), enabled));
+    }
+    Ok(overrides)
+}
+
+fn parse_json(text: &str) -> Result<Vec<(String, bool)>, String> {
+    let mut parser = JsonParser { text, position: 0 };
+    let mut overrides = Vec::new();
+
+    parser.expect('{')?;
+    if !parser.consume('}') {
+        loop {
+            let key = parser.string()?;
+            parser.expect(':')?;
+            let enabled = parser.boolean()?;
+            overrides.push((key, enabled));
+            if parser.consume('}') {
+                break;
+            }
+            parser.expect(',')?;
+        }
+    }
+
+    parser.skip_whitespace();
+    if parser.position < text.len() {
+        return Err(format!("unexpected text after the object at byte {}", parser.position));
+    }
+    Ok(overrides)
+}
+
+struct JsonParser<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl JsonParser<'_> {
+    fn rest(&self) -> &str {
+        &self.text[self.position..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    /// Skips whitespace, then consumes `expected` if it comes next.
+    fn consume(&mut self, expected: char) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(expected) {
+            self.position += expected.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        if self.consume(expected) {
+            Ok(())
+        } else {
+            Err(format!("expected `{}` at byte {}", expected, self.position))
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut value = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((offset, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.position += offset + 1;
+                    return Ok(value);
+                }
+                '\\' => match chars.next() {
+                    Some((_, '"')) => value.push('"'),
+                    Some((_, '\\')) => value.push('\\'),
+                    Some((_, '/')) => value.push('/'),
+                    Some((_, 'n')) => value.push('\n'),
+                    Some((_, 't')) => value.push('\t'),
+                    _ => return Err(format!("unsupported escape in string at byte {}", self.position + offset)),
+                },
+                c => value.push(c),
+            }
+        }
+        Err("unterminated string".to_string())
+    }
+
+    fn boolean(&mut self) -> Result<bool, String> {
+        self.skip_whitespace();
+        for (literal, value) in [("true", true), ("false", false)] {
+            if self.rest().starts_with(literal) {
+                self.position += literal.len();
+                return Ok(value);
+            }
+        }
+        Err(format!("expected `true` or `false` at byte {}", self.position))
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::parse_overrides;
+
+    #[test]
+    fn parses_toml() {
+        let text = "# comment\nWeight = false\n\n\"Age\" = true # keep the age\n";
+        assert_eq!(
+            parse_overrides(Path::new("run.toml"), text),
+            Ok(vec![("Weight".to_string(), false), ("Age".to_string(), true)])
+        );
+        assert_eq!(
+            parse_overrides(Path::new("run.toml"), "Weight = no"),
+            Err("line 1: `Weight` must be `true` or `false`".to_string())
+        );
+    }
+
+    #[test]
+    fn parses_json() {
+        let text = r#" { "Weight" : false, "A \"quoted\" name": true } "#;
+        assert_eq!(
+            parse_overrides(Path::new("run.json"), text),
+            Ok(vec![("Weight".to_string(), false), ("A \"quoted\" name".to_string(), true)])
+        );
+        assert_eq!(parse_overrides(Path::new("run.json"), "{}"), Ok(vec![]));
+        assert!(parse_overrides(Path::new("run.json"), r#"{"Weight": 0}"#).is_err());
+        assert!(parse_overrides(Path::new("run.json"), r#"{"Weight": true} extra"#).is_err());
+    }
+}
@@ -0,0 +1,37 @@
+//! Writing CSV by hand.
+
+use std::io::{self, Write};
+
+/// Writes `cells` to `writer` as one CSV record terminated by `\n`.
+pub(crate) fn write_csv_record<W: Write>(writer: &mut W, cells: &[String]) -> io::Result<()> {
+    for (index, cell) in cells.iter().enumerate() {
+        if index > 0 {
+            writer.write_all(b",")?;
+        }
+        write_csv_cell(writer, cell)?;
+    }
+    writer.write_all(b"\n")
+}
+
+/// Writes `cell` to `writer`, quoted as described in RFC 4180 if it contains a comma, a quote, or a line break.
+fn write_csv_cell<W: Write>(writer: &mut W, cell: &str) -> io::Result<()> {
+    if cell.contains([',', '"', '\n', '\r']) {
+        write!(writer, "\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        writer.write_all(cell.as_bytes())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::write_csv_record;
+
+    #[test]
+    fn cells_are_quoted_when_needed() {
+        let mut csv = Vec::new();
+        let cells = ["plain".to_string(), "a,b".to_string(), "say \"hi\"".to_string()];
+        write_csv_record(&mut csv, &cells).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), "plain,\"a,b\",\"say \"\"hi\"\"\"\n");
+    }
+}
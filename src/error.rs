@@ -0,0 +1,397 @@
+//! Errors reported while constructing a `Context` or initializing its entities.
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt::{Display, Formatter};
+
+use crate::{Context, PersonId, Version};
+
+/// Any failure of this crate: either a mistake in the plugins or their configuration, which retrying cannot fix, or a
+/// failure while operating on people and their values at runtime. Displays as the error it wraps, which is also its
+/// `source`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InitError {
+    /// The plugins or their configuration are invalid. See `ConfigError`.
+    Config(ConfigError),
+    /// An operation on people or their values failed. See `RuntimeError`.
+    Runtime(RuntimeError),
+}
+
+/// A mistake in the set of registered plugins or in how they are configured, found by `ContextBuilder::build` or by
+/// reconfiguring a `Context` with `Context::enable` or `Context::disable`. The same configuration always fails the
+/// same way, so the fix is to change it rather than to retry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    /// More than one plugin is registered under this name in this namespace. `origins` holds the `origin` of each of
+    /// the `count` plugins, sorted.
+    DuplicateName { namespace: &'static str, name: &'static str, count: usize, origins: Vec<&'static str> },
+    /// The enabled plugin `plugin` depends on `needs`, which is not registered.
+    MissingDependency { plugin: &'static str, needs: &'static str },
+    /// The enabled plugin `plugin` depends on `needs`, which is registered but disabled.
+    DisabledDependency { plugin: &'static str, needs: &'static str },
+    /// The enabled plugin `plugin` depends on a disabled plugin through other enabled plugins. The `chain` starts with
+    /// `plugin`, each plugin on it depends on the next, and the last is the disabled plugin.
+    DisabledDependencyChain { plugin: &'static str, chain: Vec<&'static str> },
+    /// The enabled plugin `plugin` depends on `needs`, which `ContextBuilder::only_tags` excluded.
+    ExcludedDependency { plugin: &'static str, needs: &'static str },
+    /// The enabled plugin `plugin` needs at least version `required` of `needs`, but `found` is registered.
+    DependencyTooOld { plugin: &'static str, needs: &'static str, found: Version, required: Version },
+    /// The enabled plugin `plugin` depends on the bare name `needs`, which plugins in several namespaces share.
+    AmbiguousDependency { plugin: &'static str, needs: &'static str },
+    /// The dependencies form a cycle, so no initialization order exists. The path starts and ends with the same plugin,
+    /// and each plugin on it depends on the next.
+    DependencyCycle(Vec<&'static str>),
+    /// The enabled plugins `plugin` and `other` conflict, so they cannot both be enabled.
+    Conflict { plugin: &'static str, other: &'static str },
+    /// The plugin is declared `required` but also declared not `enabled`, so entities would lack a mandatory property.
+    RequiredButDisabled(&'static str),
+    /// The plugin has a `batch_initializer`, but registered `count` columns instead of exactly one.
+    BatchColumnCount { plugin: &'static str, count: usize },
+    /// The constructor of `plugin` registered `registered`, a plugin that is not being initialized, usually because it
+    /// names the wrong `static`.
+    ForeignRegistration { plugin: &'static str, registered: &'static str },
+    /// The constructor of the plugin called `ContextBuilder::build`, directly or through `Context::new`, while its own
+    /// `Context` was being built or was enabling it.
+    ReentrantBuild(&'static str),
+    /// A `ContextBuilder` override or `Context::disable` disables the plugin, whose `default_state` is
+    /// `DefaultState::Unconditional`.
+    CannotDisable(&'static str),
+    /// `Context::disable`, or a `ContextBuilder` override by name or tag, disables a `required` plugin.
+    DisableRequired(&'static str),
+    /// `Context::disable` was called on `plugin`, but the enabled plugin `dependent` depends on it.
+    HasDependent { plugin: &'static str, dependent: &'static str },
+    /// `Context::merge` was called on two contexts of which only one has the plugin enabled, with every person
+    /// initialized and the same columns.
+    MergeMismatch(&'static str),
+    /// A `ContextBuilder` override, or a call to `Context::enable` or `Context::disable`, names a plugin that is not
+    /// registered.
+    UnknownPlugin(String),
+    /// A `ContextBuilder` override, or a call to `Context::enable` or `Context::disable`, names a plugin by a bare
+    /// name that plugins in several namespaces share.
+    AmbiguousName(String),
+    /// A `ContextBuilder` tag override names a tag that no registered plugin carries.
+    UnknownTag(String),
+    /// The configuration file at `path` could not be read or parsed.
+    InvalidConfig { path: String, message: String },
+}
+
+/// A failure while creating people, reading or writing their values, or saving or loading a checkpoint, which
+/// depends on the data rather than only on the configuration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RuntimeError {
+    /// The initializer of `plugin` could not compute the first value for `person_id`.
+    InitializerFailed { plugin: &'static str, person_id: PersonId, message: String },
+    /// The values computed by `plugin` for `person_id` are invalid, as its `validator` explains in `message`.
+    InvalidValue { plugin: &'static str, person_id: PersonId, message: String },
+    /// The required plugin `plugin` has no value for `person_id`.
+    MissingValue { plugin: &'static str, person_id: PersonId },
+    /// The person was never created, or was removed by `Context::remove_person`.
+    NoSuchPerson(PersonId),
+    /// `Context::set` was called with a value of type `type_name`, but `plugin` has no column of that type.
+    TypeMismatch { plugin: &'static str, type_name: &'static str },
+    /// `Context::backfill` or `Context::set` was called on a disabled plugin.
+    PluginDisabled(&'static str),
+    /// `Context::set` was called to replace the value of the `immutable` plugin `plugin` for `person_id`, which is not
+    /// unset.
+    Immutable { plugin: &'static str, person_id: PersonId },
+    /// `Context::save` was called, but these plugins have a column without a codec registered by
+    /// `Context::register_codec`, as listed by `Context::unserializable_plugins`.
+    NotSerializable(Vec<&'static str>),
+    /// `Context::get_as` was asked for `plugin`'s values in `unit`, which is neither the plugin's `unit` nor one it
+    /// registered a conversion to with `Context::register_conversion`.
+    UnknownConversion { plugin: &'static str, unit: String },
+    /// A checkpoint could not be written, read or parsed, or does not match the registered plugins.
+    InvalidCheckpoint(String),
+}
+
+/// A failed `Context::merge`, which hands both contexts back unchanged so that the caller keeps their people and
+/// state. Displays as the `error` it wraps, which is also its `source`.
+pub struct MergeError {
+    pub error: InitError,
+    /// The `Context` that `merge` was called on.
+    pub context: Context,
+    /// The `Context` that was to be merged into it.
+    pub other: Context,
+}
+
+impl InitError {
+    /// The name of the plugin the error is about, if it is about one plugin in particular.
+    pub fn plugin(&self) -> Option<&str> {
+        match self {
+            InitError::Config(error) => error.plugin(),
+            InitError::Runtime(error) => error.plugin(),
+        }
+    }
+}
+
+impl ConfigError {
+    /// The name of the plugin the error is about, if it is about one plugin in particular.
+    pub fn plugin(&self) -> Option<&str> {
+        match self {
+            ConfigError::DuplicateName { name, .. } => Some(name),
+            ConfigError::MissingDependency { plugin, .. }
+            | ConfigError::DisabledDependency { plugin, .. }
+            | ConfigError::DisabledDependencyChain { plugin, .. }
+            | ConfigError::ExcludedDependency { plugin, .. }
+            | ConfigError::DependencyTooOld { plugin, .. }
+            | ConfigError::AmbiguousDependency { plugin, .. }
+            | ConfigError::Conflict { plugin, .. }
+            | ConfigError::BatchColumnCount { plugin, .. }
+            | ConfigError::ForeignRegistration { plugin, .. }
+            | ConfigError::HasDependent { plugin, .. } => Some(plugin),
+            ConfigError::RequiredButDisabled(name)
+            | ConfigError::CannotDisable(name)
+            | ConfigError::DisableRequired(name)
+            | ConfigError::ReentrantBuild(name)
+            | ConfigError::MergeMismatch(name) => Some(name),
+            ConfigError::UnknownPlugin(name) | ConfigError::AmbiguousName(name) => Some(name),
+            ConfigError::DependencyCycle(_) | ConfigError::UnknownTag(_) | ConfigError::InvalidConfig { .. } => None,
+        }
+    }
+}
+
+impl RuntimeError {
+    /// The name of the plugin the error is about, if it is about one plugin in particular.
+    pub fn plugin(&self) -> Option<&str> {
+        match self {
+            RuntimeError::InitializerFailed { plugin, .. }
+            | RuntimeError::InvalidValue { plugin, .. }
+            | RuntimeError::MissingValue { plugin, .. }
+            | RuntimeError::TypeMismatch { plugin, .. }
+            | RuntimeError::Immutable { plugin, .. }
+            | RuntimeError::UnknownConversion { plugin, .. } => Some(plugin),
+            RuntimeError::PluginDisabled(name) => Some(name),
+            RuntimeError::NotSerializable(names) => match names.as_slice() {
+                [name] => Some(name),
+                _ => None,
+            },
+            RuntimeError::NoSuchPerson(_) | RuntimeError::InvalidCheckpoint(_) => None,
+        }
+    }
+}
+
+impl From<ConfigError> for InitError {
+    fn from(error: ConfigError) -> Self {
+        InitError::Config(error)
+    }
+}
+
+impl From<RuntimeError> for InitError {
+    fn from(error: RuntimeError) -> Self {
+        InitError::Runtime(error)
+    }
+}
+
+impl From<Box<MergeError>> for InitError {
+    fn from(error: Box<MergeError>) -> Self {
+        error.error
+    }
+}
+
+impl core::fmt::Debug for MergeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MergeError").field("error", &self.error).finish_non_exhaustive()
+    }
+}
+
+impl Display for MergeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl Display for InitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            InitError::Config(error) => error.fmt(f),
+            InitError::Runtime(error) => error.fmt(f),
+        }
+    }
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ConfigError::DuplicateName { namespace, name, count, origins } => {
+                match *namespace {
+                    "" => write!(f, "plugin name `{}` is used by {} plugins", name, count)?,
+                    namespace => write!(f, "plugin name `{}::{}` is used by {} plugins", namespace, name, count)?,
+                }
+                let known: Vec<String> =
+                    origins.iter().filter(|origin| !origin.is_empty()).map(|origin| format!("`{}`", origin)).collect();
+                if !known.is_empty() {
+                    write!(f, ", declared in {}", known.join(", "))?;
+                }
+                Ok(())
+            }
+            ConfigError::MissingDependency { plugin, needs } => {
+                write!(f, "plugin `{}` depends on `{}`, which is not registered", plugin, needs)
+            }
+            ConfigError::DisabledDependency { plugin, needs } => {
+                write!(f, "plugin `{}` depends on `{}`, which is disabled", plugin, needs)
+            }
+            ConfigError::DisabledDependencyChain { plugin, chain } => {
+                write!(f, "plugin `{}` depends on a disabled plugin: {} (disabled)", plugin, chain.join(" -> "))
+            }
+            ConfigError::ExcludedDependency { plugin, needs } => {
+                write!(f, "plugin `{}` depends on `{}`, which is excluded by its tags", plugin, needs)
+            }
+            ConfigError::DependencyTooOld { plugin, needs, found, required } => {
+                write!(
+                    f,
+                    "plugin `{}` needs `{}` version {}.{}.{} or later, but found version {}.{}.{}",
+                    plugin, needs, required.0, required.1, required.2, found.0, found.1, found.2
+                )
+            }
+            ConfigError::AmbiguousDependency { plugin, needs } => {
+                write!(f, "plugin `{}` depends on `{}`, which names plugins in several namespaces", plugin, needs)
+            }
+            ConfigError::DependencyCycle(path) => {
+                write!(f, "plugin dependencies form a cycle: {}", path.join(" -> "))
+            }
+            ConfigError::Conflict { plugin, other } => {
+                write!(f, "plugins `{}` and `{}` conflict and cannot both be enabled", plugin, other)
+            }
+            ConfigError::RequiredButDisabled(name) => {
+                write!(f, "plugin `{}` is required but not enabled", name)
+            }
+            ConfigError::BatchColumnCount { plugin, count } => {
+                write!(
+                    f,
+                    "plugin `{}` has a batch initializer, so it must register exactly one column, not {}",
+                    plugin, count
+                )
+            }
+            ConfigError::ForeignRegistration { plugin, registered } => {
+                write!(
+                    f,
+                    "the constructor of plugin `{}` registered the plugin `{}` instead of its own",
+                    plugin, registered
+                )
+            }
+            ConfigError::ReentrantBuild(name) => {
+                write!(f, "the constructor of plugin `{}` tried to build a context while being initialized", name)
+            }
+            ConfigError::CannotDisable(name) => {
+                write!(f, "plugin `{}` is unconditional and cannot be disabled", name)
+            }
+            ConfigError::DisableRequired(name) => {
+                write!(f, "plugin `{}` is required and cannot be disabled", name)
+            }
+            ConfigError::HasDependent { plugin, dependent } => {
+                write!(f, "plugin `{}` cannot be disabled because `{}` depends on it", plugin, dependent)
+            }
+            ConfigError::MergeMismatch(name) => {
+                write!(f, "plugin `{}` is not enabled and populated alike in both merged contexts", name)
+            }
+            ConfigError::UnknownPlugin(name) => {
+                write!(f, "no plugin named `{}` is registered", name)
+            }
+            ConfigError::AmbiguousName(name) => {
+                write!(f, "plugin name `{}` is ambiguous; qualify it as `namespace::{}`", name, name)
+            }
+            ConfigError::UnknownTag(tag) => {
+                write!(f, "no registered plugin is tagged `{}`", tag)
+            }
+            ConfigError::InvalidConfig { path, message } => {
+                write!(f, "invalid configuration file `{}`: {}", path, message)
+            }
+        }
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            RuntimeError::InitializerFailed { plugin, person_id, message } => {
+                write!(f, "failed to initialize plugin `{}` for person {}: {}", plugin, person_id, message)
+            }
+            RuntimeError::InvalidValue { plugin, person_id, message } => {
+                write!(f, "plugin `{}` has an invalid value for person {}: {}", plugin, person_id, message)
+            }
+            RuntimeError::MissingValue { plugin, person_id } => {
+                write!(f, "required plugin `{}` has no value for person {}", plugin, person_id)
+            }
+            RuntimeError::NoSuchPerson(person_id) => write!(f, "person {} does not exist", person_id),
+            RuntimeError::TypeMismatch { plugin, type_name } => {
+                write!(f, "plugin `{}` has no column of type `{}`", plugin, type_name)
+            }
+            RuntimeError::PluginDisabled(name) => {
+                write!(f, "plugin `{}` is not enabled", name)
+            }
+            RuntimeError::Immutable { plugin, person_id } => {
+                write!(f, "the value of plugin `{}` for person {} is immutable", plugin, person_id)
+            }
+            RuntimeError::NotSerializable(names) => {
+                let names: Vec<String> = names.iter().map(|name| format!("`{}`", name)).collect();
+                write!(f, "plugins {} have a column without a codec, so they cannot be saved", names.join(", "))
+            }
+            RuntimeError::UnknownConversion { plugin, unit } => {
+                write!(f, "plugin `{}` has no conversion to `{}`", plugin, unit)
+            }
+            RuntimeError::InvalidCheckpoint(message) => write!(f, "invalid checkpoint: {}", message),
+        }
+    }
+}
+
+impl Error for InitError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            InitError::Config(error) => Some(error),
+            InitError::Runtime(error) => Some(error),
+        }
+    }
+}
+
+impl Error for MergeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl Error for ConfigError {}
+
+impl Error for RuntimeError {}
+
+
+#[cfg(test)]
+mod test {
+    use std::error::Error;
+
+    use super::{ConfigError, InitError, RuntimeError};
+    use crate::{Context, ContextBuilder, PersonId, Plugin};
+
+    static NEEDY_PLUGINS: [Plugin; 1] = [Plugin::builder("Needy").dependencies(&["Absent"]).build()];
+
+    #[test]
+    fn configuration_mistakes_are_config_errors() {
+        let error = ContextBuilder::with_plugins(&NEEDY_PLUGINS).build().err().unwrap();
+        match &error {
+            InitError::Config(ConfigError::MissingDependency { plugin, needs }) => {
+                assert_eq!((*plugin, *needs), ("Needy", "Absent"));
+            }
+            error => panic!("expected a configuration error, got {:?}", error),
+        }
+        assert_eq!(error.plugin(), Some("Needy"));
+
+        let source = error.source().unwrap().downcast_ref::<ConfigError>().unwrap();
+        assert_eq!(source.to_string(), error.to_string());
+    }
+
+    #[test]
+    fn failures_on_people_are_runtime_errors() {
+        let mut context = Context::new();
+        let error = context.set::<u32>("Age", PersonId(3), 30).err().unwrap();
+        assert!(matches!(error, InitError::Runtime(RuntimeError::NoSuchPerson(PersonId(3)))));
+        assert_eq!(error.plugin(), None);
+        assert!(error.source().unwrap().is::<RuntimeError>());
+
+        context.create_person().unwrap();
+        let error = context.set::<String>("Age", PersonId(0), String::new()).err().unwrap();
+        assert!(matches!(error, InitError::Runtime(RuntimeError::TypeMismatch { .. })));
+        assert_eq!(error.plugin(), Some("Age"));
+    }
+}
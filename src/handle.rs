@@ -0,0 +1,90 @@
+//! Typed handles to property columns, for access without a name lookup.
+
+use core::marker::PhantomData;
+
+use crate::ColumnKey;
+
+/// Identifies the column of type `T` of one plugin, returned by `Context::handle`. `Context::get_by_handle` and
+/// `Context::set_by_handle` use it in place of a name, skipping the resolution of the name against every plugin that
+/// `Context::get` and `Context::set` repeat on each call, for tight loops. Only the lookup of the column by its
+/// precomputed key remains.
+///
+/// A handle stays valid for the `Context` that returned it, but only while the plugin is enabled: once
+/// `Context::disable` drops the column, `get_by_handle` returns `None` and `set_by_handle` fails until the plugin is
+/// enabled again. A handle must not be used with another `Context`.
+pub struct PropertyHandle<T> {
+    pub(crate) plugin: &'static str,
+    pub(crate) key: ColumnKey,
+    pub(crate) value_type: PhantomData<fn() -> T>,
+}
+
+// Implemented by hand, since deriving would require `T: Clone`.
+impl<T> Clone for PropertyHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for PropertyHandle<T> {}
+
+
+#[cfg(test)]
+mod test {
+    use crate::{ConfigError, Context, InitError, PersonId, Plugin, Property, RuntimeError};
+
+    #[test]
+    fn handles_read_and_write_like_names() {
+        let mut context = Context::new();
+        let people: Vec<PersonId> = (0..3).map(|_| context.create_person().unwrap()).collect();
+        let age = context.handle::<u32>("Age").unwrap();
+
+        context.set_by_handle(age, people[1], 30).unwrap();
+        assert_eq!(context.get_by_handle(age, people[1]), Some(&30));
+        assert_eq!(context.get::<u32>("Age", people[1]), Some(&30));
+        assert_eq!(context.get_by_handle(age, people[0]), context.get::<u32>("Age", people[0]));
+
+        context.remove_person(people[2]).unwrap();
+        assert_eq!(context.get_by_handle(age, people[2]), None);
+        assert_eq!(
+            context.set_by_handle(age, people[2], 1),
+            Err(InitError::Runtime(RuntimeError::NoSuchPerson(people[2])))
+        );
+
+        assert_eq!(
+            context.handle::<u8>("Age").err(),
+            Some(InitError::Runtime(RuntimeError::TypeMismatch { plugin: "Age", type_name: "u8" }))
+        );
+        assert_eq!(
+            context.handle::<u32>("Nobody").err(),
+            Some(InitError::Config(ConfigError::UnknownPlugin("Nobody".to_string())))
+        );
+    }
+
+    static SCORE: Property<i32> = Property{
+        name: "Score",
+        default: 5,
+        initializer: None,
+    };
+
+    static SCORE_PLUGINS: [Plugin; 1] = [Plugin::builder(SCORE.name)
+        .initializer(|context, person_id| SCORE.initialize(context, person_id))
+        .constructor(|context| SCORE.construct(context))
+        .build()];
+
+    #[test]
+    fn handles_stop_working_while_the_plugin_is_disabled() {
+        let mut context = Context::with_plugins(&SCORE_PLUGINS);
+        let person_id = context.create_person().unwrap();
+        let score = context.handle::<i32>("Score").unwrap();
+
+        context.disable("Score").unwrap();
+        assert_eq!(context.get_by_handle(score, person_id), None);
+        let disabled = InitError::Runtime(RuntimeError::PluginDisabled("Score"));
+        assert_eq!(context.set_by_handle(score, person_id, 1), Err(disabled.clone()));
+        assert_eq!(context.handle::<i32>("Score").err(), Some(disabled));
+
+        context.enable("Score").unwrap();
+        context.backfill("Score").unwrap();
+        assert_eq!(context.get_by_handle(score, person_id), Some(&5));
+    }
+}
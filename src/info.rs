@@ -0,0 +1,120 @@
+//! A public summary of a plugin's metadata, for exposing a model's plugins to other programs.
+
+use alloc::string::String;
+use core::fmt::Write;
+
+use crate::snapshot::write_json_string;
+use crate::{PluginInstance, Version};
+
+/// The metadata of one plugin a `Context` was built from, returned by `Context::describe` and
+/// `Context::describe_all`. Unlike `Plugin`, this holds no functions, so its shape does not change when the plugin
+/// gains new hooks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PluginInfo {
+    pub name: &'static str,
+    pub namespace: &'static str,
+    pub description: &'static str,
+    pub unit: Option<&'static str>,
+    pub required: bool,
+    /// Whether the plugin is enabled, taking `ContextBuilder` overrides and later calls to `Context::enable` and
+    /// `Context::disable` into account.
+    pub enabled: bool,
+    pub tags: &'static [&'static str],
+    /// The plugin's hard `dependencies`, in declaration order.
+    pub dependencies: &'static [&'static str],
+    pub version: Version,
+}
+
+impl PluginInfo {
+    /// Serializes the summary as a JSON object with the fields of `PluginInfo`, the `version` being written as a
+    /// `"major.minor.patch"` string and a missing `unit` as `null`.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\"name\":");
+        write_json_string(&mut json, self.name);
+        json.push_str(",\"namespace\":");
+        write_json_string(&mut json, self.namespace);
+        json.push_str(",\"description\":");
+        write_json_string(&mut json, self.description);
+        json.push_str(",\"unit\":");
+        match self.unit {
+            Some(unit) => write_json_string(&mut json, unit),
+            None => json.push_str("null"),
+        }
+        write!(json, ",\"required\":{},\"enabled\":{}", self.required, self.enabled).unwrap();
+        json.push_str(",\"tags\":");
+        write_json_strings(&mut json, self.tags);
+        json.push_str(",\"dependencies\":");
+        write_json_strings(&mut json, self.dependencies);
+        let (major, minor, patch) = self.version;
+        write!(json, ",\"version\":\"{}.{}.{}\"}}", major, minor, patch).unwrap();
+        json
+    }
+}
+
+impl From<&PluginInstance> for PluginInfo {
+    fn from(instance: &PluginInstance) -> Self {
+        let plugin = instance.plugin;
+        PluginInfo {
+            name: plugin.name,
+            namespace: plugin.namespace,
+            description: plugin.description,
+            unit: plugin.unit,
+            required: plugin.required,
+            enabled: instance.enabled,
+            tags: plugin.tags,
+            dependencies: plugin.dependencies,
+            version: plugin.version,
+        }
+    }
+}
+
+/// Appends `values` to `json` as a JSON array of strings.
+fn write_json_strings(json: &mut String, values: &[&str]) {
+    json.push('[');
+    for (index, value) in values.iter().enumerate() {
+        if index > 0 {
+            json.push(',');
+        }
+        write_json_string(json, value);
+    }
+    json.push(']');
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::PluginInfo;
+    use crate::{ContextBuilder, Plugin};
+
+    static HEIGHT_PLUGINS: [Plugin; 1] = [Plugin::builder("Height")
+        .namespace("anthropometry")
+        .description("Height of the person")
+        .tags(&["anthropometric"])
+        .build()];
+
+    #[test]
+    fn describe_summarizes_the_plugin() {
+        let mut context = ContextBuilder::with_plugins(&HEIGHT_PLUGINS).disable("Height").build().unwrap();
+        let info = PluginInfo {
+            name: "Height",
+            namespace: "anthropometry",
+            description: "Height of the person",
+            unit: None,
+            required: false,
+            enabled: false,
+            tags: &["anthropometric"],
+            dependencies: &[],
+            version: (1, 0, 0),
+        };
+        assert_eq!(context.describe("Height"), Some(info.clone()));
+        assert_eq!(context.describe("Heigth"), None);
+        let json = concat!(
+            r#"{"name":"Height","namespace":"anthropometry","description":"Height of the person","unit":null,"#,
+            r#""required":false,"enabled":false,"tags":["anthropometric"],"dependencies":[],"version":"1.0.0"}"#,
+        );
+        assert_eq!(info.to_json(), json);
+
+        context.enable("Height").unwrap();
+        assert_eq!(context.describe_all(), vec![PluginInfo { enabled: true, ..info }]);
+    }
+}
@@ -9,15 +9,16 @@ Demonstrates compile time registration, potentially across crate boundaries.
 - Modules requiring a particular plugin must enforce this requirement in their `init(context: &mut Context)` function.
 - Thus, model authors must perform module configuration prior to `init()` being called if a module allows
   disabling/enabling a plugin.
-- The `init()` functions are called in arbitrary order in the constructor of `Context`--although we could have a
-  dependency mechanism if we want.
+  `ContextBuilder` records such configuration, for example `Context::builder().disable("Waist").build()`.
+- The `init()` functions are called in dependency order in the constructor of `Context`. A plugin lists the names of
+  the plugins that must be initialized before it in `Plugin::dependencies`.
 - In this design, it is the `init` functions that are statically registered, but this is an implementation detail.
 
 ### Application to Entity Properties
 
 A module "owns" the properties declared within it by _convention_.
 
-- Opt in/out mechanism is determined by the module itself.
+- Opt in/out mechanism is determined by the module itself, through `Plugin::default_state`.
     - plugins can be unconditionally enabled by the module
     - can be opt-out (if module supports it)
     - can be opt-in (if module supports it)
@@ -28,12 +29,40 @@ A module "owns" the properties declared within it by _convention_.
 #### Q: If we require every module to have an `init()`, what's the point of statically defining the plugins?
 
 A: The `init()` functions can be declared anywhere in the codebase, including across crate boundaries, and called
-automatically in the constructor for `Context`. In the implementation below, we use the `Property` type, which has an
-`init` method.
+automatically in the constructor for `Context`. In the implementation below, we use the `Plugin` type, which has an
+`init` method. A typed `Property<T>` supplies the default value and the column storage that its plugin delegates to.
 
 
-Open questions:
-- What happens with naming conflicts?
+#### Q: What happens with naming conflicts?
+
+A: Each plugin has a `namespace`, usually the name of the crate that registers it, and the pair of namespace and name
+must be unique across every crate that registers into `PLUGINS`. The constructor of `Context` checks this before calling
+any `init()` and fails on a colliding pair. Plugins are looked up by a qualified `"namespace::name"`, or by their bare
+name when no other plugin shares it.
+
+Since `linkme` only assembles `PLUGINS` at link time, no `const` evaluation can see every name. Instead, each
+`register_plugin!` invocation also generates a `#[test]` that calls `check_unique_names` on `PLUGINS`, so a collision
+fails `cargo test` in every crate that declares plugins, before any model runs.
+
+#### Q: Can a plugin be left out of a build?
+
+A: Putting `#[cfg(feature = "...")]` on the `register_plugin!` invocation compiles the plugin out when the feature is
+off, as if it had never been declared. Other plugins that must work either way name it in `optional_dependencies`,
+which are ordered before them when present and are not reported when missing, rather than in `dependencies`.
+
+## `no_std`
+
+The plugin registry, the column store and `ContextBuilder` only need `alloc`. Building without the default `std`
+feature makes the crate `no_std` and leaves out what needs the operating system: configuration files, checkpoints, CSV
+export, the timings in `InitMetrics`, and the threads of `Context::create_people`, whose batch initializers then run
+one after another.
+
+## Dependencies
+
+Besides `linkme` and the `initialization_derive` macros, the crate has no dependencies, so that it builds quickly and
+under `no_std`. The JSON of `ConfigSnapshot::to_json` and `PluginInfo::to_json`, the CSV of `Context::export_csv`, the
+checkpoint format, the configuration parsers and the random number generator are therefore written by hand rather than
+with `serde` or `rand`, and no type implements `serde::Serialize`.
 
 ## Implementation Mechanism
 
@@ -42,96 +71,4047 @@ data plugins, etc.).
 
 */
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::any::{type_name, Any, TypeId};
+use core::marker::PhantomData;
+use core::ops::Range;
+#[cfg(feature = "std")]
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::io::{self, Read, Write};
+#[cfg(feature = "std")]
+use std::panic::{self, AssertUnwindSafe};
+#[cfg(feature = "std")]
+use std::thread;
+#[cfg(feature = "std")]
+use std::time::Instant;
 
 use linkme::distributed_slice;
 
+// Used by `register_plugin!`, so that crates using the macro need not depend on `linkme` themselves.
+#[doc(hidden)]
+pub use linkme as __linkme;
+
+mod builder;
+#[cfg(feature = "std")]
+mod checkpoint;
+mod column;
+#[cfg(feature = "std")]
+mod config;
+#[cfg(feature = "std")]
+mod csv;
+mod error;
+mod handle;
+mod info;
+mod macros;
+mod metrics;
+mod person;
+mod plugin_builder;
+mod property;
+mod rng;
+mod snapshot;
+mod store;
+mod warning;
+
+use column::{store, store_mut, Bitset, Column};
+
+use builder::{check_conflicts, default_skip_reason, dependency_order, duplicate_names, optional_dependency_warnings};
+
+pub use builder::{check_unique_names, ContextBuilder, UnknownPlugins};
+pub use error::{ConfigError, InitError, MergeError, RuntimeError};
+pub use handle::PropertyHandle;
+/// Declares a property plugin from a type, as a shorthand for a `Property` and a `register_plugin!` invocation. The
+/// `#[plugin(...)]` attribute gives the `value` type of the plugin's column and its `default`, and optionally its
+/// `name`, which defaults to the name of the type, a `description`, a `unit`, `required`, and `dependencies(...)`.
+/// The type gains a `NAME` constant for looking up the values:
+///
+/// ```standalone_crate
+/// use initialization::{Context, Plugin};
+///
+/// #[derive(Plugin)]
+/// #[plugin(value = f64, default = 170.0, description = "Height of the person in cm", required)]
+/// struct Height;
+///
+/// let mut context = Context::new();
+/// let person_id = context.create_person().unwrap();
+/// assert_eq!(context.get::<f64>(Height::NAME, person_id), Some(&170.0));
+/// ```
+///
+/// The plugin's values are initialized to `default` in batches, so the derive does not support an `initializer`
+/// computed from other plugins; write out the `Property` and `register_plugin!` for that.
+pub use info::PluginInfo;
+pub use initialization_derive::Plugin;
+pub use metrics::{InitMetrics, PluginState, SkipReason};
+pub use person::{PersonId, PersonRange, RemapTable};
+pub use plugin_builder::PluginBuilder;
+pub use property::{Property, PropertySpec};
+pub use rng::Rng;
+pub use snapshot::{ConfigSnapshot, PluginConfig};
+pub use store::PropertyStore;
+pub use warning::Warning;
+
 /// There are a million ways to do this. In this simple example we just have a `Plugin` type. This array is GLOBAL and 
-/// determined at compile time.
+/// determined at compile time. It is public for `register_plugin!`, but enumerate it with `all_plugins`, which does not
+/// depend on how the registry is assembled.
 #[distributed_slice]
 pub static PLUGINS: [Plugin];
 
+/// Every plugin in the global `PLUGINS` registry, from every linked crate, whether or not it is enabled.
+pub fn all_plugins() -> impl Iterator<Item = &'static Plugin> {
+    PLUGINS.static_slice().iter()
+}
+
+/// A plugin version, `(major, minor, patch)`, compared lexicographically. See `Plugin::dependency_versions`.
+pub type Version = (u16, u16, u16);
+
+/// Called with the `Context` and the plugin before or after each `plugin.init`. See `ContextBuilder::before_each`.
+pub type InitHook = fn(&Context, &Plugin);
+
+/// Initializes one plugin's column, passed as `&mut dyn Any`, for a range of people. See `Plugin::batch_initializer`.
+pub type BatchInitializer = fn(column: &mut dyn Any, people: Range<usize>) -> Result<(), InitError>;
+
+/// Checks a plugin's values for a new person, failing, for example with `RuntimeError::InvalidValue`, if they are
+/// invalid. See `Plugin::validator`.
+pub type Validator = fn(&Context, person_id: PersonId) -> Result<(), InitError>;
+
 /// In this example, the `Plugin` type holds configuration that can affect what happens when `init` is called. The
 /// constructor of `Context` iterates over all plugins in the static "Distributed Slice", calling `plugin.init(context)`
-/// with each `plugin`.
+/// with each enabled `plugin`.
+///
+/// A plugin literal should end in `..Plugin::DEFAULT` and spell out only the fields that differ from the defaults, as
+/// `register_plugin!` and `Plugin::builder` do for it. Every new field gets a default there, so adding one does not
+/// break existing plugins.
 pub struct Plugin {
     pub name: &'static str,
+    /// The crate that registers the plugin, usually `env!("CARGO_PKG_NAME")`, so that plugins from different crates
+    /// may share a `name`. Empty for no namespace.
+    pub namespace: &'static str,
+    /// The module that declares the plugin, usually `module_path!()`, which `register_plugin!` fills in, so that error
+    /// messages and `Context::init_report` can say where each plugin comes from. Empty if unknown.
+    pub origin: &'static str,
+    /// Former names that still refer to this plugin everywhere a name is looked up, such as in `Context::get_plugin`,
+    /// `ContextBuilder` overrides and configuration files, and `dependencies`. Each must be unique among the names and
+    /// aliases of the plugins in the same namespace.
+    pub aliases: &'static [&'static str],
     pub description: &'static str,
+    /// The unit of the plugin's values, such as `"lbs"`, for converting and displaying them. Shown in `PluginInfo`
+    /// and in the headers of `Context::export_csv`.
+    pub unit: Option<&'static str>,
     /// Required means must have a value for every entity
     pub required: bool,
-    /// Enabled means this property is instantiated in the `Context`
+    /// Immutable means the value computed by the initializer is final, such as a birth cohort: `Context::set` and
+    /// `Context::set_by_handle` fail with `RuntimeError::Immutable` instead of replacing it. A value left unset by
+    /// `Context::unset` may still be set once, for a property given its value later than its initializer runs.
+    pub immutable: bool,
+    /// Enabled means this property is instantiated in the `Context`. This is the default for an `OptOut` plugin, and is
+    /// ignored for `Unconditional` and `OptIn` plugins.
     pub enabled: bool,
-    /// The initializer knows how to compute the first value assigned to an entity
-    pub initializer: fn(&mut Context, person_id: usize),
+    /// Whether the plugin is enabled unless a model author says otherwise, and whether they may say otherwise.
+    pub default_state: DefaultState,
+    /// The initializer knows how to compute the first value assigned to an entity. It fails, for example with
+    /// `RuntimeError::InitializerFailed`, if the value cannot be computed. It may compute the value from the entity's
+    /// values of other plugins, read with `Context::get`, if they are among its `dependencies`: their initializers are
+    /// guaranteed to have run for the entity first.
+    pub initializer: fn(&mut Context, person_id: PersonId) -> Result<(), InitError>,
+    /// Optionally, initializes the plugin's column for a range of people at once, without access to the rest of the
+    /// `Context`, so that `Context::create_people` can run the batch initializers of different plugins in parallel.
+    /// The column is passed as `&mut dyn Any` to be downcast to `Vec<T>`, or to the `Box<dyn PropertyStore<T> + Send>`
+    /// of a column registered with `Context::register_property_store`, and the plugin must register exactly one
+    /// column. It must produce the same values as `initializer`.
+    pub batch_initializer: Option<BatchInitializer>,
+    /// Names of the plugins that must be initialized before this one. Every dependency must be present and enabled.
+    pub dependencies: &'static [&'static str],
+    /// Names of plugins that must be initialized before this one if they are enabled. Unlike `dependencies`, they may
+    /// be missing or disabled.
+    pub optional_dependencies: &'static [&'static str],
+    /// The minimum version of some of the `dependencies` and `optional_dependencies`, named the same way. A dependency
+    /// that is present, or an optional dependency that is enabled, must be at least this version.
+    pub dependency_versions: &'static [(&'static str, Version)],
+    /// Names of plugins that must not be enabled together with this one, named the same way as `dependencies`. It is
+    /// enough for one of the two plugins to declare the conflict.
+    pub conflicts_with: &'static [&'static str],
+    /// Plugins with a lower priority are initialized first, unless a dependency requires otherwise. Use `0` unless a
+    /// plugin needs to run before (negative) or after (positive) the others.
+    pub priority: i32,
+    /// The `(major, minor, patch)` version of the plugin, checked against the `dependency_versions` of its dependents.
+    pub version: Version,
+    /// Categories used to group related plugins, for example `"demographic"`, so they can be queried or disabled
+    /// together.
+    pub tags: &'static [&'static str],
     //... etc.
     
-    pub constructor: fn(&mut Context)
+    /// Registers the plugin's columns with the `Context` being built. The `Context` has already registered the plugin
+    /// itself, so calling `Context::register_plugin` on it is allowed but unnecessary. It must only change that
+    /// `Context`, never global mutable state, so that several contexts built from the same plugins stay independent
+    /// of each other.
+    pub constructor: fn(&mut Context),
+    /// Optionally, releases resources the plugin acquired, such as files or caches, when `Context::shutdown` is
+    /// called or the plugin is disabled by `Context::disable`. The plugin's columns are still registered.
+    pub teardown: Option<fn(&mut Context)>,
+    /// Optionally, reacts to a new person once every enabled plugin has initialized their values, for example to index
+    /// the person in a spatial structure. Called by `Context::create_person` and `Context::create_people` for each new
+    /// person, in initialization order, unlike `initializer`, which only sees the values of the dependencies.
+    pub on_person_created: Option<fn(&mut Context, person_id: PersonId)>,
+    /// Optionally, checks the plugin's values for a new person right after they are computed, such as that an age is
+    /// neither negative nor absurdly large. It fails, for example with `RuntimeError::InvalidValue`, if they are
+    /// invalid. The person is then not created, and `Context::create_person` and `Context::create_people` fail with
+    /// the validator's error.
+    pub validator: Option<Validator>,
+    /// Further properties the plugin contributes, each with columns of its own, for a module owning several related
+    /// properties of the same type. See `PropertySpec`.
+    pub properties: &'static [PropertySpec],
 }
 
 impl Plugin {
+    /// The defaults for every field: no name, namespace, description, unit, dependencies, columns or further
+    /// `properties`; enabled, neither required nor immutable, `DefaultState::OptOut` and version 1.0.0; an initializer
+    /// and constructor that do nothing; and no teardown, `on_person_created` or validator. Meant for struct update
+    /// syntax, such as `Plugin{ name: "Height", ..Plugin::DEFAULT }`.
+    pub const DEFAULT: Plugin = Plugin{
+        name: "",
+        namespace: "",
+        origin: "",
+        aliases: &[],
+        description: "",
+        unit: None,
+        required: false,
+        immutable: false,
+        enabled: true,
+        initializer: |_context, _person_id| Ok(()),
+        dependencies: &[],
+        optional_dependencies: &[],
+        dependency_versions: &[],
+        conflicts_with: &[],
+        priority: 0,
+        version: (1, 0, 0),
+        tags: &[],
+        default_state: DefaultState::OptOut,
+        batch_initializer: None,
+        teardown: None,
+        on_person_created: None,
+        validator: None,
+        properties: &[],
+        constructor: |_context| {},
+    };
+
+    /// A `const` builder for a plugin named `name`, starting from defaults. See `PluginBuilder`.
+    pub const fn builder(name: &'static str) -> PluginBuilder {
+        PluginBuilder::new(name)
+    }
+
+    /// Sets up the plugin in `context` by running its `constructor` and those of its `properties`. This does not check
+    /// whether the plugin is enabled: `ContextBuilder::build` and `Context::enable` decide that, and only call `init`
+    /// for plugins that are, so a disabled plugin's `constructor` never runs.
     pub fn init(&self, context: &mut Context) {
-        // Maybe there is a PluginInstance type that gets stored in `context`, or maybe
-        // there is an api for configuring entity properties that this method interacts with,
-        // or....
+        // The `Context` records a `PluginInstance` for every plugin, or there could be an api for configuring entity
+        // properties that this method interacts with, or....
         //
-        // This is also where the database of property metadata would be initialized: TypeId->metadata
+        // This is also where the database of property metadata would be initialized: TypeId->metadata. For now the
+        // constructor registers the typed column holding the plugin's values with `Context::register_property`.
         (self.constructor)(context);
+        for property in self.properties {
+            (property.constructor)(context);
+        }
+    }
+
+    /// The name qualified by the namespace, such as `"my_plugin::Weight"`, or just the name if there is no
+    /// namespace.
+    pub fn qualified_name(&self) -> String {
+        if self.namespace.is_empty() {
+            self.name.to_string()
+        } else {
+            format!("{}::{}", self.namespace, self.name)
+        }
+    }
+
+    /// Whether `name` refers to this plugin, either as a qualified `"namespace::name"` or as a bare name. The name
+    /// may also be one of the `aliases`.
+    pub fn is_named(&self, name: &str) -> bool {
+        let (namespace, name) = match name.rsplit_once("::") {
+            Some((namespace, name)) => (Some(namespace), name),
+            None => (None, name),
+        };
+        namespace.is_none_or(|namespace| namespace == self.namespace)
+            && (self.name == name || self.aliases.contains(&name))
+    }
+
+    /// The key plugins are sorted by: the name, then the namespace.
+    fn sort_key(&self) -> (&'static str, &'static str) {
+        (self.name, self.namespace)
+    }
+
+    /// Whether `owner`, the first half of a `ColumnKey`, belongs to this plugin, either as its own `sort_key` or as
+    /// the name of one of its `properties` in its namespace.
+    fn owns(&self, owner: (&str, &str)) -> bool {
+        owner == self.sort_key() || self.properties.iter().any(|property| owner == (property.name, self.namespace))
+    }
+}
+
+/// Finds the single plugin in `plugins` that `name` refers to. A bare name shared by plugins in several namespaces is
+/// ambiguous and finds nothing.
+fn resolve<T>(plugins: &[T], plugin: impl Fn(&T) -> &Plugin, name: &str) -> Option<usize> {
+    let mut matches = plugins
+        .iter()
+        .enumerate()
+        .filter(|(_, candidate)| plugin(candidate).is_named(name))
+        .map(|(index, _)| index);
+    match (matches.next(), matches.next()) {
+        (Some(index), None) => Some(index),
+        _ => None,
+    }
+}
+
+/// The columns of the batch plugins that `Context::create_people` takes out of the store, each with its plugin.
+type Batches = [(&'static Plugin, ColumnKey, Box<dyn Column>)];
+
+/// Runs the `batch_initializer` of each batch plugin over `people`, each on its own thread, and reports the first error
+/// in plugin order once every thread has been joined.
+#[cfg(feature = "std")]
+fn run_batches(batches: &mut Batches, people: Range<usize>) -> Result<(), InitError> {
+    thread::scope(|scope| {
+        let handles: Vec<_> = batches
+            .iter_mut()
+            .map(|(plugin, _, column)| {
+                let batch_initializer = plugin.batch_initializer.unwrap();
+                let people = people.clone();
+                scope.spawn(move || {
+                    let column: &mut dyn Any = column.as_mut();
+                    batch_initializer(column, people)
+                })
+            })
+            .collect();
+        // Join every thread before reporting the first error in plugin order.
+        let results: Vec<Result<(), InitError>> = handles
+            .into_iter()
+            .map(|handle| handle.join().expect("a batch initializer panicked"))
+            .collect();
+        results.into_iter().collect::<Result<(), InitError>>()
+    })
+}
+
+/// Without `std` there are no threads, so the batch initializers run one after another in plugin order.
+#[cfg(not(feature = "std"))]
+fn run_batches(batches: &mut Batches, people: Range<usize>) -> Result<(), InitError> {
+    batches.iter_mut().try_for_each(|(plugin, _, column)| {
+        let column: &mut dyn Any = column.as_mut();
+        (plugin.batch_initializer.unwrap())(column, people.clone())
+    })
+}
+
+/// How a module lets model authors opt in to or out of one of its plugins through `ContextBuilder`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DefaultState {
+    /// Always enabled. Disabling the plugin by name is an error, and disabling one of its tags leaves it enabled.
+    Unconditional,
+    /// Disabled unless explicitly enabled.
+    OptIn,
+    /// Enabled, or rather as the plugin's `enabled` flag says, unless explicitly disabled.
+    OptOut,
+}
+
+/// Formats the value at an index of a type-erased column as a CSV cell, or returns `None` if there is no value there.
+type Formatter = Box<dyn Fn(&dyn Any, usize) -> Option<String> + Send + Sync>;
+
+/// Overwrites the values at the given indices of a type-erased column with a default set by
+/// `ContextBuilder::set_default`.
+type DefaultOverride = Box<dyn Fn(&mut dyn Any, Range<usize>) + Send + Sync>;
+
+/// Replaces the value at an index of a type-erased column with a blank, dropping the value it held.
+type Reset = Box<dyn Fn(&mut dyn Any, usize) + Send + Sync>;
+
+/// Reacts to a change of a plugin's value for a person. See `Context::observe`.
+type Observer = fn(&mut Context, PersonId);
+
+/// Converts a value from a plugin's `unit` to another unit. See `Context::register_conversion`.
+type Conversion = fn(f64) -> f64;
+
+/// Encodes every value of a type-erased column.
+type Encoder = Box<dyn Fn(&dyn Any) -> Vec<String> + Send + Sync>;
+
+/// Replaces the values of a type-erased column with the decoded values, or returns the first value that cannot be
+/// decoded.
+type Decoder = Box<dyn Fn(&mut dyn Any, &[String]) -> Result<(), String> + Send + Sync>;
+
+/// Encodes and decodes the values of a type-erased column for `Context::save` and `Context::load`. Without `std` there
+/// are no checkpoints to read the codecs, but `Context::register_codec` stays so that constructors compile either way.
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+struct Codec {
+    /// The `std::any::type_name` of the values, which identifies the column in a checkpoint.
+    type_name: &'static str,
+    encode: Encoder,
+    decode: Decoder,
+}
+
+/// Identifies a column by the `Plugin::sort_key` of the plugin that registered it and its value type.
+type ColumnKey = ((&'static str, &'static str), TypeId);
+
+/// The runtime state of a plugin in a particular `Context`, as opposed to the static `Plugin` descriptor shared by
+/// every `Context`.
+#[derive(Clone)]
+pub struct PluginInstance {
+    pub plugin: &'static Plugin,
+    /// Whether the plugin was initialized, taking `ContextBuilder` overrides into account.
+    pub enabled: bool,
+    /// The types of the columns the plugin registered during `init`. Empty if the plugin is disabled.
+    pub columns: Vec<TypeId>,
+    /// The name and type of each column registered for one of the plugin's `properties` during `init`. Empty if the
+    /// plugin is disabled.
+    pub property_columns: Vec<(&'static str, TypeId)>,
+    /// Why the plugin is disabled, or `None` if it is enabled.
+    pub skip_reason: Option<SkipReason>,
+}
+
+impl PluginInstance {
+    /// Records the keys of the columns the plugin registered during `init`, as its own `columns` and its
+    /// `property_columns`.
+    fn record_columns(&mut self, keys: &[ColumnKey]) {
+        let sort_key = self.plugin.sort_key();
+        self.columns = keys.iter().filter(|key| key.0 == sort_key).map(|&(_, type_id)| type_id).collect();
+        self.property_columns = keys
+            .iter()
+            .filter(|key| key.0 != sort_key)
+            .map(|&((name, _), type_id)| (name, type_id))
+            .collect();
+    }
+
+    /// The keys of every column the plugin registered, its own `columns` and then its `property_columns`.
+    fn column_keys(&self) -> impl Iterator<Item = ColumnKey> + '_ {
+        let sort_key = self.plugin.sort_key();
+        let own = self.columns.iter().map(move |&type_id| (sort_key, type_id));
+        own.chain(self.property_columns.iter().map(move |&(name, type_id)| ((name, sort_key.1), type_id)))
     }
 }
 
 #[derive(Default)]
 pub struct Context {
-    pub plugins: Vec<&'static str>
+    /// An instance for every plugin the `Context` was built from, whether or not it is enabled, sorted by name and then
+    /// namespace.
+    instances: Vec<PluginInstance>,
+    /// The initialized plugins, sorted by name and then namespace so that the order is reproducible regardless of link
+    /// order. This is not the order in which the plugins were initialized.
+    pub plugins: Vec<&'static Plugin>,
+    /// The enabled plugins whose initializers run for each new person, in initialization order.
+    active: Vec<&'static Plugin>,
+    /// Plugins enabled by `Context::enable` after people were created. Their initializers do not run until the plugin
+    /// is backfilled by `Context::backfill`, since its columns have no values for the existing people.
+    unpopulated: Vec<&'static Plugin>,
+    /// The number of `person_id`s handed out so far, including those of removed people. Unless a removed id is
+    /// reused, this is the next `person_id`.
+    person_count: usize,
+    /// Whether `Context::shutdown` has run, so that dropping the `Context` does not tear the plugins down again.
+    shut_down: bool,
+    /// The plugin whose `init` is running, so that `Context::register_plugin` can tell its own registration from
+    /// another's.
+    initializing: Option<&'static Plugin>,
+    /// The first plugin other than `initializing` that its constructor registered, reported by `Context::init_plugin`
+    /// as `ConfigError::ForeignRegistration`.
+    foreign: Option<&'static Plugin>,
+    /// The number of people every column registered by `Context::register_property` has room for from the start. See
+    /// `ContextBuilder::capacity`.
+    capacity: usize,
+    /// The ids of people removed by `Context::remove_person`, which `Context::create_person` reuses smallest first.
+    removed: BTreeSet<usize>,
+    /// Per-entity property values, one column per plugin and value type. The column of plugin `name` for `T` is a
+    /// `Vec<T>` indexed by `person_id`.
+    columns: BTreeMap<ColumnKey, Box<dyn Column>>,
+    /// The people whose slot in a column holds only a placeholder, marked by `Context::unset`. Columns in which no slot
+    /// was ever unset have no entry.
+    unset: BTreeMap<ColumnKey, Bitset>,
+    /// How `Context::save` and `Context::load` encode and decode each column.
+    codecs: BTreeMap<ColumnKey, Codec>,
+    /// The defaults set by `ContextBuilder::set_default`, keyed like the columns they overwrite.
+    defaults: BTreeMap<ColumnKey, DefaultOverride>,
+    /// How `Context::remove_person` clears each column's slot, set by `Context::register_reset`.
+    resets: BTreeMap<ColumnKey, Reset>,
+    /// How `Context::export_csv` formats each plugin's values, keyed by the plugin's `sort_key` or, for one of its
+    /// `properties`, the first half of the property's `ColumnKey`, with the type of the column it formats.
+    formatters: BTreeMap<(&'static str, &'static str), (TypeId, Formatter)>,
+    /// How `Context::get_as` converts each plugin's values from its `unit`, keyed by the plugin's `sort_key` and the
+    /// target unit.
+    conversions: BTreeMap<((&'static str, &'static str), &'static str), Conversion>,
+    /// The observers of each plugin's values, keyed by the plugin's `sort_key`, in registration order. See
+    /// `Context::observe`.
+    observers: BTreeMap<(&'static str, &'static str), Vec<Observer>>,
+    /// Whether observers are running, in which case the values they set notify no further observers.
+    notifying: bool,
+    metrics: InitMetrics,
+    /// Draws random values for initializers, seeded by `ContextBuilder::seed`.
+    rng: Rng,
+    /// State shared by the whole `Context` rather than held per entity, one value per type. See `Context::global_mut`.
+    globals: BTreeMap<TypeId, Box<dyn Any + Send + Sync>>,
+    /// Values shared by the initializers of the person being initialized, one per type, emptied once the person is
+    /// initialized. See `Context::scratch_mut`.
+    scratch: BTreeMap<TypeId, Box<dyn Any + Send + Sync>>,
+    /// The name of every plugin whose `init` was called, in call order.
+    init_order: Vec<&'static str>,
+    /// Non-fatal problems noticed during construction. The `OptionalDependencyDisabled` warnings are kept up to date
+    /// by `Context::enable` and `Context::disable`.
+    warnings: Vec<Warning>,
+    /// Called before each `plugin.init`, in registration order.
+    before_each: Vec<InitHook>,
+    /// Called after each `plugin.init`, in registration order.
+    after_each: Vec<InitHook>,
+    /// The time `plugin.init` may take before it is reported as a `Warning::SlowInit`. See `ContextBuilder::warn_slow`.
+    #[cfg(feature = "std")]
+    slow_threshold: Option<Duration>,
 }
 
 impl Context {
+    /// Constructs a `Context` from the global `PLUGINS` registry, panicking if the registered plugins are invalid. See
+    /// `Context::try_new`.
     pub fn new() -> Self {
-        let mut context = Context::default();
-        for plugin in PLUGINS.iter() {
-            plugin.init(&mut context);
-        }
+        Context::try_new().unwrap_or_else(|error| panic!("{}", error))
+    }
 
-        // If we validate dependency constraints, we would do it here, because the iteration order is nondeterministic.
+    /// Constructs a `Context` from the global `PLUGINS` registry. The enabled plugins are initialized in dependency
+    /// order. An empty registry is valid and gives a `Context` without plugins, in which people have no properties.
+    ///
+    /// Fails if two plugins share a name in the same namespace, if a plugin is declared both `required` and not
+    /// `enabled`, if an enabled plugin depends on a plugin that is missing or disabled, or if the dependencies form a
+    /// cycle. No plugin is initialized if validation fails.
+    pub fn try_new() -> Result<Self, InitError> {
+        Context::try_with_plugins(PLUGINS.static_slice())
+    }
 
+    /// Like `Context::new`, but first checks that the global `PLUGINS` registry holds no duplicate names and contains a
+    /// plugin for each of the `expected` names, whether or not it is enabled. A test can thus prove that the plugins
+    /// of every crate it links are registered. Available in this crate's tests and with the `test-utils` feature.
+    ///
+    /// Panics with the registered names if an expected plugin is missing.
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn new_for_test(expected: &[&str]) -> Self {
+        check_unique_names(PLUGINS.static_slice()).unwrap_or_else(|error| panic!("{}", error));
+        let context = Context::new();
+        let missing: Vec<&str> = expected.iter().copied().filter(|name| context.instance(name).is_none()).collect();
+        if !missing.is_empty() {
+            let registered: Vec<String> =
+                context.instances.iter().map(|instance| instance.plugin.qualified_name()).collect();
+            panic!("plugins {:?} are not registered; the registered plugins are {:?}", missing, registered);
+        }
         context
     }
 
-    pub fn register_plugin(&mut self, plugin: &Plugin) {
-        self.plugins.push(plugin.name);
+    /// Like `Context::new`, but constructs the `Context` from an arbitrary set of plugins rather than the global
+    /// `PLUGINS` registry.
+    pub fn with_plugins(plugins: &'static [Plugin]) -> Self {
+        Context::try_with_plugins(plugins).unwrap_or_else(|error| panic!("{}", error))
     }
-}
 
+    /// Like `Context::new`, but every property column has room for `capacity` people from the start, so creating up
+    /// to that many people does not reallocate the columns. See `ContextBuilder::capacity`.
+    pub fn with_capacity(capacity: usize) -> Self {
+        ContextBuilder::new().capacity(capacity).build().unwrap_or_else(|error| panic!("{}", error))
+    }
 
-// Example of an "internal" module
-mod built_in_plugins{
-    use linkme::distributed_slice;
-    use crate::{Plugin, PLUGINS};
+    /// Like `Context::try_new`, but constructs the `Context` from an arbitrary set of plugins rather than the global
+    /// `PLUGINS` registry.
+    pub fn try_with_plugins(plugins: &'static [Plugin]) -> Result<Self, InitError> {
+        ContextBuilder::with_plugins(plugins).build()
+    }
 
-    #[distributed_slice(PLUGINS)]
-    static AGE_PLUGIN: Plugin = Plugin{
-        name: "Age",
-        description: "Age of the person",
-        required: true,
-        enabled: true,
-        initializer: |_context, _person_id| {
-            // The default age.
-            42;
-        },
-        constructor: |context| {
-            context.register_plugin(&AGE_PLUGIN);
+    /// Checks the global `PLUGINS` registry like `Context::try_new`, but reports every problem found rather than only
+    /// the first. See `ContextBuilder::validate_all`.
+    pub fn validate_all() -> Result<(), Vec<InitError>> {
+        ContextBuilder::new().validate_all()
+    }
+
+    /// Returns a `ContextBuilder` over the global `PLUGINS` registry, for configuring plugins before they are
+    /// initialized.
+    pub fn builder() -> ContextBuilder {
+        ContextBuilder::new()
+    }
+
+    /// Records `plugin` as initialized. The `Context` calls this before each plugin's `constructor`, so a constructor
+    /// calling it on its own plugin has no further effect. A constructor that registers any other plugin, by name and
+    /// namespace, fails with `ConfigError::ForeignRegistration`, even if that plugin is already initialized. Outside a
+    /// constructor, a plugin with the name and namespace of one already recorded is not recorded again.
+    pub fn register_plugin(&mut self, plugin: &'static Plugin) {
+        if let Some(current) = self.initializing {
+            if plugin.sort_key() != current.sort_key() {
+                self.foreign.get_or_insert(plugin);
+            }
+            return;
         }
-    };
-}
+        if !self.plugins.iter().any(|other| other.sort_key() == plugin.sort_key()) {
+            self.plugins.push(plugin);
+        }
+    }
 
+    /// The names of the initialized plugins, in the same order as `Context::plugins`.
+    pub fn names(&self) -> Vec<&'static str> {
+        self.plugins.iter().map(|plugin| plugin.name).collect()
+    }
 
-#[cfg(test)]
-mod test {
-    use super::Context;
+    /// The number of plugins the `Context` was built from, whether or not they are enabled. For a `Context` built from
+    /// the global registry, this is the number of plugins in `PLUGINS`.
+    pub fn plugin_count(&self) -> usize {
+        self.instances.len()
+    }
 
-    #[test]
-    fn it_works() {
-        let context = Context::new();
-        for plugin in context.plugins.iter() {
-            println!("Plugin: {}", plugin);
+    /// The number of initialized plugins.
+    pub fn enabled_plugin_count(&self) -> usize {
+        self.plugins.len()
+    }
+
+    /// The initialized plugins carrying `tag`, in the same order as `Context::plugins`.
+    pub fn plugins_with_tag(&self, tag: &str) -> Vec<&'static Plugin> {
+        self.plugins
+            .iter()
+            .copied()
+            .filter(|plugin| plugin.tags.contains(&tag))
+            .collect()
+    }
+
+    /// The initialized plugins ordered by `key`, such as their `priority` or the length of their `dependencies`.
+    /// Plugins with equal keys stay in the same order as `Context::plugins`.
+    pub fn plugins_sorted_by<K: Ord>(&self, key: impl Fn(&Plugin) -> K) -> Vec<&'static Plugin> {
+        let mut plugins = self.plugins.clone();
+        plugins.sort_by_key(|plugin| key(plugin));
+        plugins
+    }
+
+    /// The plugins the `Context` was built from, enabled or not, whose name or description contains `query`, ignoring
+    /// case. In the same order as `Context::instances`.
+    pub fn find_plugins(&self, query: &str) -> Vec<&'static Plugin> {
+        let query = query.to_lowercase();
+        self.instances
+            .iter()
+            .map(|instance| instance.plugin)
+            .filter(|plugin| {
+                plugin.name.to_lowercase().contains(&query) || plugin.description.to_lowercase().contains(&query)
+            })
+            .collect()
+    }
+
+    /// The enabled plugins that are `required`, in the same order as `Context::plugins`.
+    pub fn required_plugins(&self) -> Vec<&'static Plugin> {
+        self.plugins.iter().copied().filter(|plugin| plugin.required).collect()
+    }
+
+    /// Checks that every required plugin has a value for `person_id`, that is, each column the plugin registered has an
+    /// entry at that index that is not unset. This catches initializers that forget to write their value. A required
+    /// plugin that registered no columns has nothing to check.
+    pub fn validate_person(&self, person_id: PersonId) -> Result<(), InitError> {
+        if !self.is_alive(person_id) {
+            return Err(InitError::Runtime(RuntimeError::NoSuchPerson(person_id)));
+        }
+        for plugin in self.required_plugins() {
+            let owned = &self.instance_of(plugin).columns;
+            let has_value = |type_id: TypeId| {
+                let key = (plugin.sort_key(), type_id);
+                self.columns[&key].len() > person_id.0 && !self.is_unset(&key, person_id.0)
+            };
+            if !owned.iter().all(|&type_id| has_value(type_id)) {
+                return Err(InitError::Runtime(RuntimeError::MissingValue { plugin: plugin.name, person_id }));
+            }
+        }
+        Ok(())
+    }
+
+    /// The instance of every plugin the `Context` was built from, including disabled plugins, sorted by name and then
+    /// namespace.
+    pub fn instances(&self) -> &[PluginInstance] {
+        &self.instances
+    }
+
+    /// The instance of the plugin with the given name, if the `Context` was built from such a plugin. The name is
+    /// either qualified, `"namespace::name"`, or a bare name that no other plugin shares.
+    pub fn instance(&self, name: &str) -> Option<&PluginInstance> {
+        let index = resolve(&self.instances, |instance| instance.plugin, name)?;
+        Some(&self.instances[index])
+    }
+
+    /// A compact id for the plugin with the given name, for on-disk formats and protocols where names are too verbose:
+    /// its index in `Context::instances`. Since the instances are sorted by name and then namespace, a `Context` built
+    /// from the same plugins assigns the same ids in every run, but `Context::add_dynamic_plugin` shifts the ids of
+    /// the plugins sorted after the new one. The name is resolved like in `Context::instance`.
+    pub fn plugin_id(&self, name: &str) -> Option<u32> {
+        let index = resolve(&self.instances, |instance| instance.plugin, name)?;
+        Some(index as u32)
+    }
+
+    /// The plugin with the id `Context::plugin_id` assigned to it, whether or not it is enabled.
+    pub fn plugin_by_id(&self, id: u32) -> Option<&'static Plugin> {
+        self.instances.get(id as usize).map(|instance| instance.plugin)
+    }
+
+    /// The instance of `plugin`, which the `Context` was built from.
+    fn instance_of(&self, plugin: &Plugin) -> &PluginInstance {
+        let index = self
+            .instances
+            .binary_search_by_key(&plugin.sort_key(), |instance| instance.plugin.sort_key())
+            .expect("every plugin has an instance");
+        &self.instances[index]
+    }
+
+    /// The initialized plugin with the given name, if any. The name is either qualified, `"namespace::name"`, or a bare
+    /// name that no other initialized plugin shares.
+    pub fn get_plugin(&self, name: &str) -> Option<&'static Plugin> {
+        let index = resolve(&self.plugins, |plugin| plugin, name)?;
+        Some(self.plugins[index])
+    }
+
+    /// The names of the registered plugins that the plugin `name` depends on, whether or not they are enabled: its
+    /// `dependencies` and then its `optional_dependencies`, in declaration order, leaving out those that are not
+    /// registered. Empty if no plugin has that name. The name is resolved like in `Context::instance`.
+    pub fn dependencies_of(&self, name: &str) -> Vec<&'static str> {
+        let Some(instance) = self.instance(name) else {
+            return Vec::new();
+        };
+        let plugin = instance.plugin;
+        plugin
+            .dependencies
+            .iter()
+            .chain(plugin.optional_dependencies)
+            .filter_map(|dependency| self.instance(dependency).map(|instance| instance.plugin.name))
+            .collect()
+    }
+
+    /// The names of the registered plugins that list the plugin `name` among their `dependencies` or
+    /// `optional_dependencies`, whether or not they are enabled, sorted by name. These are the plugins affected by
+    /// disabling it. Empty if no plugin has that name. The name is resolved like in `Context::instance`.
+    pub fn dependents_of(&self, name: &str) -> Vec<&'static str> {
+        let Some(instance) = self.instance(name) else {
+            return Vec::new();
+        };
+        let plugin = instance.plugin;
+        self.instances
+            .iter()
+            .map(|instance| instance.plugin)
+            .filter(|other| {
+                let mut dependencies = other.dependencies.iter().chain(other.optional_dependencies);
+                dependencies.any(|&dependency| plugin.is_named(dependency))
+            })
+            .map(|other| other.name)
+            .collect()
+    }
+
+    /// Renders the dependency graph of the registered plugins in the Graphviz DOT language. Each plugin is a node
+    /// identified by its qualified name and labeled with its name, dashed and gray if it is disabled. An edge points
+    /// from a plugin to each registered plugin it depends on, dashed for an optional dependency.
+    pub fn to_dot(&self) -> String {
+        let quote = |name: &str| format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""));
+        let mut dot = String::from("digraph plugins {\n");
+        for instance in &self.instances {
+            let plugin = instance.plugin;
+            let style = if instance.enabled { "" } else { ", style=dashed, color=gray" };
+            let (id, label) = (quote(&plugin.qualified_name()), quote(plugin.name));
+            dot.push_str(&format!("    {} [label={}{}];\n", id, label, style));
         }
+        for instance in &self.instances {
+            let plugin = instance.plugin;
+            let hard = plugin.dependencies.iter().map(|dependency| (dependency, ""));
+            let optional = plugin.optional_dependencies.iter().map(|dependency| (dependency, " [style=dashed]"));
+            for (dependency, style) in hard.chain(optional) {
+                if let Some(other) = self.instance(dependency) {
+                    let (from, to) = (plugin.qualified_name(), other.plugin.qualified_name());
+                    dot.push_str(&format!("    {} -> {}{};\n", quote(&from), quote(&to), style));
+                }
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Like `Context::get_plugin`, for code that cannot work without the plugin.
+    ///
+    /// Panics if there is no such initialized plugin, with a message listing the initialized plugins.
+    pub fn require(&self, name: &str) -> &'static Plugin {
+        self.get_plugin(name).unwrap_or_else(|| {
+            let available: Vec<String> = self.plugins.iter().map(|plugin| plugin.qualified_name()).collect();
+            let reason = match self.resolve_instance(name) {
+                Ok(_) => "is not enabled",
+                Err(InitError::Config(ConfigError::AmbiguousName(_))) => "is ambiguous",
+                Err(_) => "is not registered",
+            };
+            panic!("required plugin `{}` {}; the enabled plugins are {:?}", name, reason, available)
+        })
+    }
+
+    /// The index in `instances` of the plugin `name` refers to, failing if there is no such plugin or the bare name is
+    /// ambiguous.
+    fn resolve_instance(&self, name: &str) -> Result<usize, InitError> {
+        if let Some(index) = resolve(&self.instances, |instance| instance.plugin, name) {
+            return Ok(index);
+        }
+        if self.instances.iter().any(|instance| instance.plugin.is_named(name)) {
+            Err(InitError::Config(ConfigError::AmbiguousName(name.to_string())))
+        } else {
+            Err(InitError::Config(ConfigError::UnknownPlugin(name.to_string())))
+        }
+    }
+
+    /// Calls `plugin.init` between the `before_each` and `after_each` hooks, returning the keys of the columns it
+    /// registered for itself and its `properties`. Fails if the constructor registered another plugin, or if the plugin
+    /// has a `batch_initializer` but did not register exactly one column of its own.
+    fn init_plugin(&mut self, plugin: &'static Plugin) -> Result<Vec<ColumnKey>, InitError> {
+        for hook in &self.before_each {
+            hook(self, plugin);
+        }
+        let registered_before: BTreeSet<ColumnKey> = self.columns.keys().copied().collect();
+        self.register_plugin(plugin);
+        self.initializing = Some(plugin);
+        #[cfg(feature = "std")]
+        let start = Instant::now();
+        #[cfg(feature = "std")]
+        let guard = builder::InitGuard::enter(plugin.name);
+        plugin.init(self);
+        self.initializing = None;
+        #[cfg(feature = "std")]
+        drop(guard);
+        #[cfg(feature = "std")]
+        {
+            let duration = start.elapsed();
+            self.metrics.durations.push((plugin.name, duration));
+            if let Some(threshold) = self.slow_threshold.filter(|&threshold| duration > threshold) {
+                // Keep the `OptionalDependencyDisabled` warnings last.
+                let at = self
+                    .warnings
+                    .iter()
+                    .position(|warning| matches!(warning, Warning::OptionalDependencyDisabled { .. }))
+                    .unwrap_or(self.warnings.len());
+                self.warnings.insert(at, Warning::SlowInit { plugin: plugin.name, duration, threshold });
+            }
+        }
+        if let Some(foreign) = self.foreign.take() {
+            return Err(InitError::Config(ConfigError::ForeignRegistration {
+                plugin: plugin.name,
+                registered: foreign.name,
+            }));
+        }
+        let owned: Vec<ColumnKey> = self
+            .columns
+            .keys()
+            .filter(|key| plugin.owns(key.0) && !registered_before.contains(key))
+            .copied()
+            .collect();
+
+        let count = owned.iter().filter(|key| key.0 == plugin.sort_key()).count();
+        if plugin.batch_initializer.is_some() && count != 1 {
+            return Err(InitError::Config(ConfigError::BatchColumnCount { plugin: plugin.name, count }));
+        }
+        self.init_order.push(plugin.name);
+        for hook in &self.after_each {
+            hook(self, plugin);
+        }
+        Ok(owned)
+    }
+
+    /// Enables the named plugin after construction, calling its `init`. Does nothing if it is already enabled. The
+    /// name is either qualified, `"namespace::name"`, or a bare name that no other plugin shares.
+    ///
+    /// People created before the plugin is enabled have no values for it: its columns stay empty, and its initializer
+    /// does not run for new people either, until `Context::backfill` is called. With no people yet, the plugin takes
+    /// part in `create_person` straight away.
+    ///
+    /// Fails like `ContextBuilder::build` if one of the plugin's dependencies is missing or disabled, or if enabling
+    /// it would form a dependency cycle or enable two conflicting plugins. The `Context` is then unchanged.
+    pub fn enable(&mut self, name: &str) -> Result<(), InitError> {
+        let index = self.resolve_instance(name)?;
+        if self.instances[index].enabled {
+            return Ok(());
+        }
+        let plugin = self.instances[index].plugin;
+
+        let plugins: Vec<&'static Plugin> = self.instances.iter().map(|instance| instance.plugin).collect();
+        let enabled = self
+            .instances
+            .iter()
+            .filter(|instance| instance.enabled || instance.plugin.sort_key() == plugin.sort_key())
+            .map(|instance| instance.plugin)
+            .collect::<Vec<_>>();
+        check_conflicts(&enabled)?;
+        dependency_order(&plugins, enabled)?;
+
+        let owned = self.init_plugin(plugin)?;
+        self.plugins.sort_unstable_by_key(|plugin| plugin.sort_key());
+        let instance = &mut self.instances[index];
+        instance.enabled = true;
+        instance.record_columns(&owned);
+        instance.skip_reason = None;
+        self.metrics.enabled += 1;
+        self.metrics.skipped -= 1;
+
+        if self.person_count > 0 {
+            self.unpopulated.push(plugin);
+        }
+        self.update_active();
+        self.update_optional_dependency_warnings();
+        Ok(())
+    }
+
+    /// Adds a plugin that was not in the registry, such as one assembled from a configuration file at runtime, after
+    /// construction. It is validated against the existing plugins and then enabled by its default state, as
+    /// `Context::enable` would do, so the same rules apply to people created before it: they have no values for it
+    /// until `Context::backfill` is called. A plugin that is disabled by default is only recorded as an instance.
+    ///
+    /// The `Context` refers to plugins by `&'static Plugin`, as it does for the registry, and the caller keeps the
+    /// plugin: a plugin that is rejected, or whose `init` fails, is still valid and may be added again once the problem
+    /// is fixed. A plugin assembled at runtime, with a name built with `format!`, is made `'static` by the caller, for
+    /// example with `Box::leak` and `String::leak`, once for the lifetime of the process.
+    ///
+    /// Fails if one of its names is already taken in its namespace, if it is `required` but disabled, or like
+    /// `Context::enable`. The `Context` is then unchanged.
+    pub fn add_dynamic_plugin(&mut self, plugin: &'static Plugin) -> Result<(), InitError> {
+        let existing = self.instances.iter().map(|instance| instance.plugin);
+        if let Some(error) = duplicate_names(existing.chain([plugin])).into_iter().next() {
+            return Err(error);
+        }
+        if plugin.required && !plugin.enabled {
+            return Err(InitError::Config(ConfigError::RequiredButDisabled(plugin.name)));
+        }
+
+        let index = self.instances.partition_point(|instance| instance.plugin.sort_key() < plugin.sort_key());
+        let skip_reason = default_skip_reason(plugin);
+        let instance =
+            PluginInstance { plugin, enabled: false, columns: Vec::new(), property_columns: Vec::new(), skip_reason };
+        self.instances.insert(index, instance);
+        self.metrics.seen += 1;
+        self.metrics.skipped += 1;
+
+        if skip_reason.is_none() && let Err(error) = self.enable(&plugin.qualified_name()) {
+            self.instances.remove(index);
+            self.metrics.seen -= 1;
+            self.metrics.skipped -= 1;
+            return Err(error);
+        }
+        self.update_optional_dependency_warnings();
+        Ok(())
+    }
+
+    /// Runs the initializer of a plugin enabled by `Context::enable` for every existing person that has no value for
+    /// it yet, in order of `person_id`, so that its columns are fully populated. From then on its initializer runs for
+    /// new people too. Does nothing if the plugin is already populated, so calling it again is harmless.
+    ///
+    /// Fails if the plugin is disabled, or with the error of the first initializer call that fails. The plugin's
+    /// columns are then truncated back to their length before the call, and it remains unpopulated.
+    pub fn backfill(&mut self, name: &str) -> Result<(), InitError> {
+        let index = self.resolve_instance(name)?;
+        let plugin = self.instances[index].plugin;
+        if !self.instances[index].enabled {
+            return Err(InitError::Runtime(RuntimeError::PluginDisabled(plugin.name)));
+        }
+        let Some(position) = self.unpopulated.iter().position(|other| other.sort_key() == plugin.sort_key()) else {
+            return Ok(());
+        };
+
+        let owned: Vec<ColumnKey> = self.instances[index].column_keys().collect();
+        let start = owned.iter().map(|key| self.columns[key].len()).min().unwrap_or(0);
+        for key in &owned {
+            self.clear_unset(Some(key.0), start..self.person_count);
+        }
+        for person_id in start..self.person_count {
+            let initialized = (plugin.initializer)(self, PersonId(person_id))
+                .and_then(|()| self.initialize_properties(plugin, PersonId(person_id)));
+            self.scratch.clear();
+            if let Err(error) = initialized {
+                for key in &owned {
+                    self.columns.get_mut(key).unwrap().truncate(start);
+                }
+                return Err(error);
+            }
+        }
+        self.apply_defaults(plugin, start..self.person_count);
+
+        self.unpopulated.remove(position);
+        self.update_active();
+        Ok(())
+    }
+
+    /// Combines this `Context` with `other`, which was built independently, for example by another subsystem of a
+    /// composed model. The merged `Context` has the people of both: this one's keep their ids, and `other`'s are offset
+    /// by this one's `person_id`s, so that `other`'s person `i` becomes person `range.start.0 + i`, where `range`, the
+    /// second value returned, holds the new ids of all of `other`'s people. Removed people stay removed.
+    ///
+    /// The merged `Context` is built from the plugins of both. Those only `other` was built from are added disabled,
+    /// with their skip reason. The plugins enabled in either must be enabled in both, with every person initialized,
+    /// so that each person has a value in every column; call `Context::enable` and `Context::backfill` on one of them
+    /// beforehand otherwise. Apart from the people and the plugins, the merged `Context` keeps this one's state, such
+    /// as its globals, hooks and random number generator, adding only the globals of types it lacks. `other` is not
+    /// shut down, since its plugins live on in the merged `Context`.
+    ///
+    /// Fails with `ConfigError::DuplicateName` if the contexts have different plugins with the same name in the same
+    /// namespace, with `ConfigError::MergeMismatch` if a plugin is not enabled and populated alike in both, and with
+    /// `RuntimeError::MissingValue` if a person has no value in one of the columns. Both contexts are then handed back
+    /// unchanged in the `MergeError`, boxed since it holds both, so that neither is torn down.
+    pub fn merge(mut self, mut other: Context) -> Result<(Context, PersonRange), Box<MergeError>> {
+        if let Err(error) = self.check_merge(&other) {
+            return Err(Box::new(MergeError { error, context: self, other }));
+        }
+
+        let offset = self.person_count;
+        for (key, column) in core::mem::take(&mut other.columns) {
+            self.columns.get_mut(&key).expect("both contexts have the same columns").append(column);
+        }
+        for (key, bits) in &other.unset {
+            let merged = self.unset.entry(*key).or_default();
+            for index in bits.iter() {
+                merged.insert(offset + index);
+            }
+        }
+        self.removed.extend(other.removed.iter().map(|&index| offset + index));
+        self.person_count += other.person_count;
+
+        for instance in &other.instances {
+            if let Err(index) =
+                self.instances.binary_search_by_key(&instance.plugin.sort_key(), |mine| mine.plugin.sort_key())
+            {
+                self.instances.insert(index, instance.clone());
+                self.metrics.seen += 1;
+                self.metrics.skipped += 1;
+            }
+        }
+        for (type_id, global) in core::mem::take(&mut other.globals) {
+            self.globals.entry(type_id).or_insert(global);
+        }
+        self.update_optional_dependency_warnings();
+        // The plugins' resources now belong to the merged `Context`.
+        other.shut_down = true;
+        let people = (offset..self.person_count).into();
+        Ok((self, people))
+    }
+
+    /// Fails like `Context::merge` if this `Context` and `other` cannot be merged, without changing either.
+    fn check_merge(&self, other: &Context) -> Result<(), InitError> {
+        let mut plugins: Vec<&'static Plugin> = self.instances.iter().map(|instance| instance.plugin).collect();
+        for instance in &other.instances {
+            if !plugins.iter().any(|&plugin| core::ptr::eq(plugin, instance.plugin)) {
+                plugins.push(instance.plugin);
+            }
+        }
+        if let Some(error) = duplicate_names(plugins.iter().copied()).into_iter().next() {
+            return Err(error);
+        }
+        for &plugin in &plugins {
+            let mine = self.instances.iter().find(|instance| core::ptr::eq(instance.plugin, plugin));
+            let theirs = other.instances.iter().find(|instance| core::ptr::eq(instance.plugin, plugin));
+            let populated = |context: &Context, instance: Option<&PluginInstance>| -> Option<Vec<ColumnKey>> {
+                let instance = instance.filter(|instance| instance.enabled)?;
+                let waiting = context.unpopulated.iter().any(|&other| core::ptr::eq(other, plugin));
+                (!waiting).then(|| instance.column_keys().collect())
+            };
+            if populated(self, mine) != populated(other, theirs) {
+                return Err(InitError::Config(ConfigError::MergeMismatch(plugin.name)));
+            }
+        }
+        for context in [self, other] {
+            let short = context.columns.iter().find(|(_, column)| column.len() < context.person_count);
+            if let Some((&((plugin, _), _), column)) = short {
+                let person_id = PersonId(column.len());
+                return Err(InitError::Runtime(RuntimeError::MissingValue { plugin, person_id }));
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces the `OptionalDependencyDisabled` warnings with those for the currently enabled plugins.
+    fn update_optional_dependency_warnings(&mut self) {
+        let plugins: Vec<&'static Plugin> = self.instances.iter().map(|instance| instance.plugin).collect();
+        self.warnings
+            .retain(|warning| !matches!(warning, Warning::OptionalDependencyDisabled { .. }));
+        self.warnings.extend(optional_dependency_warnings(&plugins, &self.plugins));
+    }
+
+    /// Recomputes the initialization order of the enabled plugins that are not waiting to be backfilled.
+    fn update_active(&mut self) {
+        let plugins: Vec<&'static Plugin> = self.instances.iter().map(|instance| instance.plugin).collect();
+        let enabled = self
+            .instances
+            .iter()
+            .filter(|instance| instance.enabled)
+            .map(|instance| instance.plugin)
+            .collect();
+        let order = dependency_order(&plugins, enabled).expect("the enabled plugins were validated");
+
+        let unpopulated = &self.unpopulated;
+        self.active = order
+            .into_iter()
+            .filter(|plugin| !unpopulated.iter().any(|other| other.sort_key() == plugin.sort_key()))
+            .collect();
+    }
+
+    /// Disables the named plugin after construction, calling its `teardown` and then dropping its columns and the
+    /// values in them. Does nothing if it is already disabled. Its instance remains, like that of a plugin disabled
+    /// through `ContextBuilder`.
+    ///
+    /// Fails if the plugin is `required` or `Unconditional`, or if an enabled plugin depends on it. Enabled plugins
+    /// that list it in `optional_dependencies` are unaffected.
+    pub fn disable(&mut self, name: &str) -> Result<(), InitError> {
+        let index = self.resolve_instance(name)?;
+        let plugin = self.instances[index].plugin;
+        if !self.instances[index].enabled {
+            return Ok(());
+        }
+        if plugin.required {
+            return Err(InitError::Config(ConfigError::DisableRequired(plugin.name)));
+        }
+        if plugin.default_state == DefaultState::Unconditional {
+            return Err(InitError::Config(ConfigError::CannotDisable(plugin.name)));
+        }
+        let dependent = self
+            .plugins
+            .iter()
+            .find(|other| other.dependencies.iter().any(|&dependency| plugin.is_named(dependency)));
+        if let Some(dependent) = dependent {
+            return Err(InitError::Config(ConfigError::HasDependent { plugin: plugin.name, dependent: dependent.name }));
+        }
+
+        if let Some(teardown) = plugin.teardown
+            && !self.shut_down
+        {
+            teardown(self);
+        }
+        let instance = &mut self.instances[index];
+        instance.enabled = false;
+        instance.skip_reason = Some(SkipReason::Runtime);
+        self.metrics.enabled -= 1;
+        self.metrics.skipped += 1;
+        let owned: Vec<ColumnKey> = instance.column_keys().collect();
+        instance.columns.clear();
+        instance.property_columns.clear();
+        for key in owned {
+            self.columns.remove(&key);
+            self.codecs.remove(&key);
+            self.resets.remove(&key);
+            self.unset.remove(&key);
+        }
+        self.formatters.retain(|&owner, _| !plugin.owns(owner));
+        self.conversions.retain(|(owner, _), _| *owner != plugin.sort_key());
+        self.observers.remove(&plugin.sort_key());
+        let is_other = |other: &&'static Plugin| other.sort_key() != plugin.sort_key();
+        self.plugins.retain(is_other);
+        self.active.retain(is_other);
+        self.unpopulated.retain(is_other);
+        self.update_optional_dependency_warnings();
+        Ok(())
+    }
+
+    /// Calls the `teardown` of every enabled plugin that has one, in reverse initialization order, so that a plugin is
+    /// torn down before the plugins it depends on. The `Context` stays usable, but plugins that released resources may
+    /// no longer work. Does nothing if the `Context` was already shut down; otherwise dropping it shuts it down.
+    pub fn shutdown(&mut self) {
+        if self.shut_down {
+            return;
+        }
+        self.shut_down = true;
+        let plugins: Vec<&'static Plugin> = self.instances.iter().map(|instance| instance.plugin).collect();
+        let enabled = self
+            .instances
+            .iter()
+            .filter(|instance| instance.enabled)
+            .map(|instance| instance.plugin)
+            .collect();
+        let order = dependency_order(&plugins, enabled).expect("the enabled plugins were validated");
+        for plugin in order.into_iter().rev() {
+            if let Some(teardown) = plugin.teardown {
+                teardown(self);
+            }
+        }
+    }
+
+    /// The non-fatal problems noticed while constructing the `Context`. The `Warning::OptionalDependencyDisabled`
+    /// warnings, which come last, describe the currently enabled plugins, even after `Context::enable` or
+    /// `Context::disable`.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// The global value of type `T`, such as a lookup table that a data plugin's `constructor` stashes for the
+    /// initializers of other plugins, or `None` if nothing stored one. Unlike columns, global values belong to no
+    /// plugin: they are keyed by type alone, stay when a plugin is disabled, and are not saved by `Context::save`.
+    pub fn global<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.globals.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    /// The global value of type `T`, inserting `T::default()` first if there is none yet. See `Context::global`.
+    pub fn global_mut<T: Any + Default + Send + Sync>(&mut self) -> &mut T {
+        self.globals
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(T::default()))
+            .downcast_mut()
+            .expect("globals are keyed by their type")
+    }
+
+    /// The scratch value of type `T` for the person being initialized, or `None` if no initializer stored one for them
+    /// yet. See `Context::scratch_mut`.
+    pub fn scratch<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.scratch.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    /// The scratch value of type `T` for the person being initialized, inserting `T::default()` first if there is none
+    /// yet, for initializers that derive several properties from one expensive computation: the first to need the
+    /// result stores it here, and the later ones reuse it. Scratch values only live while one person is initialized by
+    /// `Context::create_person`, `Context::create_people` or `Context::backfill`, and are dropped before the next
+    /// person's initializers run, so unlike globals they never leak from one person to another.
+    pub fn scratch_mut<T: Any + Default + Send + Sync>(&mut self) -> &mut T {
+        self.scratch
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(T::default()))
+            .downcast_mut()
+            .expect("scratch values are keyed by their type")
+    }
+
+    /// The random number generator, for initializers that draw random initial values. It is seeded by
+    /// `ContextBuilder::seed`, or with `0` by default, so runs are reproducible.
+    pub fn rng(&mut self) -> &mut Rng {
+        &mut self.rng
+    }
+
+    /// The names of the plugins in the order their `init` was called, which is the dependency order of the enabled
+    /// plugins rather than the sorted order of `Context::plugins`. Plugins enabled later with `Context::enable` are
+    /// appended, and a plugin that is disabled stays in the list, so a plugin enabled again appears twice.
+    pub fn init_order(&self) -> &[&'static str] {
+        &self.init_order
+    }
+
+    /// Counts of the plugins seen, enabled and skipped, and how long each `plugin.init` took.
+    pub fn metrics(&self) -> &InitMetrics {
+        &self.metrics
+    }
+
+    /// A human-readable summary of construction: how many plugins were initialized and which, which were skipped and
+    /// why, and any warnings. Plugins are listed by qualified name, sorted, followed by their `origin` if it is known,
+    /// and sections with nothing to list are left out.
+    pub fn init_report(&self) -> String {
+        let describe = |plugin: &Plugin| match plugin.origin {
+            "" => plugin.qualified_name(),
+            origin => format!("{} (from {})", plugin.qualified_name(), origin),
+        };
+        let mut report = format!("initialized {} of {} plugins\n", self.metrics.enabled, self.metrics.seen);
+        if !self.plugins.is_empty() {
+            report.push_str("initialized:\n");
+            for plugin in &self.plugins {
+                report.push_str(&format!("  {}\n", describe(plugin)));
+            }
+        }
+        let skipped: Vec<&PluginInstance> = self.instances.iter().filter(|instance| !instance.enabled).collect();
+        if !skipped.is_empty() {
+            report.push_str("skipped:\n");
+            for instance in skipped {
+                let reason = instance.skip_reason.map_or("disabled".to_string(), |reason| reason.to_string());
+                report.push_str(&format!("  {}: {}\n", describe(instance.plugin), reason));
+            }
+        }
+        if !self.warnings.is_empty() {
+            report.push_str("warnings:\n");
+            for warning in &self.warnings {
+                report.push_str(&format!("  {}\n", warning));
+            }
+        }
+        report
+    }
+
+    /// The state of every plugin the `Context` was built from, keyed by name. Dependencies are resolved among those
+    /// plugins, like in `ContextBuilder::build`. A plugin that is disabled by an override counts as
+    /// `DisabledByOverride` even if its dependencies failed too. Plugins of different namespaces that
+    /// share a name share an entry, holding the state of the last in the order of `Context::instances`, so qualify
+    /// through `Context::instance` to tell them apart.
+    pub fn plugin_states(&self) -> BTreeMap<&'static str, PluginState> {
+        let mut states: Vec<PluginState> = self
+            .instances
+            .iter()
+            .map(|instance| match instance.skip_reason {
+                _ if instance.enabled => PluginState::Enabled,
+                Some(SkipReason::Override | SkipReason::Tag(_) | SkipReason::Runtime) => {
+                    PluginState::DisabledByOverride
+                }
+                _ => PluginState::DisabledByDefault,
+            })
+            .collect();
+        // Failures spread to the dependents of the dependents, so repeat until no state changes. The dependencies of
+        // disabled plugins were never checked for cycles, so this cannot follow the initialization order instead.
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for index in 0..states.len() {
+                if states[index] != PluginState::DisabledByDefault {
+                    continue;
+                }
+                let has_failed = |dependency: usize| {
+                    use PluginState::{DisabledByFailedDependency, DisabledByOverride};
+                    matches!(states[dependency], DisabledByOverride | DisabledByFailedDependency)
+                };
+                let failed = self.instances[index]
+                    .plugin
+                    .dependencies
+                    .iter()
+                    .any(|&name| resolve(&self.instances, |instance| instance.plugin, name).is_none_or(has_failed));
+                if failed {
+                    states[index] = PluginState::DisabledByFailedDependency;
+                    changed = true;
+                }
+            }
+        }
+        self.instances.iter().map(|instance| instance.plugin.name).zip(states).collect()
+    }
+
+    /// Records, for every plugin the `Context` was built from, whether it is enabled once `ContextBuilder` overrides
+    /// are taken into account.
+    pub fn config_snapshot(&self) -> ConfigSnapshot {
+        let plugins = self
+            .instances
+            .iter()
+            .map(|instance| PluginConfig {
+                name: instance.plugin.name,
+                enabled: instance.enabled,
+                required: instance.plugin.required,
+                description: instance.plugin.description,
+            })
+            .collect();
+        ConfigSnapshot { plugins }
+    }
+
+    /// A hash of the effective configuration, for detecting configuration drift between runs, or as part of a cache
+    /// key: of the name, namespace, version, `required` flag and priority of every enabled plugin, sorted by name.
+    /// Contexts with the same plugins enabled therefore share a fingerprint regardless of link order, and the
+    /// fingerprint stays the same across runs and builds.
+    pub fn config_fingerprint(&self) -> u64 {
+        snapshot::fingerprint(&self.plugins)
+    }
+
+    /// The metadata of the plugin with the given name, enabled or not, if the `Context` was built from such a plugin.
+    /// The name is resolved like in `Context::instance`.
+    pub fn describe(&self, name: &str) -> Option<PluginInfo> {
+        self.instance(name).map(PluginInfo::from)
+    }
+
+    /// The metadata of every plugin the `Context` was built from, in the same order as `Context::instances`.
+    pub fn describe_all(&self) -> Vec<PluginInfo> {
+        self.instances.iter().map(PluginInfo::from).collect()
+    }
+
+    /// Creates a new person, running the `initializer` of every enabled plugin, in initialization order, to assign the
+    /// person's first property values. The whole order is followed for each person in turn, so an initializer that
+    /// reads the values of its dependencies sees those of the person being created. The smallest id freed by
+    /// `Context::remove_person` is reused if there is one, so the columns stay dense; otherwise ids are assigned
+    /// consecutively starting at zero. An initializer must therefore store its value at `person_id`, overwriting the
+    /// removed person's value, rather than append it. Once every initializer has run, the `on_person_created`
+    /// callbacks of the enabled plugins are called, also in initialization order.
+    ///
+    /// Fails with the error of the first initializer that fails. The person is then not created: every column is
+    /// truncated back to its length before the call, and the id is handed out again by the next call. Any other state
+    /// an initializer changed is not rolled back.
+    ///
+    /// In debug builds, panics unless the initializer of every enabled plugin ran exactly once for the new person.
+    pub fn create_person(&mut self) -> Result<PersonId, InitError> {
+        let reused = self.removed.first().copied();
+        let person_id = reused.unwrap_or(self.person_count);
+        self.clear_unset(None, person_id..person_id + 1);
+        #[cfg(debug_assertions)]
+        let mut calls = BTreeMap::new();
+        // Index rather than iterate, since each initializer needs `self` mutably.
+        for index in 0..self.active.len() {
+            let plugin = self.active[index];
+            #[cfg(debug_assertions)]
+            {
+                *calls.entry(plugin.sort_key()).or_default() += 1;
+            }
+            let initialized = (plugin.initializer)(self, PersonId(person_id)).and_then(|()| {
+                self.initialize_properties(plugin, PersonId(person_id))?;
+                self.apply_defaults(plugin, person_id..person_id + 1);
+                self.validate(plugin, PersonId(person_id))
+            });
+            if let Err(error) = initialized {
+                self.scratch.clear();
+                // A reused slot stays free, holding whatever values were written to it.
+                if reused.is_none() {
+                    for column in self.columns.values_mut() {
+                        column.truncate(person_id);
+                    }
+                }
+                return Err(error);
+            }
+        }
+        self.scratch.clear();
+        #[cfg(debug_assertions)]
+        self.assert_initialized_once(&calls, person_id);
+        match reused {
+            Some(person_id) => {
+                self.removed.remove(&person_id);
+            }
+            None => self.person_count += 1,
+        }
+        self.notify_created(person_id..person_id + 1);
+        Ok(PersonId(person_id))
+    }
+
+    /// Panics unless `calls` counts exactly one initialization of `person_id` by each enabled plugin, other than those
+    /// waiting for `Context::backfill`, which catches a plugin that is active twice or not at all. Only checked in
+    /// debug builds.
+    #[cfg(debug_assertions)]
+    fn assert_initialized_once(&self, calls: &BTreeMap<(&'static str, &'static str), usize>, person_id: usize) {
+        for &plugin in &self.plugins {
+            let expected = usize::from(!self.unpopulated.iter().any(|&other| core::ptr::eq(other, plugin)));
+            let count = calls.get(&plugin.sort_key()).copied().unwrap_or(0);
+            assert_eq!(
+                count, expected,
+                "plugin `{}` was initialized {} times for person {}",
+                plugin.name, count, person_id
+            );
+        }
+    }
+
+    /// Runs the `validator` of `plugin`, if it has one, on the values just computed for `person_id`.
+    fn validate(&self, plugin: &'static Plugin, person_id: PersonId) -> Result<(), InitError> {
+        match plugin.validator {
+            Some(validator) => validator(self, person_id),
+            None => Ok(()),
+        }
+    }
+
+    /// Calls the `on_person_created` callback of each active plugin for each of `people`, person by person in
+    /// initialization order.
+    fn notify_created(&mut self, people: Range<usize>) {
+        let callbacks: Vec<fn(&mut Context, PersonId)> =
+            self.active.iter().filter_map(|plugin| plugin.on_person_created).collect();
+        for person_id in people {
+            for callback in &callbacks {
+                callback(self, PersonId(person_id));
+            }
+        }
+    }
+
+    /// Marks the slots of `people` as holding values again, in the columns of the plugin with sort key `owner`, or in
+    /// every column if `None`. Called before initializers fill the slots.
+    fn clear_unset(&mut self, owner: Option<(&'static str, &'static str)>, people: Range<usize>) {
+        for (key, bits) in &mut self.unset {
+            if owner.is_none_or(|owner| key.0 == owner) {
+                for person_id in people.clone() {
+                    bits.remove(person_id);
+                }
+            }
+        }
+    }
+
+    /// Whether the slot of `person_id` in the column `key` was unset by `Context::unset`.
+    fn is_unset(&self, key: &ColumnKey, person_id: usize) -> bool {
+        self.unset.get(key).is_some_and(|bits| bits.contains(person_id))
+    }
+
+    /// Overwrites the values of `plugin` for `people` with the defaults set by `ContextBuilder::set_default`.
+    fn apply_defaults(&mut self, plugin: &'static Plugin, people: Range<usize>) {
+        if self.defaults.is_empty() {
+            return;
+        }
+        for type_id in self.instance_of(plugin).columns.clone() {
+            let key = (plugin.sort_key(), type_id);
+            if let (Some(default), Some(column)) = (self.defaults.get(&key), self.columns.get_mut(&key)) {
+                let column: &mut dyn Any = column.as_mut();
+                default(column, people.clone());
+            }
+        }
+    }
+
+    /// Removes the person `person_id`, freeing the id for reuse by the next `Context::create_person`. In columns with
+    /// a blank registered by `Context::register_reset`, the person's value is dropped and replaced by the blank right
+    /// away. Other columns keep the person's values until the slot is reused. Either way `Context::get` returns `None`
+    /// for a removed person, and `Context::set` and `Context::validate_person` fail with `RuntimeError::NoSuchPerson`.
+    /// Only the columns returned by `Context::property` still show the blanks or stale values.
+    ///
+    /// Fails with `RuntimeError::NoSuchPerson` if the person was never created or is already removed.
+    pub fn remove_person(&mut self, person_id: PersonId) -> Result<(), InitError> {
+        if !self.is_alive(person_id) {
+            return Err(InitError::Runtime(RuntimeError::NoSuchPerson(person_id)));
+        }
+        for (key, reset) in &self.resets {
+            if let Some(column) = self.columns.get_mut(key)
+                && person_id.0 < column.len()
+            {
+                reset(column.as_mut(), person_id.0);
+            }
+        }
+        self.removed.insert(person_id.0);
+        Ok(())
     }
-}
\ No newline at end of file
+
+    /// Drops the slots of removed people from every column, moving the remaining people's values down so that they
+    /// keep their order, and releases the memory freed, for long runs where many people are removed. The remaining
+    /// people are renumbered consecutively from zero, which also discards the spare room reserved by
+    /// `ContextBuilder::capacity`. Returns the old and new id of each remaining person, so that ids held outside the
+    /// `Context` can be updated; ids from before the call must not be used with the `Context` otherwise.
+    pub fn compact(&mut self) -> RemapTable {
+        let mut next = 0;
+        let new_ids: Vec<Option<PersonId>> = (0..self.person_count)
+            .map(|index| {
+                (!self.removed.contains(&index)).then(|| {
+                    next += 1;
+                    PersonId(next - 1)
+                })
+            })
+            .collect();
+        let removed = &self.removed;
+        for column in self.columns.values_mut() {
+            column.compact(&|index| !removed.contains(&index));
+        }
+        let remap = RemapTable::new(new_ids);
+        for bits in self.unset.values_mut() {
+            let mut compacted = Bitset::default();
+            for index in bits.iter() {
+                if let Some(person_id) = remap.get(PersonId(index)) {
+                    compacted.insert(person_id.0);
+                }
+            }
+            *bits = compacted;
+        }
+        self.removed.clear();
+        self.person_count = next;
+        remap
+    }
+
+    /// Removes every person at once, emptying every column, so that the next person created gets id 0 again, for
+    /// starting another simulation run without rebuilding the `Context`. Everything else stays as it is: no
+    /// `constructor` or `teardown` runs, and the enabled plugins, globals, observers, defaults and the state of
+    /// `Context::rng` are kept. Plugins enabled by `Context::enable` since people were created no longer need
+    /// `Context::backfill`, since there is no one left to backfill.
+    pub fn reset(&mut self) {
+        for column in self.columns.values_mut() {
+            column.truncate(0);
+        }
+        self.unset.clear();
+        self.removed.clear();
+        self.person_count = 0;
+        if !self.unpopulated.is_empty() {
+            self.unpopulated.clear();
+            self.update_active();
+        }
+    }
+
+    /// The number of people created and not removed.
+    pub fn entity_count(&self) -> usize {
+        self.person_count - self.removed.len()
+    }
+
+    /// The ids of the people created and not removed, in id order, skipping the slots of removed people.
+    pub fn entity_ids(&self) -> impl Iterator<Item = PersonId> + '_ {
+        (0..self.person_count).filter(|index| !self.removed.contains(index)).map(PersonId)
+    }
+
+    /// Whether `person_id` was created and not removed.
+    fn is_alive(&self, person_id: PersonId) -> bool {
+        person_id.0 < self.person_count && !self.removed.contains(&person_id.0)
+    }
+
+    /// Creates `count` people at once, returning their ids. The result is the same as calling `create_person` `count`
+    /// times, including when an initializer fails, except that no person is created at all in that case, and that
+    /// ids freed by `Context::remove_person` are not reused: the new people always get consecutive new ids, and that
+    /// the `on_person_created` callbacks only run once every new person has been initialized.
+    ///
+    /// The columns of plugins with a `batch_initializer` are initialized in parallel, one thread per plugin, before
+    /// the remaining plugins' initializers run person by person in initialization order. This is sound because a
+    /// batch initializer only has access to the single column its plugin owns, and columns are only handed out this
+    /// way to their owners; it is correct as long as a batch initializer produces the same values as the plugin's
+    /// `initializer` and no other plugin's initializer writes to that column.
+    pub fn create_people(&mut self, count: usize) -> Result<PersonRange, InitError> {
+        let people = self.person_count..self.person_count + count;
+        self.clear_unset(None, people.clone());
+
+        // Take the batch plugins' columns out of the store so that each thread has exclusive access to its own.
+        let mut batches: Vec<(&'static Plugin, ColumnKey, Box<dyn Column>)> = Vec::new();
+        for &plugin in &self.active {
+            if plugin.batch_initializer.is_some() {
+                let key = (plugin.sort_key(), self.instance_of(plugin).columns[0]);
+                let column = self.columns.remove(&key).expect("a batch plugin's column is registered");
+                batches.push((plugin, key, column));
+            }
+        }
+
+        let batch_result = run_batches(&mut batches, people.clone());
+
+        for (_, key, column) in batches {
+            self.columns.insert(key, column);
+        }
+
+        let result = batch_result.and_then(|()| {
+            for person_id in people.clone() {
+                #[cfg(debug_assertions)]
+                let mut calls = BTreeMap::new();
+                for index in 0..self.active.len() {
+                    let plugin = self.active[index];
+                    // A batch plugin initialized the person in `run_batches`.
+                    #[cfg(debug_assertions)]
+                    {
+                        *calls.entry(plugin.sort_key()).or_default() += 1;
+                    }
+                    if plugin.batch_initializer.is_none() {
+                        (plugin.initializer)(self, PersonId(person_id))?;
+                    }
+                    self.initialize_properties(plugin, PersonId(person_id))?;
+                    self.apply_defaults(plugin, person_id..person_id + 1);
+                    self.validate(plugin, PersonId(person_id))?;
+                }
+                self.scratch.clear();
+                #[cfg(debug_assertions)]
+                self.assert_initialized_once(&calls, person_id);
+            }
+            Ok(())
+        });
+
+        match result {
+            Ok(()) => {
+                self.person_count = people.end;
+                self.notify_created(people.clone());
+                Ok(people.into())
+            }
+            Err(error) => {
+                self.scratch.clear();
+                for column in self.columns.values_mut() {
+                    column.truncate(people.start);
+                }
+                Err(error)
+            }
+        }
+    }
+
+    /// Creates the empty column of plugin `name` holding values of type `T`, if it does not exist yet. Called from
+    /// the plugin's `constructor`, after it has called `register_plugin`. The name is resolved like in
+    /// `Context::get_plugin`.
+    ///
+    /// Panics if no initialized plugin has that name.
+    pub fn register_property<T: Send + 'static>(&mut self, name: &str) {
+        let key = self
+            .column_key::<T>(name)
+            .unwrap_or_else(|| panic!("no registered plugin named `{}` to own the column", name));
+        let capacity = self.capacity;
+        self.columns.entry(key).or_insert_with(|| Box::new(Vec::<T>::with_capacity(capacity)));
+    }
+
+    /// Like `Context::register_property`, but keeps the column's values in `store` rather than in a `Vec<T>`. The
+    /// store must be empty. See `PropertyStore`.
+    ///
+    /// Panics if no initialized plugin has that name.
+    pub fn register_property_store<T: 'static>(&mut self, name: &str, store: impl PropertyStore<T> + Send + 'static) {
+        let key = self
+            .column_key::<T>(name)
+            .unwrap_or_else(|| panic!("no registered plugin named `{}` to own the column", name));
+        debug_assert!(store.is_empty(), "the store for plugin `{}` is not empty", name);
+        let store: Box<dyn PropertyStore<T> + Send> = Box::new(store);
+        self.columns.entry(key).or_insert_with(|| Box::new(store));
+    }
+
+    /// The key of the column of the initialized plugin `name`, or of the property `name` one of them contributes,
+    /// holding values of type `T`, whether or not the column exists.
+    fn column_key<T: 'static>(&self, name: &str) -> Option<ColumnKey> {
+        if let Some(plugin) = self.get_plugin(name) {
+            return Some((plugin.sort_key(), TypeId::of::<T>()));
+        }
+        let (_, owner) = self.sub_property(name)?;
+        Some((owner, TypeId::of::<T>()))
+    }
+
+    /// The initialized plugin that lists a `PropertySpec` named `name` among its `properties`, with the first half of
+    /// the keys of the property's columns: the property's name and the plugin's namespace.
+    fn sub_property(&self, name: &str) -> Option<(&'static Plugin, (&'static str, &'static str))> {
+        self.plugins.iter().find_map(|&plugin| {
+            let property = plugin.properties.iter().find(|property| property.name == name)?;
+            Some((plugin, (property.name, plugin.namespace)))
+        })
+    }
+
+    /// Runs the `initializer` of each of `plugin`'s `properties` for `person_id`, after the plugin's own.
+    fn initialize_properties(&mut self, plugin: &'static Plugin, person_id: PersonId) -> Result<(), InitError> {
+        for property in plugin.properties {
+            (property.initializer)(self, person_id)?;
+        }
+        Ok(())
+    }
+
+    /// The column of plugin `name` holding values of type `T`, or `None` if the plugin did not register one or keeps
+    /// it in a `PropertyStore` other than `Vec<T>`.
+    pub fn property<T: 'static>(&self, name: &str) -> Option<&Vec<T>> {
+        let column: &dyn Any = self.columns.get(&self.column_key::<T>(name)?)?.as_ref();
+        column.downcast_ref()
+    }
+
+    /// The column of plugin `name` holding values of type `T`, or `None` if the plugin did not register one or keeps
+    /// it in a `PropertyStore` other than `Vec<T>`. An `initializer` pushes the value for a new `person_id` here.
+    pub fn property_mut<T: 'static>(&mut self, name: &str) -> Option<&mut Vec<T>> {
+        let key = self.column_key::<T>(name)?;
+        let column: &mut dyn Any = self.columns.get_mut(&key)?.as_mut();
+        column.downcast_mut()
+    }
+
+    /// The store of plugin `name`'s column holding values of type `T`, whether a `Vec<T>` or one registered with
+    /// `Context::register_property_store`, or `None` if the plugin did not register such a column.
+    pub fn store<T: 'static>(&self, name: &str) -> Option<&dyn PropertyStore<T>> {
+        store::<T>(self.columns.get(&self.column_key::<T>(name)?)?.as_ref())
+    }
+
+    /// Like `Context::store`, for changing the values. An `initializer` pushes the value for a new `person_id` here.
+    pub fn store_mut<T: 'static>(&mut self, name: &str) -> Option<&mut dyn PropertyStore<T>> {
+        let key = self.column_key::<T>(name)?;
+        store_mut::<T>(self.columns.get_mut(&key)?.as_mut())
+    }
+
+    /// The value of plugin `name` of type `T` for `person_id`, or `None` if the plugin has no column of type `T`, the
+    /// person has no value in it, the value was unset by `Context::unset`, or the person was removed.
+    pub fn get<T: 'static>(&self, name: &str, person_id: PersonId) -> Option<&T> {
+        if self.removed.contains(&person_id.0) || self.is_unset(&self.column_key::<T>(name)?, person_id.0) {
+            return None;
+        }
+        self.store::<T>(name)?.get(person_id.0)
+    }
+
+    /// A handle to the column of plugin `name` holding values of type `T`, for `Context::get_by_handle` and
+    /// `Context::set_by_handle`. Fails like `Context::set` if the plugin does not resolve, is disabled, or has no
+    /// column of type `T`.
+    pub fn handle<T: 'static>(&self, name: &str) -> Result<PropertyHandle<T>, InitError> {
+        let plugin = match self.resolve_instance(name) {
+            Ok(index) => self.instances[index].plugin,
+            Err(error) => self.sub_property(name).ok_or(error)?.0,
+        };
+        let key = self.column_key::<T>(name).ok_or(InitError::Runtime(RuntimeError::PluginDisabled(plugin.name)))?;
+        if !self.columns.contains_key(&key) {
+            return Err(RuntimeError::TypeMismatch { plugin: plugin.name, type_name: type_name::<T>() }.into());
+        }
+        Ok(PropertyHandle { plugin: plugin.name, key, value_type: PhantomData })
+    }
+
+    /// Like `Context::get`, for the column `handle` refers to. Returns `None` if the plugin was disabled since.
+    pub fn get_by_handle<T: 'static>(&self, handle: PropertyHandle<T>, person_id: PersonId) -> Option<&T> {
+        if self.removed.contains(&person_id.0) || self.is_unset(&handle.key, person_id.0) {
+            return None;
+        }
+        store::<T>(self.columns.get(&handle.key)?.as_ref())?.get(person_id.0)
+    }
+
+    /// Like `Context::set`, for the column `handle` refers to. Fails with `RuntimeError::PluginDisabled` if the plugin
+    /// was disabled since, and otherwise like `Context::set`.
+    pub fn set_by_handle<T: 'static>(
+        &mut self,
+        handle: PropertyHandle<T>,
+        person_id: PersonId,
+        value: T,
+    ) -> Result<(), InitError> {
+        if !self.is_alive(person_id) {
+            return Err(InitError::Runtime(RuntimeError::NoSuchPerson(person_id)));
+        }
+        self.check_mutable(&handle.key, person_id)?;
+        let disabled = InitError::Runtime(RuntimeError::PluginDisabled(handle.plugin));
+        let column = store_mut::<T>(self.columns.get_mut(&handle.key).ok_or(disabled)?.as_mut()).unwrap();
+        if column.len() <= person_id.0 {
+            return Err(InitError::Runtime(RuntimeError::MissingValue { plugin: handle.plugin, person_id }));
+        }
+        column.set(person_id.0, value);
+        if let Some(bits) = self.unset.get_mut(&handle.key) {
+            bits.remove(person_id.0);
+        }
+        self.notify_changed(handle.key.0, person_id);
+        Ok(())
+    }
+
+    /// The value of plugin `name` of type `T` for each person that has one, with the person's id, in id order.
+    /// Removed people and unset slots are skipped. Yields nothing if the plugin has no column of type `T`.
+    pub fn iter_property<T: 'static>(&self, name: &str) -> impl Iterator<Item = (PersonId, &T)> {
+        let unset = self.column_key::<T>(name).and_then(|key| self.unset.get(&key));
+        let store = self.store::<T>(name);
+        (0..store.map_or(0, |store| store.len()))
+            .filter(move |&index| self.is_alive(PersonId(index)) && !unset.is_some_and(|bits| bits.contains(index)))
+            .filter_map(move |index| Some((PersonId(index), store?.get(index)?)))
+    }
+
+    /// The sum of the values of plugin `name` of type `T`, skipping the same people as `Context::iter_property`, or
+    /// `None` if there are no such values, including when the plugin has no column of type `T`. The sum is an `f64`,
+    /// so that it cannot overflow `T`.
+    pub fn sum<T: Copy + Into<f64> + 'static>(&self, name: &str) -> Option<f64> {
+        let mut values = self.iter_property::<T>(name).map(|(_, &value)| value.into()).peekable();
+        values.peek()?;
+        Some(values.sum())
+    }
+
+    /// The mean of the values of plugin `name` of type `T`, or `None` if there are none, like `Context::sum`.
+    pub fn mean<T: Copy + Into<f64> + 'static>(&self, name: &str) -> Option<f64> {
+        let (count, sum) = self
+            .iter_property::<T>(name)
+            .fold((0, 0.0), |(count, sum), (_, &value)| (count + 1, sum + value.into()));
+        (count > 0).then(|| sum / count as f64)
+    }
+
+    /// The smallest value of plugin `name` of type `T`, or `None` if there are none, like `Context::sum`. Values that
+    /// are NaN as an `f64` are only returned if every value is.
+    pub fn min<T: Copy + Into<f64> + 'static>(&self, name: &str) -> Option<T> {
+        self.extreme::<T>(name, |value, best| value < best)
+    }
+
+    /// The largest value of plugin `name` of type `T`, or `None` if there are none, like `Context::min`.
+    pub fn max<T: Copy + Into<f64> + 'static>(&self, name: &str) -> Option<T> {
+        self.extreme::<T>(name, |value, best| value > best)
+    }
+
+    /// The first of the values of plugin `name` of type `T` that no later value `replaces`, comparing them as `f64`.
+    fn extreme<T: Copy + Into<f64> + 'static>(&self, name: &str, replaces: fn(f64, f64) -> bool) -> Option<T> {
+        self.iter_property::<T>(name).map(|(_, &value)| value).reduce(|best, value| {
+            let (value_f64, best_f64) = (value.into(), best.into());
+            if best_f64.is_nan() || replaces(value_f64, best_f64) { value } else { best }
+        })
+    }
+
+    /// Calls `observer` with the person's id whenever `Context::set` or `Context::set_by_handle` changes one of plugin
+    /// `name`'s values, after the value is stored, so that a plugin can keep a derived value up to date, such as a BMI
+    /// computed from the weight. Observers of a plugin run in registration order. Usually called from the observing
+    /// plugin's `constructor`, which should list the observed plugin among its `dependencies`.
+    ///
+    /// Observers run in a single pass: values they set are stored, but notify no further observers, so observers that
+    /// set each other's plugins cannot recurse forever. To react to a derived value as well, observe the original one.
+    ///
+    /// Panics if no initialized plugin has that name.
+    pub fn observe(&mut self, name: &str, observer: fn(&mut Context, PersonId)) {
+        let plugin = self
+            .get_plugin(name)
+            .unwrap_or_else(|| panic!("no registered plugin named `{}` to observe", name));
+        self.observers.entry(plugin.sort_key()).or_default().push(observer);
+    }
+
+    /// Fails with `RuntimeError::Immutable` if the plugin that owns the column `key`, itself or through one of its
+    /// `properties`, is `immutable`, so that the value its initializer computed for `person_id` may not be replaced. A
+    /// slot that is unset has no value to replace.
+    fn check_mutable(&self, key: &ColumnKey, person_id: PersonId) -> Result<(), InitError> {
+        if self.is_unset(key, person_id.0) {
+            return Ok(());
+        }
+        match self.plugins.iter().find(|plugin| plugin.immutable && plugin.owns(key.0)) {
+            Some(plugin) => Err(InitError::Runtime(RuntimeError::Immutable { plugin: plugin.name, person_id })),
+            None => Ok(()),
+        }
+    }
+
+    /// Calls the observers of the plugin with the given `sort_key` for `person_id`, unless observers are already
+    /// running.
+    fn notify_changed(&mut self, sort_key: (&'static str, &'static str), person_id: PersonId) {
+        if self.notifying {
+            return;
+        }
+        let Some(observers) = self.observers.get(&sort_key).cloned() else {
+            return;
+        };
+        self.notifying = true;
+        for observer in observers {
+            observer(self, person_id);
+        }
+        self.notifying = false;
+    }
+
+    /// Makes `Context::remove_person` drop the removed person's value in plugin `name`'s column of type `T` and put a
+    /// clone of `blank` in its place, so that owned values such as `String`s are not kept alive until the slot is
+    /// reused. Called from the plugin's `constructor`, after it has registered the column. `Property::construct` does
+    /// this with the property's `default`.
+    ///
+    /// Panics if no initialized plugin has that name.
+    pub fn register_reset<T: Clone + Send + Sync + 'static>(&mut self, name: &str, blank: T) {
+        let key = self
+            .column_key::<T>(name)
+            .unwrap_or_else(|| panic!("no registered plugin named `{}` to own the reset", name));
+        let reset: Reset = Box::new(move |column, person_id| {
+            let column = store_mut::<T>(column).expect("the column has the type of the blank");
+            column.set(person_id, blank.clone());
+        });
+        self.resets.insert(key, reset);
+    }
+
+    /// Makes `Context::export_csv` write the values of plugin `name`'s column of type `T`, formatted by `formatter`,
+    /// which is usually `T::to_string`. Called from the plugin's `constructor`, after it has registered the column.
+    /// Replaces any formatter the plugin registered before, so a plugin exports at most one of its columns. `name` may
+    /// also be one of the plugin's `properties`, which is then exported as a column of its own.
+    ///
+    /// Panics if no initialized plugin or property has that name.
+    pub fn register_formatter<T: 'static>(&mut self, name: &str, formatter: fn(&T) -> String) {
+        let (sort_key, type_id) = self
+            .column_key::<T>(name)
+            .unwrap_or_else(|| panic!("no registered plugin named `{}` to own the formatter", name));
+        let format: Formatter = Box::new(move |column, person_id| {
+            store::<T>(column)?.get(person_id).map(formatter)
+        });
+        self.formatters.insert(sort_key, (type_id, format));
+    }
+
+    /// Makes `Context::get_as` convert the values of plugin `name`, which are in the plugin's `unit`, to `unit` with
+    /// `convert`, such as from pounds to kilograms. Called from the plugin's `constructor`. Replaces any conversion the
+    /// plugin registered to the same unit before.
+    ///
+    /// Panics if no initialized plugin has that name.
+    pub fn register_conversion(&mut self, name: &str, unit: &'static str, convert: Conversion) {
+        let plugin = self
+            .get_plugin(name)
+            .unwrap_or_else(|| panic!("no registered plugin named `{}` to own the conversion", name));
+        self.conversions.insert((plugin.sort_key(), unit), convert);
+    }
+
+    /// The value of plugin `name` of type `T` for `person_id`, like `Context::get`, converted from the plugin's `unit`
+    /// to `unit` by the conversion the plugin registered with `Context::register_conversion`. A value already in
+    /// `unit` is returned as it is. `Ok(None)` means that the person has no value.
+    ///
+    /// Fails if there is no such plugin, with `RuntimeError::PluginDisabled` if it is disabled, with
+    /// `RuntimeError::UnknownConversion` if it registered no conversion to `unit`, and with
+    /// `RuntimeError::TypeMismatch` if it has no column of type `T`.
+    pub fn get_as<T: Copy + Into<f64> + 'static>(
+        &self,
+        name: &str,
+        person_id: PersonId,
+        unit: &str,
+    ) -> Result<Option<f64>, InitError> {
+        let plugin = self.instances[self.resolve_instance(name)?].plugin;
+        if self.get_plugin(name).is_none() {
+            return Err(InitError::Runtime(RuntimeError::PluginDisabled(plugin.name)));
+        }
+        let convert = match self.conversions.get(&(plugin.sort_key(), unit)) {
+            Some(&convert) => convert,
+            None if plugin.unit == Some(unit) => |value| value,
+            None => {
+                let unit = unit.to_string();
+                return Err(InitError::Runtime(RuntimeError::UnknownConversion { plugin: plugin.name, unit }));
+            }
+        };
+        if self.store::<T>(name).is_none() {
+            return Err(RuntimeError::TypeMismatch { plugin: plugin.name, type_name: type_name::<T>() }.into());
+        }
+        Ok(self.get::<T>(name, person_id).map(|&value| convert(value.into())))
+    }
+
+    /// Writes the population to `writer` as CSV. The header row holds `person_id` and the name of every enabled plugin
+    /// that registered a formatter with `Context::register_formatter`, followed by its `unit` in parentheses if it has
+    /// one, such as `Weight (lbs)`, in the same order as `Context::plugins`. Each plugin is followed by those of its
+    /// `properties` that have a formatter, in the order they are listed. Each following row holds the id of a person,
+    /// in id order, and the formatted values for that person. Removed people are skipped, and a cell is empty if the
+    /// person has no value or it is unset.
+    #[cfg(feature = "std")]
+    pub fn export_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut exported: Vec<(String, ColumnKey, &Formatter)> = Vec::new();
+        for &plugin in &self.plugins {
+            if let Some((type_id, format)) = self.formatters.get(&plugin.sort_key()) {
+                let heading = match plugin.unit {
+                    Some(unit) => format!("{} ({})", plugin.name, unit),
+                    None => plugin.name.to_string(),
+                };
+                exported.push((heading, (plugin.sort_key(), *type_id), format));
+            }
+            for property in plugin.properties {
+                let owner = (property.name, plugin.namespace);
+                if let Some((type_id, format)) = self.formatters.get(&owner) {
+                    exported.push((property.name.to_string(), (owner, *type_id), format));
+                }
+            }
+        }
+
+        let mut header = vec!["person_id".to_string()];
+        header.extend(exported.iter().map(|(heading, _, _)| heading.clone()));
+        csv::write_csv_record(&mut writer, &header)?;
+
+        for person_id in (0..self.person_count).filter(|&person_id| self.is_alive(PersonId(person_id))) {
+            let mut row = vec![person_id.to_string()];
+            for &(_, key, format) in &exported {
+                let cell = self
+                    .columns
+                    .get(&key)
+                    .filter(|_| !self.is_unset(&key, person_id))
+                    .and_then(|column| {
+                        let column: &dyn Any = column.as_ref();
+                        format(column, person_id)
+                    });
+                row.push(cell.unwrap_or_default());
+            }
+            csv::write_csv_record(&mut writer, &row)?;
+        }
+        Ok(())
+    }
+
+    /// Makes `Context::save` and `Context::load` save and restore the values of plugin `name`'s column of type `T`,
+    /// encoded by `encode` and decoded by `decode`, which must invert each other. Called from the plugin's
+    /// `constructor`, after it has registered the column.
+    ///
+    /// Panics if no initialized plugin has that name.
+    pub fn register_codec<T: 'static>(&mut self, name: &str, encode: fn(&T) -> String, decode: fn(&str) -> Option<T>) {
+        let key = self
+            .column_key::<T>(name)
+            .unwrap_or_else(|| panic!("no registered plugin named `{}` to own the codec", name));
+        let codec = Codec {
+            type_name: type_name::<T>(),
+            encode: Box::new(move |column| {
+                let column = store::<T>(column).expect("the column has the codec's type");
+                (0..column.len()).map(|index| encode(column.get(index).unwrap())).collect()
+            }),
+            decode: Box::new(move |column, values| {
+                let column = store_mut::<T>(column).expect("the column has the codec's type");
+                let decoded = values
+                    .iter()
+                    .map(|value| decode(value).ok_or_else(|| value.clone()))
+                    .collect::<Result<Vec<T>, String>>()?;
+                column.truncate(0);
+                for value in decoded {
+                    column.push(value);
+                }
+                Ok(())
+            }),
+        };
+        self.codecs.insert(key, codec);
+    }
+
+    /// The names of the enabled plugins with a column, of their own or of one of their `properties`, that has no codec
+    /// registered by `Context::register_codec`, in the same order as `Context::plugins`, so that callers can tell
+    /// before saving which plugins `Context::save` would fail on. Plugins without columns have nothing to save and are
+    /// never listed.
+    pub fn unserializable_plugins(&self) -> Vec<&'static str> {
+        self.plugins
+            .iter()
+            .filter(|plugin| self.instance_of(plugin).column_keys().any(|key| !self.codecs.contains_key(&key)))
+            .map(|plugin| plugin.name)
+            .collect()
+    }
+
+    /// Saves the state of the `Context` to `writer` as a checkpoint: which plugins are enabled, every column,
+    /// the removed people and the state of `Context::rng`. Restore it with `Context::load`.
+    ///
+    /// Fails with `RuntimeError::NotSerializable`, naming every enabled plugin listed by
+    /// `Context::unserializable_plugins`, if any has a column without a codec registered by `Context::register_codec`,
+    /// and with
+    /// `RuntimeError::InvalidCheckpoint` if writing fails.
+    #[cfg(feature = "std")]
+    pub fn save<W: Write>(&self, writer: W) -> Result<(), InitError> {
+        checkpoint::save(self, writer)
+    }
+
+    /// Restores a `Context` from a checkpoint written by `Context::save`, over the global `PLUGINS` registry. See
+    /// `ContextBuilder::load`.
+    #[cfg(feature = "std")]
+    pub fn load<R: Read>(reader: R) -> Result<Context, InitError> {
+        ContextBuilder::new().load(reader)
+    }
+
+    /// Replaces the value of plugin `name` of type `T` for `person_id`, which also makes an unset value set again, and
+    /// then calls the plugin's observers. See `Context::observe`.
+    ///
+    /// Fails if there is no such initialized plugin, with `RuntimeError::NoSuchPerson` if the person was never created
+    /// or was removed, with `RuntimeError::TypeMismatch` if the plugin has no column of type `T`, and with
+    /// `RuntimeError::MissingValue` if the person has no value to replace, and with `RuntimeError::Immutable` if the
+    /// plugin is `immutable` and the value is not unset.
+    pub fn set<T: 'static>(&mut self, name: &str, person_id: PersonId, value: T) -> Result<(), InitError> {
+        if let Err(error) = self.resolve_instance(name)
+            && self.sub_property(name).is_none()
+        {
+            return Err(error);
+        }
+        if !self.is_alive(person_id) {
+            return Err(InitError::Runtime(RuntimeError::NoSuchPerson(person_id)));
+        }
+        let key = self.slot_key::<T>(name, person_id)?;
+        self.check_mutable(&key, person_id)?;
+        store_mut::<T>(self.columns.get_mut(&key).unwrap().as_mut()).unwrap().set(person_id.0, value);
+        if let Some(bits) = self.unset.get_mut(&key) {
+            bits.remove(person_id.0);
+        }
+        self.notify_changed(key.0, person_id);
+        Ok(())
+    }
+
+    /// Marks the value of plugin `name` of type `T` for `person_id` as unset, so that `Context::get` returns `None`
+    /// for it, for properties that only some people have. The column keeps whatever value the slot holds as a
+    /// placeholder, until `Context::set` replaces it. Unlike `set`, this may be called from an initializer for the
+    /// person being created, after storing the placeholder.
+    ///
+    /// Fails like `Context::set`, except that it does not check whether the person exists.
+    pub fn unset<T: 'static>(&mut self, name: &str, person_id: PersonId) -> Result<(), InitError> {
+        let key = self.slot_key::<T>(name, person_id)?;
+        self.unset.entry(key).or_default().insert(person_id.0);
+        Ok(())
+    }
+
+    /// The key of the column of plugin `name` holding values of type `T`, if the column has a slot for `person_id`.
+    fn slot_key<T: 'static>(&self, name: &str, person_id: PersonId) -> Result<ColumnKey, InitError> {
+        let (plugin, owner) = match self.resolve_instance(name) {
+            Ok(index) => {
+                let plugin = self.instances[index].plugin;
+                if self.get_plugin(name).is_none() {
+                    return Err(InitError::Runtime(RuntimeError::PluginDisabled(plugin.name)));
+                }
+                (plugin, plugin.sort_key())
+            }
+            Err(error) => self.sub_property(name).ok_or(error)?,
+        };
+        let column = self.store::<T>(name).ok_or(InitError::Runtime(RuntimeError::TypeMismatch {
+            plugin: plugin.name,
+            type_name: type_name::<T>(),
+        }))?;
+        if column.len() <= person_id.0 {
+            return Err(InitError::Runtime(RuntimeError::MissingValue { plugin: plugin.name, person_id }));
+        }
+        Ok((owner, TypeId::of::<T>()))
+    }
+}
+
+
+impl Drop for Context {
+    /// Shuts the `Context` down unless `Context::shutdown` was already called. A teardown that panics is reported by
+    /// the panic hook as usual, but the panic does not escape `drop`, and the remaining teardowns are skipped.
+    fn drop(&mut self) {
+        #[cfg(feature = "std")]
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| self.shutdown()));
+        #[cfg(not(feature = "std"))]
+        self.shutdown();
+    }
+}
+
+
+// Example of an "internal" module
+mod built_in_plugins{
+    use alloc::string::ToString;
+
+    use crate::{register_plugin, Property};
+
+    static AGE: Property<u32> = Property{
+        name: "Age",
+        // The default age.
+        default: 42,
+        initializer: None,
+    };
+
+    register_plugin! {
+        static AGE_PLUGIN: Plugin{
+            name: AGE.name,
+            namespace: env!("CARGO_PKG_NAME"),
+            description: "Age of the person",
+            required: true,
+            enabled: true,
+            initializer: |context, person_id| AGE.initialize(context, person_id),
+            tags: &["demographic"],
+            batch_initializer: Some(|column, people| AGE.initialize_batch(column, people)),
+        };
+        constructor: |context| {
+            AGE.construct(context);
+            context.register_formatter::<u32>(AGE.name, u32::to_string);
+            context.register_codec::<u32>(AGE.name, u32::to_string, |value| value.parse().ok());
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    use linkme::distributed_slice;
+    use super::{
+        all_plugins, check_unique_names, ConfigError, Context, ContextBuilder, DefaultState, InitError, MergeError,
+        PersonId, PersonRange, Plugin, Property, PropertySpec, RuntimeError, SkipReason, PLUGINS,
+    };
+
+    #[test]
+    fn it_works() {
+        let context = Context::new();
+        for plugin in context.plugins.iter() {
+            println!("Plugin: {}", plugin.name);
+        }
+    }
+
+    static INITIALIZED: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+    static SKIPPED_PLUGINS: [Plugin; 4] = [
+        Plugin{ name: "Active", constructor: |_context| INITIALIZED.lock().unwrap().push("Active"), ..Plugin::DEFAULT },
+        Plugin{
+            name: "OptedIn",
+            default_state: DefaultState::OptIn,
+            constructor: |_context| INITIALIZED.lock().unwrap().push("OptedIn"),
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Switched",
+            enabled: false,
+            constructor: |_context| INITIALIZED.lock().unwrap().push("Switched"),
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Overridden",
+            constructor: |_context| INITIALIZED.lock().unwrap().push("Overridden"),
+            ..Plugin::DEFAULT
+        },
+    ];
+
+    #[test]
+    fn disabled_plugins_are_never_constructed() {
+        let mut context = ContextBuilder::with_plugins(&SKIPPED_PLUGINS).disable("Overridden").build().unwrap();
+        assert_eq!(*INITIALIZED.lock().unwrap(), ["Active"]);
+        assert_eq!(context.names(), vec!["Active"]);
+
+        context.enable("OptedIn").unwrap();
+        assert_eq!(*INITIALIZED.lock().unwrap(), ["Active", "OptedIn"]);
+    }
+
+    static COMPUTED: AtomicUsize = AtomicUsize::new(0);
+
+    /// The expensive computation both `INCOME` and `TAX` derive from, done once per person.
+    fn household_income(context: &mut Context, person_id: PersonId) -> u32 {
+        *context.scratch_mut::<Option<u32>>().get_or_insert_with(|| {
+            COMPUTED.fetch_add(1, Ordering::Relaxed);
+            1000 + 100 * person_id.0 as u32
+        })
+    }
+
+    static INCOME: Property<u32> = Property{ name: "Income", default: 0, initializer: Some(household_income) };
+
+    static TAX: Property<u32> = Property{
+        name: "Tax",
+        default: 0,
+        initializer: Some(|context, person_id| household_income(context, person_id) / 10),
+    };
+
+    static SCRATCH_PLUGINS: [Plugin; 2] = [
+        Plugin::builder("Income")
+            .constructor(|context| INCOME.construct(context))
+            .initializer(|context, person_id| INCOME.initialize(context, person_id))
+            .build(),
+        Plugin::builder("Tax")
+            .constructor(|context| TAX.construct(context))
+            .initializer(|context, person_id| TAX.initialize(context, person_id))
+            .build(),
+    ];
+
+    #[test]
+    fn initializers_share_scratch_values_for_one_person() {
+        let mut context = Context::with_plugins(&SCRATCH_PLUGINS);
+        context.create_person().unwrap();
+        assert_eq!(COMPUTED.load(Ordering::Relaxed), 1);
+        context.create_people(2).unwrap();
+        assert_eq!(COMPUTED.load(Ordering::Relaxed), 3);
+
+        assert_eq!(context.property::<u32>("Income"), Some(&vec![1000, 1100, 1200]));
+        assert_eq!(context.get::<u32>("Tax", PersonId(2)), Some(&120));
+        assert_eq!(context.scratch::<Option<u32>>(), None);
+    }
+
+    static DESCRIBED_PLUGINS: [Plugin; 4] = [
+        Plugin::builder("Alpha").description("the first letter").build(),
+        Plugin::builder("Beta").description("second").build(),
+        Plugin::builder("Gamma").description("a third one").build(),
+        Plugin::builder("Delta").description("fourth!").build(),
+    ];
+
+    #[test]
+    fn plugins_sort_by_any_key() {
+        let context = Context::with_plugins(&DESCRIBED_PLUGINS);
+        let names = |plugins: Vec<&'static Plugin>| plugins.iter().map(|plugin| plugin.name).collect::<Vec<_>>();
+        assert_eq!(
+            names(context.plugins_sorted_by(|plugin| plugin.description.len())),
+            ["Beta", "Delta", "Gamma", "Alpha"]
+        );
+        // Equal keys keep the order of `Context::plugins`.
+        assert_eq!(names(context.plugins_sorted_by(|plugin| plugin.priority)), context.names());
+    }
+
+    #[test]
+    fn reset_empties_the_population_but_keeps_the_plugins() {
+        let mut context = Context::with_plugins(&TOGGLE_PLUGINS);
+        context.create_people(3).unwrap();
+        context.enable("Extra").unwrap();
+        *context.global_mut::<u32>() = 5;
+        let names = context.names();
+
+        context.reset();
+        assert_eq!(context.names(), names);
+        assert_eq!(context.property::<i16>("Extra"), Some(&vec![]));
+        assert_eq!(context.get::<i16>("Extra", PersonId(0)), None);
+        assert_eq!(context.global::<u32>(), Some(&5));
+
+        assert_eq!(context.create_person().unwrap(), PersonId(0));
+        assert_eq!(context.get::<i16>("Extra", PersonId(0)), Some(&7));
+    }
+
+    #[test]
+    fn aggregates_summarize_a_numeric_property() {
+        let mut context = Context::new();
+        assert_eq!(context.mean::<u32>("Age"), None);
+        for age in [10, 20, 30, 99] {
+            let person_id = context.create_person().unwrap();
+            context.set::<u32>("Age", person_id, age).unwrap();
+        }
+        context.remove_person(PersonId(3)).unwrap();
+
+        assert_eq!(context.sum::<u32>("Age"), Some(60.0));
+        assert_eq!(context.mean::<u32>("Age"), Some(20.0));
+        assert_eq!(context.min::<u32>("Age"), Some(10));
+        assert_eq!(context.max::<u32>("Age"), Some(30));
+        assert_eq!(context.mean::<f64>("Age"), None);
+        assert_eq!(context.sum::<u32>("Agee"), None);
+    }
+
+    static INDEXED: Mutex<Vec<(PersonId, Option<u8>)>> = Mutex::new(Vec::new());
+
+    // "Indexer" initializes first, but its callback already sees the value of "Rank", which initializes later.
+    static CREATION_PLUGINS: [Plugin; 2] = [
+        Plugin::builder("Indexer")
+            .on_person_created(|context, person_id| {
+                let rank = context.get::<u8>("Rank", person_id).copied();
+                INDEXED.lock().unwrap().push((person_id, rank));
+            })
+            .build(),
+        Plugin::builder("Rank")
+            .priority(1)
+            .initializer(|context, _person_id| {
+                context.property_mut::<u8>("Rank").unwrap().push(7);
+                Ok(())
+            })
+            .constructor(|context| context.register_property::<u8>("Rank"))
+            .build(),
+    ];
+
+    #[test]
+    fn on_person_created_runs_after_every_initializer() {
+        let mut context = Context::with_plugins(&CREATION_PLUGINS);
+        context.create_person().unwrap();
+        context.create_people(2).unwrap();
+        let indexed: Vec<(PersonId, Option<u8>)> = (0..3).map(|index| (PersonId(index), Some(7))).collect();
+        assert_eq!(*INDEXED.lock().unwrap(), indexed);
+    }
+
+    #[test]
+    fn with_capacity_reserves_room_in_every_column() {
+        let mut context = Context::with_capacity(1000);
+        let column = context.property::<u32>("Age").unwrap();
+        assert!(column.capacity() >= 1000);
+        let buffer = column.as_ptr();
+
+        for _ in 0..1000 {
+            context.create_person().unwrap();
+        }
+        assert_eq!(context.property::<u32>("Age").unwrap().as_ptr(), buffer);
+        assert_eq!(Context::new().property::<u32>("Age").unwrap().capacity(), 0);
+    }
+
+    static WEIGHT: Property<f64> = Property{ name: "Weight", default: 72.0, initializer: None };
+
+    static BMI: Property<f64> = Property{
+        name: "Bmi",
+        default: 0.0,
+        initializer: Some(|context, person_id| bmi(context, person_id)),
+    };
+
+    /// The body mass index of `person_id`, for a height of 2 m.
+    fn bmi(context: &Context, person_id: PersonId) -> f64 {
+        context.get::<f64>(WEIGHT.name, person_id).unwrap() / 4.0
+    }
+
+    static OBSERVED_PLUGINS: [Plugin; 2] = [
+        Plugin::builder(WEIGHT.name)
+            .initializer(|context, person_id| WEIGHT.initialize(context, person_id))
+            .constructor(|context| WEIGHT.construct(context))
+            .build(),
+        Plugin::builder(BMI.name)
+            .dependencies(&["Weight"])
+            .initializer(|context, person_id| BMI.initialize(context, person_id))
+            .constructor(|context| {
+                BMI.construct(context);
+                context.observe(WEIGHT.name, |context, person_id| {
+                    let bmi = bmi(context, person_id);
+                    context.set(BMI.name, person_id, bmi).unwrap();
+                });
+                // Counts the changes of the BMI that notified its observers.
+                context.observe(BMI.name, |context, _person_id| *context.global_mut::<usize>() += 1);
+            })
+            .build(),
+    ];
+
+    #[test]
+    fn observers_recompute_derived_values() {
+        let mut context = Context::with_plugins(&OBSERVED_PLUGINS);
+        let person_id = context.create_person().unwrap();
+        assert_eq!(context.get::<f64>("Bmi", person_id), Some(&18.0));
+
+        context.set::<f64>("Weight", person_id, 100.0).unwrap();
+        assert_eq!(context.get::<f64>("Bmi", person_id), Some(&25.0));
+        // The BMI set by the observer of the weight notified no further observers.
+        assert_eq!(context.global::<usize>(), None);
+
+        let handle = context.handle::<f64>("Bmi").unwrap();
+        context.set_by_handle(handle, person_id, 30.0).unwrap();
+        assert_eq!(context.global::<usize>(), Some(&1));
+    }
+
+    // The second person's computed age is negative, which the validator rejects.
+    static CHECKED_AGE: Property<i32> = Property{
+        name: "CheckedAge",
+        default: 0,
+        initializer: Some(|_context, person_id| 30 - 35 * person_id.0 as i32),
+    };
+
+    static VALIDATED_PLUGINS: [Plugin; 1] = [Plugin::builder(CHECKED_AGE.name)
+        .initializer(|context, person_id| CHECKED_AGE.initialize(context, person_id))
+        .constructor(|context| CHECKED_AGE.construct(context))
+        .validator(|context, person_id| match context.get::<i32>("CheckedAge", person_id) {
+            Some(age) if !(0..=150).contains(age) => Err(InitError::Runtime(RuntimeError::InvalidValue {
+                plugin: "CheckedAge",
+                person_id,
+                message: format!("age {} is out of range", age),
+            })),
+            _ => Ok(()),
+        })
+        .build()];
+
+    #[test]
+    fn validator_rejects_out_of_range_values() {
+        let mut context = Context::with_plugins(&VALIDATED_PLUGINS);
+        assert_eq!(context.create_person(), Ok(PersonId(0)));
+        let invalid = InitError::Runtime(RuntimeError::InvalidValue {
+            plugin: "CheckedAge",
+            person_id: PersonId(1),
+            message: "age -5 is out of range".to_string(),
+        });
+        assert_eq!(context.create_person(), Err(invalid.clone()));
+        assert_eq!(context.create_people(2), Err(invalid.clone()));
+        assert_eq!(context.property::<i32>("CheckedAge"), Some(&vec![30]));
+        let message = "plugin `CheckedAge` has an invalid value for person 1: age -5 is out of range";
+        assert_eq!(invalid.to_string(), message);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn built_in_plugins_initialize_each_person_once() {
+        let mut context = Context::new();
+        assert_eq!(context.create_person(), Ok(PersonId(0)));
+        assert_eq!(context.create_people(3), Ok(PersonRange::from(1..4)));
+        context.remove_person(PersonId(2)).unwrap();
+        assert_eq!(context.create_person(), Ok(PersonId(2)));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "plugin `Age` was initialized 2 times for person 0")]
+    fn initializing_a_person_twice_panics() {
+        let mut context = Context::new();
+        let age = context.active[0];
+        context.active.push(age);
+        let _ = context.create_person();
+    }
+
+    #[test]
+    fn merge_appends_the_other_population() {
+        let mut first = Context::new();
+        let mut second = Context::new();
+        for (context, ages) in [(&mut first, [1, 2]), (&mut second, [3, 4])] {
+            for age in ages {
+                let person_id = context.create_person().unwrap();
+                context.set::<u32>("Age", person_id, age).unwrap();
+            }
+        }
+        second.remove_person(PersonId(0)).unwrap();
+
+        let (mut merged, people) = first.merge(second).unwrap();
+        assert_eq!(people, PersonRange::from(2..4));
+        assert_eq!(people.collect::<Vec<_>>(), vec![PersonId(2), PersonId(3)]);
+        let ages: Vec<(PersonId, u32)> = merged.iter_property::<u32>("Age").map(|(id, &age)| (id, age)).collect();
+        assert_eq!(ages, vec![(PersonId(0), 1), (PersonId(1), 2), (PersonId(3), 4)]);
+        assert_eq!(merged.create_person(), Ok(PersonId(2)));
+        assert_eq!(merged.create_person(), Ok(PersonId(4)));
+    }
+
+    #[test]
+    fn a_failed_merge_hands_back_both_contexts() {
+        let mut first = Context::with_plugins(&TOGGLE_PLUGINS);
+        first.create_people(2).unwrap();
+        let mut second = ContextBuilder::with_plugins(&TOGGLE_PLUGINS).enable("Extra").build().unwrap();
+        second.create_person().unwrap();
+
+        let MergeError { error, context: mut first, other: mut second } = *first.merge(second).err().unwrap();
+        assert_eq!(error, InitError::Config(ConfigError::MergeMismatch("Extra")));
+        assert_eq!(first.create_person(), Ok(PersonId(2)));
+        assert_eq!(second.get::<i16>("Extra", PersonId(0)), Some(&7));
+        assert_eq!(second.create_person(), Ok(PersonId(1)));
+    }
+
+    #[test]
+    fn compact_drops_the_slots_of_removed_people() {
+        let mut context = Context::new();
+        for age in 0..6 {
+            let person_id = context.create_person().unwrap();
+            context.set::<u32>("Age", person_id, age).unwrap();
+        }
+        for person_id in [1, 3, 5] {
+            context.remove_person(PersonId(person_id)).unwrap();
+        }
+        context.unset::<u32>("Age", PersonId(4)).unwrap();
+
+        let remap = context.compact();
+        let pairs = vec![(PersonId(0), PersonId(0)), (PersonId(2), PersonId(1)), (PersonId(4), PersonId(2))];
+        assert_eq!(remap.iter().collect::<Vec<_>>(), pairs);
+        assert_eq!(remap.get(PersonId(3)), None);
+        assert_eq!(context.property::<u32>("Age"), Some(&vec![0, 2, 4]));
+        assert_eq!(context.get::<u32>("Age", PersonId(1)), Some(&2));
+        assert_eq!(context.get::<u32>("Age", PersonId(2)), None);
+        assert_eq!(context.create_person(), Ok(PersonId(3)));
+    }
+
+    #[test]
+    fn all_plugins_lists_the_registry() {
+        let age = all_plugins().find(|plugin| plugin.name == "Age").unwrap();
+        assert_eq!(age.description, "Age of the person");
+        assert_eq!(all_plugins().count(), PLUGINS.len());
+    }
+
+    #[test]
+    fn plugin_counts_distinguish_disabled_plugins() {
+        let context = Context::new();
+        assert_eq!((context.plugin_count(), context.enabled_plugin_count()), (1, 1));
+
+        assert_eq!(context.plugin_count(), PLUGINS.len());
+
+        let context = Context::with_plugins(&TOGGLE_PLUGINS);
+        assert_eq!((context.plugin_count(), context.enabled_plugin_count()), (3, 1));
+    }
+
+    #[test]
+    fn find_plugins_searches_names_and_descriptions() {
+        let context = Context::with_plugins(&TOGGLE_PLUGINS);
+        let extra = context.instance("Extra").unwrap().plugin;
+        assert!(std::ptr::eq(context.find_plugins("runtime")[0], extra));
+        assert!(std::ptr::eq(context.find_plugins("eXTRA")[0], extra));
+        assert!(context.find_plugins("weight").is_empty());
+    }
+
+    #[test]
+    fn get_plugin_finds_age() {
+        let context = Context::new();
+        let age = context.get_plugin("Age").unwrap();
+        assert_eq!(age.description, "Age of the person");
+        assert!(age.required);
+        assert!(context.get_plugin("Height").is_none());
+    }
+
+    static INITIALIZER_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    static COUNTING_PLUGINS: [Plugin; 1] = [Plugin{
+        name: "Counting",
+        description: "Counts how often its initializer runs",
+        initializer: |_context, _person_id| {
+            INITIALIZER_CALLS.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        },
+        constructor: |context| {
+            context.register_plugin(&COUNTING_PLUGINS[0]);
+        },
+        ..Plugin::DEFAULT
+    }];
+
+    #[test]
+    fn create_person_runs_initializers() {
+        let mut context = Context::with_plugins(&COUNTING_PLUGINS);
+        let ids: Vec<PersonId> = (0..3).map(|_| context.create_person().unwrap()).collect();
+        assert_eq!(ids, vec![PersonId(0), PersonId(1), PersonId(2)]);
+        assert_eq!(INITIALIZER_CALLS.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn create_person_assigns_default_age() {
+        let mut context = Context::new();
+        context.create_person().unwrap();
+        context.create_person().unwrap();
+        assert_eq!(context.property::<u32>("Age"), Some(&vec![42, 42]));
+    }
+
+    static FAILING_PLUGINS: [Plugin; 2] = [
+        Plugin{
+            name: "Stored",
+            description: "Stores a value before the failing plugin runs",
+            initializer: |context, _person_id| {
+                context.property_mut::<u8>("Stored").unwrap().push(1);
+                Ok(())
+            },
+            constructor: |context| {
+                context.register_plugin(&FAILING_PLUGINS[0]);
+                context.register_property::<u8>("Stored");
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Failing",
+            description: "Fails for every person after the first",
+            initializer: |_context, person_id| {
+                if person_id == PersonId(0) {
+                    return Ok(());
+                }
+                Err(InitError::Runtime(RuntimeError::InitializerFailed {
+                    plugin: "Failing",
+                    person_id,
+                    message: "only one person is supported".to_string(),
+                }))
+            },
+            dependencies: &["Stored"],
+            constructor: |context| {
+                context.register_plugin(&FAILING_PLUGINS[1]);
+            },
+            ..Plugin::DEFAULT
+        },
+    ];
+
+    #[test]
+    fn failing_initializer_is_surfaced_and_rolled_back() {
+        let mut context = Context::with_plugins(&FAILING_PLUGINS);
+        assert_eq!(context.create_person(), Ok(PersonId(0)));
+
+        let error = context.create_person().unwrap_err();
+        assert_eq!(
+            error,
+            InitError::Runtime(RuntimeError::InitializerFailed {
+                plugin: "Failing",
+                person_id: PersonId(1),
+                message: "only one person is supported".to_string(),
+            })
+        );
+        assert_eq!(error.to_string(), "failed to initialize plugin `Failing` for person 1: only one person is supported");
+        assert_eq!(context.property::<u8>("Stored"), Some(&vec![1]));
+
+        // The failed person's id is not used up.
+        assert_eq!(context.create_person().unwrap_err(), error);
+    }
+
+    static BATCH_PLUGINS: [Plugin; 3] = [
+        Plugin{
+            name: "Id",
+            description: "A value derived from the person's id",
+            initializer: |context, person_id| {
+                context.property_mut::<u64>("Id").unwrap().push(person_id.0 as u64 * 3);
+                Ok(())
+            },
+            batch_initializer: Some(|column, people| {
+                let column = column.downcast_mut::<Vec<u64>>().unwrap();
+                column.extend(people.map(|person_id| person_id as u64 * 3));
+                Ok(())
+            }),
+            constructor: |context| {
+                context.register_plugin(&BATCH_PLUGINS[0]);
+                context.register_property::<u64>("Id");
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Even",
+            description: "Whether the person's id is even",
+            initializer: |context, person_id| {
+                context.property_mut::<bool>("Even").unwrap().push(person_id.0 % 2 == 0);
+                Ok(())
+            },
+            batch_initializer: Some(|column, people| {
+                let column = column.downcast_mut::<Vec<bool>>().unwrap();
+                column.extend(people.map(|person_id| person_id % 2 == 0));
+                Ok(())
+            }),
+            constructor: |context| {
+                context.register_plugin(&BATCH_PLUGINS[1]);
+                context.register_property::<bool>("Even");
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Serial",
+            description: "Reads the batch-initialized columns, so it has no batch initializer",
+            initializer: |context, person_id| {
+                let id = context.property::<u64>("Id").unwrap()[person_id.0];
+                let even = context.property::<bool>("Even").unwrap()[person_id.0];
+                context.property_mut::<String>("Serial").unwrap().push(format!("{}:{}", id, even));
+                Ok(())
+            },
+            dependencies: &["Id", "Even"],
+            constructor: |context| {
+                context.register_plugin(&BATCH_PLUGINS[2]);
+                context.register_property::<String>("Serial");
+            },
+            ..Plugin::DEFAULT
+        },
+    ];
+
+    #[test]
+    fn create_people_matches_create_person() {
+        let mut serial = Context::with_plugins(&BATCH_PLUGINS);
+        for _ in 0..10_000 {
+            serial.create_person().unwrap();
+        }
+
+        let mut parallel = Context::with_plugins(&BATCH_PLUGINS);
+        assert_eq!(parallel.create_people(10_000), Ok(PersonRange::from(0..10_000)));
+
+        assert_eq!(parallel.property::<u64>("Id"), serial.property::<u64>("Id"));
+        assert_eq!(parallel.property::<bool>("Even"), serial.property::<bool>("Even"));
+        assert_eq!(parallel.property::<String>("Serial"), serial.property::<String>("Serial"));
+        assert_eq!(parallel.property::<String>("Serial").unwrap()[9_999], "29997:false");
+
+        assert_eq!(parallel.create_person(), Ok(PersonId(10_000)));
+    }
+
+    #[test]
+    fn create_people_rolls_back_on_failure() {
+        let mut context = Context::with_plugins(&FAILING_PLUGINS);
+        assert!(context.create_people(3).is_err());
+        assert_eq!(context.property::<u8>("Stored"), Some(&vec![]));
+        assert_eq!(context.create_people(1), Ok(PersonRange::from(0..1)));
+    }
+
+    static FORGETFUL_PLUGINS: [Plugin; 1] = [Plugin{
+        name: "Forgetful",
+        description: "Required, but its initializer never writes a value",
+        required: true,
+        constructor: |context| {
+            context.register_plugin(&FORGETFUL_PLUGINS[0]);
+            context.register_property::<i8>("Forgetful");
+        },
+        ..Plugin::DEFAULT
+    }];
+
+    #[test]
+    fn validate_person_reports_missing_required_value() {
+        let mut context = Context::with_plugins(&FORGETFUL_PLUGINS);
+        let person_id = context.create_person().unwrap();
+        assert_eq!(context.required_plugins()[0].name, "Forgetful");
+        assert_eq!(
+            context.validate_person(person_id),
+            Err(InitError::Runtime(RuntimeError::MissingValue { plugin: "Forgetful", person_id }))
+        );
+    }
+
+    #[test]
+    fn validate_person_accepts_initialized_person() {
+        let mut context = Context::new();
+        let person_id = context.create_person().unwrap();
+        assert_eq!(context.validate_person(person_id), Ok(()));
+        assert!(context.validate_person(PersonId(person_id.0 + 1)).is_err());
+    }
+
+    #[test]
+    fn age_column_is_registered_by_plugin_and_type() {
+        let mut context = Context::new();
+        assert_eq!(context.property::<u32>("Age"), Some(&vec![]));
+        assert!(context.property::<String>("Age").is_none());
+
+        context.property_mut::<u32>("Age").unwrap().push(7);
+        assert_eq!(context.property::<u32>("Age"), Some(&vec![7]));
+    }
+
+    #[test]
+    fn new_for_test_finds_the_built_in_plugins() {
+        let context = Context::new_for_test(&["Age"]);
+        assert_eq!(context.names(), vec!["Age"]);
+    }
+
+    #[test]
+    fn plugins_record_where_they_are_declared() {
+        let context = Context::new();
+        assert_eq!(context.require("Age").origin, "initialization::built_in_plugins");
+        assert!(context.init_report().contains("  initialization::Age (from initialization::built_in_plugins)\n"));
+
+        let error = InitError::Config(ConfigError::DuplicateName {
+            namespace: "",
+            name: "Weight",
+            count: 2,
+            origins: vec!["my_plugin", "other_plugin::body"],
+        });
+        assert_eq!(
+            error.to_string(),
+            "plugin name `Weight` is used by 2 plugins, declared in `my_plugin`, `other_plugin::body`"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = r#"plugins ["Weight"] are not registered"#)]
+    fn new_for_test_reports_missing_plugins() {
+        Context::new_for_test(&["Age", "Weight"]);
+    }
+
+    #[test]
+    fn require_returns_enabled_plugins() {
+        let context = Context::new();
+        assert_eq!(context.require("Age").name, "Age");
+    }
+
+    #[test]
+    #[should_panic(expected = r#"plugin `Height` is not registered; the enabled plugins are ["initialization::Age"]"#)]
+    fn require_lists_enabled_plugins_when_missing() {
+        Context::new().require("Height");
+    }
+
+    #[test]
+    fn get_and_set_age() {
+        let mut context = Context::new();
+        let person_id = context.create_person().unwrap();
+        assert_eq!(context.get::<u32>("Age", person_id), Some(&42));
+
+        context.set::<u32>("Age", person_id, 30).unwrap();
+        assert_eq!(context.get::<u32>("Age", person_id), Some(&30));
+        assert_eq!(context.get::<u32>("initialization::Age", person_id), Some(&30));
+        assert_eq!(context.get::<u32>("Age", PersonId(person_id.0 + 1)), None);
+        assert_eq!(
+            context.set::<u32>("Age", PersonId(person_id.0 + 1), 30),
+            Err(InitError::Runtime(RuntimeError::NoSuchPerson(PersonId(person_id.0 + 1))))
+        );
+        assert_eq!(
+            context.set::<u32>("Height", person_id, 30),
+            Err(InitError::Config(ConfigError::UnknownPlugin("Height".to_string())))
+        );
+    }
+
+    #[test]
+    fn iter_property_yields_every_persons_value() {
+        let mut context = Context::new();
+        context.create_people(3).unwrap();
+        let ages: Vec<(PersonId, &u32)> = context.iter_property::<u32>("Age").collect();
+        assert_eq!(ages, vec![(PersonId(0), &42), (PersonId(1), &42), (PersonId(2), &42)]);
+
+        context.remove_person(PersonId(1)).unwrap();
+        let ages: Vec<(PersonId, &u32)> = context.iter_property::<u32>("Age").collect();
+        assert_eq!(ages, vec![(PersonId(0), &42), (PersonId(2), &42)]);
+        assert_eq!(context.iter_property::<String>("Age").count(), 0);
+    }
+
+    #[test]
+    fn export_csv_writes_a_row_per_person() {
+        let mut context = Context::new();
+        context.create_people(2).unwrap();
+        context.set::<u32>("Age", PersonId(1), 30).unwrap();
+
+        let mut csv = Vec::new();
+        context.export_csv(&mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), "person_id,Age\n0,42\n1,30\n");
+    }
+
+    static CREW_SIZE: Property<u8> = Property{ name: "CrewSize", default: 3, initializer: None };
+
+    static CREW_PLUGINS: [Plugin; 1] = [Plugin::builder("Crew")
+        .properties(&[PropertySpec{
+            name: CREW_SIZE.name,
+            constructor: |context| {
+                CREW_SIZE.construct(context);
+                context.register_formatter::<u8>(CREW_SIZE.name, u8::to_string);
+            },
+            initializer: |context, person_id| CREW_SIZE.initialize(context, person_id),
+        }])
+        .build()];
+
+    #[test]
+    fn export_csv_writes_the_columns_of_properties() {
+        let mut context = Context::with_plugins(&CREW_PLUGINS);
+        context.create_people(2).unwrap();
+        context.set::<u8>("CrewSize", PersonId(0), 5).unwrap();
+
+        let mut csv = Vec::new();
+        context.export_csv(&mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), "person_id,CrewSize\n0,5\n1,3\n");
+
+        context.disable("Crew").unwrap();
+        let mut csv = Vec::new();
+        context.export_csv(&mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), "person_id\n0\n1\n");
+    }
+
+    // Only people with an even id have a diagnosis; the others hold an unset placeholder.
+    static SPARSE_PLUGINS: [Plugin; 1] = [Plugin::builder("Diagnosis")
+        .initializer(|context, person_id| {
+            context.property_mut::<u8>("Diagnosis").unwrap().push(7);
+            if person_id.0 % 2 == 1 {
+                context.unset::<u8>("Diagnosis", person_id)?;
+            }
+            Ok(())
+        })
+        .constructor(|context| {
+            context.register_property::<u8>("Diagnosis");
+            context.register_codec::<u8>("Diagnosis", u8::to_string, |value| value.parse().ok());
+        })
+        .build()];
+
+    #[test]
+    fn unset_slots_read_as_none() {
+        let mut context = Context::with_plugins(&SPARSE_PLUGINS);
+        context.create_people(4).unwrap();
+        assert_eq!(context.property::<u8>("Diagnosis").unwrap().len(), 4);
+        assert_eq!(context.get::<u8>("Diagnosis", PersonId(0)), Some(&7));
+        assert_eq!(context.get::<u8>("Diagnosis", PersonId(1)), None);
+        let diagnoses: Vec<(PersonId, &u8)> = context.iter_property::<u8>("Diagnosis").collect();
+        assert_eq!(diagnoses, vec![(PersonId(0), &7), (PersonId(2), &7)]);
+
+        let mut checkpoint = Vec::new();
+        context.save(&mut checkpoint).unwrap();
+        let loaded = ContextBuilder::with_plugins(&SPARSE_PLUGINS).load(checkpoint.as_slice()).unwrap();
+        assert_eq!(loaded.get::<u8>("Diagnosis", PersonId(3)), None);
+
+        context.set::<u8>("Diagnosis", PersonId(1), 3).unwrap();
+        assert_eq!(context.get::<u8>("Diagnosis", PersonId(1)), Some(&3));
+        context.unset::<u8>("Diagnosis", PersonId(2)).unwrap();
+        assert_eq!(context.get::<u8>("Diagnosis", PersonId(2)), None);
+        context.remove_person(PersonId(2)).unwrap();
+        assert_eq!(context.create_person(), Ok(PersonId(2)));
+        assert_eq!(context.get::<u8>("Diagnosis", PersonId(2)), Some(&7));
+    }
+
+    #[test]
+    fn removed_person_ids_are_reused() {
+        let mut context = Context::new();
+        assert_eq!(context.create_people(3), Ok(PersonRange::from(0..3)));
+        context.set::<u32>("Age", PersonId(1), 30).unwrap();
+
+        assert_eq!(context.remove_person(PersonId(1)), Ok(()));
+        assert_eq!(context.get::<u32>("Age", PersonId(1)), None);
+        let no_such_person = InitError::Runtime(RuntimeError::NoSuchPerson(PersonId(1)));
+        assert_eq!(context.set::<u32>("Age", PersonId(1), 30), Err(no_such_person.clone()));
+        assert_eq!(context.validate_person(PersonId(1)), Err(no_such_person.clone()));
+        assert_eq!(context.remove_person(PersonId(1)), Err(no_such_person));
+
+        assert_eq!(context.create_person(), Ok(PersonId(1)));
+        assert_eq!(context.get::<u32>("Age", PersonId(1)), Some(&42));
+        assert_eq!(context.property::<u32>("Age").unwrap().len(), 3);
+        assert_eq!(context.create_person(), Ok(PersonId(3)));
+    }
+
+    #[test]
+    fn get_and_set_with_the_wrong_type() {
+        let mut context = Context::new();
+        let person_id = context.create_person().unwrap();
+        assert_eq!(context.get::<i64>("Age", person_id), None);
+
+        let error = context.set::<i64>("Age", person_id, 30).err().unwrap();
+        assert_eq!(error, InitError::Runtime(RuntimeError::TypeMismatch { plugin: "Age", type_name: "i64" }));
+        assert_eq!(error.to_string(), "plugin `Age` has no column of type `i64`");
+        assert_eq!(context.get::<u32>("Age", person_id), Some(&42));
+    }
+
+    static COHORT: Property<u16> = Property{ name: "Cohort", default: 1990, initializer: None };
+
+    static COHORT_PLUGINS: [Plugin; 1] = [Plugin::builder("Cohort")
+        .immutable(true)
+        .constructor(|context| COHORT.construct(context))
+        .initializer(|context, person_id| COHORT.initialize(context, person_id))
+        .build()];
+
+    #[test]
+    fn immutable_values_are_only_written_by_the_initializer() {
+        let mut context = Context::with_plugins(&COHORT_PLUGINS);
+        let person_id = context.create_person().unwrap();
+        assert_eq!(context.get::<u16>("Cohort", person_id), Some(&1990));
+
+        let immutable = InitError::Runtime(RuntimeError::Immutable { plugin: "Cohort", person_id });
+        assert_eq!(context.set::<u16>("Cohort", person_id, 2000), Err(immutable.clone()));
+        assert_eq!(immutable.to_string(), "the value of plugin `Cohort` for person 0 is immutable");
+        let handle = context.handle::<u16>("Cohort").unwrap();
+        assert_eq!(context.set_by_handle(handle, person_id, 2000), Err(immutable));
+        assert_eq!(context.get::<u16>("Cohort", person_id), Some(&1990));
+    }
+
+    static GRADUATION: Property<u16> = Property{ name: "Graduation", default: 0, initializer: None };
+
+    // The graduation year is not known when a person is created, so the initializer leaves it unset.
+    static GRADUATION_PLUGINS: [Plugin; 1] = [Plugin::builder("Graduation")
+        .immutable(true)
+        .constructor(|context| GRADUATION.construct(context))
+        .initializer(|context, person_id| {
+            GRADUATION.initialize(context, person_id)?;
+            context.unset::<u16>("Graduation", person_id)
+        })
+        .build()];
+
+    #[test]
+    fn immutable_values_that_are_unset_are_set_once() {
+        let mut context = Context::with_plugins(&GRADUATION_PLUGINS);
+        let person_id = context.create_person().unwrap();
+        assert_eq!(context.get::<u16>("Graduation", person_id), None);
+
+        assert_eq!(context.set::<u16>("Graduation", person_id, 2012), Ok(()));
+        assert_eq!(context.get::<u16>("Graduation", person_id), Some(&2012));
+        let immutable = InitError::Runtime(RuntimeError::Immutable { plugin: "Graduation", person_id });
+        assert_eq!(context.set::<u16>("Graduation", person_id, 2013), Err(immutable));
+
+        let other = context.create_person().unwrap();
+        let handle = context.handle::<u16>("Graduation").unwrap();
+        assert_eq!(context.set_by_handle(handle, other, 2014), Ok(()));
+        let immutable = InitError::Runtime(RuntimeError::Immutable { plugin: "Graduation", person_id: other });
+        assert_eq!(context.set_by_handle(handle, other, 2015), Err(immutable));
+        assert_eq!(context.get::<u16>("Graduation", other), Some(&2014));
+    }
+
+    static BIRTH_YEAR: Property<u16> = Property{ name: "BirthYear", default: 1990, initializer: None };
+
+    static BIRTH_PLUGINS: [Plugin; 1] = [Plugin::builder("Birth")
+        .immutable(true)
+        .properties(&[PropertySpec{
+            name: BIRTH_YEAR.name,
+            constructor: |context| BIRTH_YEAR.construct(context),
+            // Left unset until the birth is recorded.
+            initializer: |context, person_id| {
+                BIRTH_YEAR.initialize(context, person_id)?;
+                context.unset::<u16>(BIRTH_YEAR.name, person_id)
+            },
+        }])
+        .build()];
+
+    #[test]
+    fn immutable_plugins_protect_the_values_of_their_properties() {
+        let mut context = Context::with_plugins(&BIRTH_PLUGINS);
+        let person_id = context.create_person().unwrap();
+        assert_eq!(context.set::<u16>("BirthYear", person_id, 2000), Ok(()));
+
+        let immutable = InitError::Runtime(RuntimeError::Immutable { plugin: "Birth", person_id });
+        assert_eq!(context.set::<u16>("BirthYear", person_id, 2001), Err(immutable.clone()));
+        let handle = context.handle::<u16>("BirthYear").unwrap();
+        assert_eq!(context.set_by_handle(handle, person_id, 2002), Err(immutable));
+        assert_eq!(context.get::<u16>("BirthYear", person_id), Some(&2000));
+    }
+
+    // A chain A -> B -> C where C needs B and B needs A, declared out of order.
+    #[distributed_slice]
+    static CHAIN_PLUGINS: [Plugin];
+
+    #[distributed_slice(CHAIN_PLUGINS)]
+    static C_PLUGIN: Plugin = Plugin{
+        name: "C",
+        description: "Needs B",
+        dependencies: &["B"],
+        constructor: |context| {
+            context.register_plugin(&C_PLUGIN);
+        },
+        ..Plugin::DEFAULT
+    };
+
+    #[distributed_slice(CHAIN_PLUGINS)]
+    static A_PLUGIN: Plugin = Plugin{
+        name: "A",
+        description: "Needs nothing",
+        constructor: |context| {
+            context.register_plugin(&A_PLUGIN);
+        },
+        ..Plugin::DEFAULT
+    };
+
+    #[distributed_slice(CHAIN_PLUGINS)]
+    static B_PLUGIN: Plugin = Plugin{
+        name: "B",
+        description: "Needs A",
+        dependencies: &["A"],
+        constructor: |context| {
+            context.register_plugin(&B_PLUGIN);
+        },
+        ..Plugin::DEFAULT
+    };
+
+    #[test]
+    fn dependency_chain_initializes_in_order() {
+        let context = Context::with_plugins(CHAIN_PLUGINS.static_slice());
+        assert_eq!(context.init_order(), ["A", "B", "C"]);
+    }
+
+    // Only the fields `Plugin` had originally, so fields added since must not break it.
+    static ORIGINAL_FIELDS_PLUGINS: [Plugin; 1] = [Plugin{
+        name: "Original",
+        description: "Declared with the original fields",
+        required: true,
+        enabled: true,
+        initializer: |context, person_id| {
+            context.property_mut::<bool>("Original").unwrap().push(person_id.0 % 2 == 0);
+            Ok(())
+        },
+        constructor: |context| {
+            context.register_property::<bool>("Original");
+        },
+        ..Plugin::DEFAULT
+    }];
+
+    #[test]
+    fn plugin_with_only_the_original_fields() {
+        let mut context = Context::with_plugins(&ORIGINAL_FIELDS_PLUGINS);
+        let plugin = context.require("Original");
+        assert_eq!((plugin.namespace, plugin.priority, plugin.version), ("", 0, (1, 0, 0)));
+        assert_eq!(plugin.default_state, DefaultState::OptOut);
+
+        let person_id = context.create_person().unwrap();
+        assert_eq!(context.get::<bool>("Original", person_id), Some(&true));
+        assert_eq!(context.validate_person(person_id), Ok(()));
+    }
+
+    #[test]
+    fn empty_plugin_set_gives_an_empty_context() {
+        let mut context = Context::with_plugins(&[]);
+        assert!(context.plugins.is_empty());
+        assert!(context.instances().is_empty());
+        assert!(context.init_order().is_empty());
+        assert!(context.warnings().is_empty());
+        assert_eq!((context.metrics().seen, context.metrics().enabled, context.metrics().skipped), (0, 0, 0));
+        assert!(context.metrics().durations.is_empty());
+        assert_eq!(context.config_snapshot().to_json(), "[]");
+
+        assert_eq!(context.create_person(), Ok(PersonId(0)));
+        assert_eq!(context.create_people(2), Ok(PersonRange::from(1..3)));
+        assert_eq!(context.validate_person(PersonId(2)), Ok(()));
+        assert_eq!(context.get::<u32>("Age", PersonId(0)), None);
+        assert_eq!(context.enable("Age"), Err(InitError::Config(ConfigError::UnknownPlugin("Age".to_string()))));
+
+        let mut csv = Vec::new();
+        context.export_csv(&mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), "person_id\n0\n1\n2\n");
+    }
+
+    static DIAMOND_PLUGINS: [Plugin; 4] = [
+        Plugin{ name: "Top", dependencies: &["Left", "Right"], ..Plugin::DEFAULT },
+        Plugin{ name: "Left", dependencies: &["Root"], ..Plugin::DEFAULT },
+        Plugin{ name: "Right", optional_dependencies: &["Root", "Absent"], enabled: false, ..Plugin::DEFAULT },
+        Plugin{ name: "Root", ..Plugin::DEFAULT },
+    ];
+
+    #[test]
+    fn dependency_queries_follow_both_directions() {
+        let context = ContextBuilder::with_plugins(&DIAMOND_PLUGINS).disable("Top").build().unwrap();
+        assert_eq!(context.dependencies_of("Top"), vec!["Left", "Right"]);
+        assert_eq!(context.dependencies_of("Right"), vec!["Root"]);
+        assert!(context.dependencies_of("Root").is_empty());
+
+        assert_eq!(context.dependents_of("Root"), vec!["Left", "Right"]);
+        assert_eq!(context.dependents_of("Right"), vec!["Top"]);
+        assert!(context.dependents_of("Top").is_empty());
+        assert!(context.dependents_of("Absent").is_empty());
+    }
+
+    #[test]
+    fn to_dot_renders_nodes_and_edges() {
+        let dot = Context::new().to_dot();
+        assert_eq!(dot, "digraph plugins {\n    \"initialization::Age\" [label=\"Age\"];\n}\n");
+
+        let dot = ContextBuilder::with_plugins(&DIAMOND_PLUGINS).disable("Top").build().unwrap().to_dot();
+        assert!(dot.contains("    \"Right\" [label=\"Right\", style=dashed, color=gray];\n"));
+        assert!(dot.contains("    \"Root\" [label=\"Root\"];\n"));
+        assert!(dot.contains("    \"Top\" -> \"Left\";\n"));
+        assert!(dot.contains("    \"Right\" -> \"Root\" [style=dashed];\n"));
+        assert!(!dot.contains("Absent"));
+    }
+
+    #[test]
+    fn init_order_reflects_overrides_and_later_enables() {
+        let mut context = ContextBuilder::with_plugins(CHAIN_PLUGINS.static_slice()).disable("C").build().unwrap();
+        assert_eq!(context.init_order(), ["A", "B"]);
+        context.enable("C").unwrap();
+        assert_eq!(context.init_order(), ["A", "B", "C"]);
+    }
+
+    static UNSORTED_PLUGINS: [Plugin; 3] = [
+        Plugin{
+            name: "Zebra",
+            description: "Declared first",
+            constructor: |context| {
+                context.register_plugin(&UNSORTED_PLUGINS[0]);
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Apple",
+            description: "Declared second, needs the mango",
+            dependencies: &["Mango"],
+            constructor: |context| {
+                context.register_plugin(&UNSORTED_PLUGINS[1]);
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Mango",
+            description: "Declared last",
+            constructor: |context| {
+                context.register_plugin(&UNSORTED_PLUGINS[2]);
+            },
+            ..Plugin::DEFAULT
+        },
+    ];
+
+    #[test]
+    fn plugins_are_sorted_by_name() {
+        let context = Context::with_plugins(&UNSORTED_PLUGINS);
+        assert_eq!(context.names(), vec!["Apple", "Mango", "Zebra"]);
+
+        let init_order: Vec<&str> = context.active.iter().map(|plugin| plugin.name).collect();
+        assert_eq!(init_order, vec!["Mango", "Apple", "Zebra"]);
+    }
+
+    #[test]
+    fn plugin_ids_follow_sorted_name_order() {
+        let context = Context::with_plugins(&UNSORTED_PLUGINS);
+        let ids: Vec<Option<u32>> = ["Apple", "Mango", "Zebra"].iter().map(|name| context.plugin_id(name)).collect();
+        assert_eq!(ids, vec![Some(0), Some(1), Some(2)]);
+        for id in 0..3 {
+            let plugin = context.plugin_by_id(id).unwrap();
+            assert_eq!(context.plugin_id(plugin.name), Some(id));
+        }
+        assert_eq!(context.plugin_id("Unknown"), None);
+        assert!(context.plugin_by_id(3).is_none());
+    }
+
+    static PRIORITY_PLUGINS: [Plugin; 3] = [
+        Plugin{
+            name: "Late",
+            description: "Runs after the others",
+            priority: 5,
+            constructor: |context| {
+                context.register_plugin(&PRIORITY_PLUGINS[0]);
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Normal",
+            description: "Runs with the default priority",
+            constructor: |context| {
+                context.register_plugin(&PRIORITY_PLUGINS[1]);
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Core",
+            description: "Runs before the others",
+            priority: -10,
+            constructor: |context| {
+                context.register_plugin(&PRIORITY_PLUGINS[2]);
+            },
+            ..Plugin::DEFAULT
+        },
+    ];
+
+    #[test]
+    fn lower_priority_initializes_first() {
+        let context = Context::with_plugins(&PRIORITY_PLUGINS);
+        let init_order: Vec<&str> = context.active.iter().map(|plugin| plugin.name).collect();
+        assert_eq!(init_order, vec!["Core", "Normal", "Late"]);
+    }
+
+    static DEFAULT_STATE_PLUGINS: [Plugin; 3] = [
+        Plugin{
+            name: "Always",
+            description: "Unconditionally enabled",
+            tags: &["group"],
+            default_state: DefaultState::Unconditional,
+            constructor: |context| {
+                context.register_plugin(&DEFAULT_STATE_PLUGINS[0]);
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Extra",
+            description: "Opt-in",
+            tags: &["group"],
+            default_state: DefaultState::OptIn,
+            constructor: |context| {
+                context.register_plugin(&DEFAULT_STATE_PLUGINS[1]);
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Usual",
+            description: "Opt-out",
+            tags: &["group"],
+            constructor: |context| {
+                context.register_plugin(&DEFAULT_STATE_PLUGINS[2]);
+            },
+            ..Plugin::DEFAULT
+        },
+    ];
+
+    #[test]
+    fn default_states_without_overrides() {
+        let context = Context::with_plugins(&DEFAULT_STATE_PLUGINS);
+        assert_eq!(context.names(), vec!["Always", "Usual"]);
+    }
+
+    #[test]
+    fn default_states_with_overrides() {
+        let context = ContextBuilder::with_plugins(&DEFAULT_STATE_PLUGINS)
+            .enable("Extra")
+            .disable("Usual")
+            .enable("Always")
+            .build()
+            .unwrap();
+        assert_eq!(context.names(), vec!["Always", "Extra"]);
+
+        let context = ContextBuilder::with_plugins(&DEFAULT_STATE_PLUGINS).disable_tag("group").build().unwrap();
+        assert_eq!(context.names(), vec!["Always"]);
+
+        let error = ContextBuilder::with_plugins(&DEFAULT_STATE_PLUGINS).disable("Always").build().err().unwrap();
+        assert_eq!(error, InitError::Config(ConfigError::CannotDisable("Always")));
+        assert_eq!(error.to_string(), "plugin `Always` is unconditional and cannot be disabled");
+    }
+
+    #[test]
+    fn init_report_explains_skipped_plugins() {
+        let context = ContextBuilder::with_plugins(&DEFAULT_STATE_PLUGINS).disable("Usual").build().unwrap();
+        assert_eq!(
+            context.init_report(),
+            "initialized 1 of 3 plugins\n\
+             initialized:\n  Always\n\
+             skipped:\n  Extra: opt-in and not enabled\n  Usual: disabled by an override\n"
+        );
+
+        let mut context = ContextBuilder::with_plugins(&DEFAULT_STATE_PLUGINS).disable_tag("group").build().unwrap();
+        assert!(context.init_report().contains("  Usual: disabled by tag `group`\n"));
+        context.enable("Usual").unwrap();
+        context.disable("Usual").unwrap();
+        assert!(context.init_report().contains("  Usual: disabled at runtime\n"));
+        assert_eq!(context.instance("Usual").unwrap().skip_reason, Some(SkipReason::Runtime));
+    }
+
+    static TOGGLE_PLUGINS: [Plugin; 3] = [
+        Plugin{
+            name: "Base",
+            description: "Always present",
+            required: true,
+            constructor: |context| {
+                context.register_plugin(&TOGGLE_PLUGINS[0]);
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Extra",
+            description: "Disabled until enabled at runtime",
+            enabled: false,
+            initializer: |context, _person_id| {
+                context.property_mut::<i16>("Extra").unwrap().push(7);
+                Ok(())
+            },
+            constructor: |context| {
+                context.register_plugin(&TOGGLE_PLUGINS[1]);
+                context.register_property::<i16>("Extra");
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Needy",
+            description: "Depends on Extra",
+            enabled: false,
+            dependencies: &["Extra"],
+            constructor: |context| {
+                context.register_plugin(&TOGGLE_PLUGINS[2]);
+            },
+            ..Plugin::DEFAULT
+        },
+    ];
+
+    #[test]
+    fn toggling_a_plugin_updates_its_instance() {
+        let mut context = Context::with_plugins(&TOGGLE_PLUGINS);
+        assert!(!context.instance("Extra").unwrap().enabled);
+
+        context.enable("Extra").unwrap();
+        assert_eq!(context.names(), vec!["Base", "Extra"]);
+        assert!(context.instance("Extra").unwrap().enabled);
+        assert_eq!(context.instance("Extra").unwrap().columns.len(), 1);
+        context.create_person().unwrap();
+        assert_eq!(context.property::<i16>("Extra"), Some(&vec![7]));
+
+        // Enabling twice does nothing.
+        context.enable("Extra").unwrap();
+        assert_eq!(context.names(), vec!["Base", "Extra"]);
+
+        context.disable("Extra").unwrap();
+        assert_eq!(context.names(), vec!["Base"]);
+        assert!(!context.instance("Extra").unwrap().enabled);
+        assert!(context.instance("Extra").unwrap().columns.is_empty());
+        assert_eq!(context.property::<i16>("Extra"), None);
+        context.create_person().unwrap();
+    }
+
+    #[test]
+    fn plugin_enabled_after_people_exist_has_no_values() {
+        let mut context = Context::with_plugins(&TOGGLE_PLUGINS);
+        context.create_person().unwrap();
+        context.enable("Extra").unwrap();
+        context.create_person().unwrap();
+        assert_eq!(context.property::<i16>("Extra"), Some(&vec![]));
+    }
+
+    #[test]
+    fn backfill_populates_existing_people() {
+        let mut context = Context::with_plugins(&TOGGLE_PLUGINS);
+        for _ in 0..5 {
+            context.create_person().unwrap();
+        }
+        assert_eq!(context.backfill("Extra"), Err(InitError::Runtime(RuntimeError::PluginDisabled("Extra"))));
+
+        context.enable("Extra").unwrap();
+        context.backfill("Extra").unwrap();
+        assert_eq!(context.property::<i16>("Extra"), Some(&vec![7; 5]));
+
+        context.backfill("Extra").unwrap();
+        assert_eq!(context.property::<i16>("Extra"), Some(&vec![7; 5]));
+        context.create_person().unwrap();
+        assert_eq!(context.property::<i16>("Extra"), Some(&vec![7; 6]));
+    }
+
+    #[test]
+    fn invalid_toggles_are_rejected() {
+        let mut context = Context::with_plugins(&TOGGLE_PLUGINS);
+        assert_eq!(context.disable("Base"), Err(InitError::Config(ConfigError::DisableRequired("Base"))));
+        assert_eq!(
+            context.enable("Needy"),
+            Err(InitError::Config(ConfigError::DisabledDependency { plugin: "Needy", needs: "Extra" }))
+        );
+        assert_eq!(context.enable("Nobody"), Err(InitError::Config(ConfigError::UnknownPlugin("Nobody".to_string()))));
+
+        context.enable("Extra").unwrap();
+        context.enable("Needy").unwrap();
+        let error = context.disable("Extra").err().unwrap();
+        assert_eq!(error, InitError::Config(ConfigError::HasDependent { plugin: "Extra", dependent: "Needy" }));
+        assert_eq!(error.to_string(), "plugin `Extra` cannot be disabled because `Needy` depends on it");
+
+        context.disable("Needy").unwrap();
+        context.disable("Extra").unwrap();
+        assert_eq!(context.names(), vec!["Base"]);
+    }
+
+    // Not registered anywhere, for adding to a `Context` after it is built.
+    static DYNAMIC_PLUGINS: [Plugin; 3] = [
+        Plugin::builder("Bonus")
+            .dependencies(&["Base"])
+            .initializer(|context, _person_id| {
+                context.property_mut::<u8>("Bonus").unwrap().push(3);
+                Ok(())
+            })
+            .constructor(|context| context.register_property::<u8>("Bonus"))
+            .build(),
+        Plugin::builder("Base").build(),
+        Plugin::builder("Needier").dependencies(&["Extra"]).build(),
+    ];
+
+    #[test]
+    fn dynamic_plugins_join_after_construction() {
+        let mut context = Context::with_plugins(&TOGGLE_PLUGINS);
+        context.add_dynamic_plugin(&DYNAMIC_PLUGINS[0]).unwrap();
+        assert_eq!(context.names(), vec!["Base", "Bonus"]);
+        assert_eq!(context.metrics().seen, 4);
+
+        context.create_person().unwrap();
+        assert_eq!(context.get::<u8>("Bonus", PersonId(0)), Some(&3));
+
+        assert_eq!(
+            context.add_dynamic_plugin(&DYNAMIC_PLUGINS[1]),
+            Err(InitError::Config(ConfigError::DuplicateName {
+                namespace: "",
+                name: "Base",
+                count: 2,
+                origins: vec!["", ""],
+            }))
+        );
+        assert_eq!(
+            context.add_dynamic_plugin(&DYNAMIC_PLUGINS[2]),
+            Err(InitError::Config(ConfigError::DisabledDependency { plugin: "Needier", needs: "Extra" }))
+        );
+        assert_eq!(context.instances().len(), 4);
+        assert_eq!(context.metrics().seen, 4);
+
+        // A rejected plugin is still the caller's, so it can be added once its dependency is enabled.
+        context.enable("Extra").unwrap();
+        context.add_dynamic_plugin(&DYNAMIC_PLUGINS[2]).unwrap();
+        assert_eq!(context.names(), vec!["Base", "Bonus", "Extra", "Needier"]);
+    }
+
+    #[test]
+    fn dynamic_plugins_may_have_owned_names() {
+        let mut context = Context::with_plugins(&TOGGLE_PLUGINS);
+        let names: Vec<String> = (1..=2).map(|index| format!("Generated{}", index)).collect();
+        for name in names {
+            let description = format!("Generated at runtime as {}", name);
+            let plugin = Plugin::builder(name.leak()).description(description.leak()).build();
+            context.add_dynamic_plugin(Box::leak(Box::new(plugin))).unwrap();
+        }
+
+        assert_eq!(context.names(), vec!["Base", "Generated1", "Generated2"]);
+        assert_eq!(context.require("Generated2").description, "Generated at runtime as Generated2");
+    }
+
+    static TORN_DOWN: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+    // Declared in the opposite of dependency order, so that only the dependencies decide the order.
+    static TEARDOWN_PLUGINS: [Plugin; 3] = [
+        Plugin::builder("Top")
+            .dependencies(&["Middle"])
+            .teardown(|_context| TORN_DOWN.lock().unwrap().push("Top"))
+            .build(),
+        Plugin::builder("Middle")
+            .dependencies(&["Bottom"])
+            .teardown(|_context| TORN_DOWN.lock().unwrap().push("Middle"))
+            .build(),
+        Plugin::builder("Bottom").teardown(|_context| TORN_DOWN.lock().unwrap().push("Bottom")).build(),
+    ];
+
+    #[test]
+    fn teardown_runs_in_reverse_init_order() {
+        let mut context = Context::with_plugins(&TEARDOWN_PLUGINS);
+        context.shutdown();
+
+        let mut init_order = context.init_order().to_vec();
+        init_order.reverse();
+        assert_eq!(*TORN_DOWN.lock().unwrap(), init_order);
+        assert_eq!(init_order, vec!["Top", "Middle", "Bottom"]);
+    }
+
+    static TEARDOWNS: AtomicUsize = AtomicUsize::new(0);
+
+    static DROPPED_PLUGINS: [Plugin; 1] = [Plugin::builder("Dropped")
+        .teardown(|_context| {
+            TEARDOWNS.fetch_add(1, Ordering::SeqCst);
+        })
+        .build()];
+
+    #[test]
+    fn dropping_the_context_tears_down_once() {
+        let context = Context::with_plugins(&DROPPED_PLUGINS);
+        assert_eq!(TEARDOWNS.load(Ordering::SeqCst), 0);
+        drop(context);
+        assert_eq!(TEARDOWNS.load(Ordering::SeqCst), 1);
+
+        let mut context = Context::with_plugins(&DROPPED_PLUGINS);
+        context.shutdown();
+        context.shutdown();
+        drop(context);
+        assert_eq!(TEARDOWNS.load(Ordering::SeqCst), 2);
+    }
+
+    static UNWOUND: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+    static UNWINDING_PLUGINS: [Plugin; 3] = [
+        Plugin::builder("Opened").teardown(|_context| UNWOUND.lock().unwrap().push("Opened")).build(),
+        Plugin::builder("Cached")
+            .dependencies(&["Opened"])
+            .teardown(|_context| UNWOUND.lock().unwrap().push("Cached"))
+            .build(),
+        // Has a batch initializer but registers no column, so its `init` fails.
+        Plugin::builder("Broken")
+            .dependencies(&["Cached"])
+            .batch_initializer(|_column, _people| Ok(()))
+            .teardown(|_context| UNWOUND.lock().unwrap().push("Broken"))
+            .build(),
+    ];
+
+    #[test]
+    fn a_failed_build_tears_down_the_initialized_plugins() {
+        let error = Context::try_with_plugins(&UNWINDING_PLUGINS).err();
+        assert_eq!(error, Some(InitError::Config(ConfigError::BatchColumnCount { plugin: "Broken", count: 0 })));
+        assert_eq!(*UNWOUND.lock().unwrap(), vec!["Cached", "Opened"]);
+    }
+
+    // A data plugin: it has no column, only a table for the initializers of other plugins.
+    static LOOKUP_PLUGINS: [Plugin; 1] = [Plugin::builder("Regions")
+        .constructor(|context| {
+            let regions = context.global_mut::<Vec<String>>();
+            regions.extend(["North", "South"].map(String::from));
+        })
+        .build()];
+
+    #[test]
+    fn plugins_share_global_state() {
+        let mut context = Context::with_plugins(&LOOKUP_PLUGINS);
+        assert_eq!(context.global::<Vec<String>>(), Some(&vec!["North".to_string(), "South".to_string()]));
+        assert_eq!(context.global::<u32>(), None);
+
+        context.global_mut::<Vec<String>>().push("East".to_string());
+        assert_eq!(context.global::<Vec<String>>().unwrap().len(), 3);
+        *context.global_mut::<u32>() += 1;
+        assert_eq!(context.global::<u32>(), Some(&1));
+    }
+
+    // The second constructor was copied from the first without changing the `static` it registers.
+    static COPIED_CONSTRUCTOR_PLUGINS: [Plugin; 2] = [
+        Plugin{
+            name: "Original",
+            enabled: false,
+            constructor: |context| context.register_plugin(&COPIED_CONSTRUCTOR_PLUGINS[0]),
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Copy",
+            constructor: |context| context.register_plugin(&COPIED_CONSTRUCTOR_PLUGINS[0]),
+            ..Plugin::DEFAULT
+        },
+    ];
+
+    // The second constructor registers a different `static` with the name of a plugin that is already initialized.
+    static ALIASED_CONSTRUCTOR_PLUGINS: [Plugin; 2] = [
+        Plugin{
+            name: "Original",
+            constructor: |context| context.register_plugin(&ALIASED_CONSTRUCTOR_PLUGINS[0]),
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Alias",
+            dependencies: &["Original"],
+            constructor: |context| context.register_plugin(&ALIASED_ORIGINAL_PLUGIN),
+            ..Plugin::DEFAULT
+        },
+    ];
+
+    static ALIASED_ORIGINAL_PLUGIN: Plugin = Plugin{ name: "Original", ..Plugin::DEFAULT };
+
+    #[test]
+    fn constructor_registering_an_initialized_plugin_is_rejected() {
+        let error = ContextBuilder::with_plugins(&ALIASED_CONSTRUCTOR_PLUGINS).build().err().unwrap();
+        assert_eq!(
+            error,
+            InitError::Config(ConfigError::ForeignRegistration { plugin: "Alias", registered: "Original" })
+        );
+    }
+
+    #[test]
+    fn constructor_registering_another_plugin_is_rejected() {
+        let error = ContextBuilder::with_plugins(&COPIED_CONSTRUCTOR_PLUGINS).build().err().unwrap();
+        assert_eq!(
+            error,
+            InitError::Config(ConfigError::ForeignRegistration { plugin: "Copy", registered: "Original" })
+        );
+        assert_eq!(
+            error.to_string(),
+            "the constructor of plugin `Copy` registered the plugin `Original` instead of its own"
+        );
+    }
+
+    static MISSING_DEPENDENCY_PLUGINS: [Plugin; 1] = [Plugin{
+        name: "Orphan",
+        description: "Needs a plugin nobody registered",
+        dependencies: &["Nobody"],
+        constructor: |context| {
+            context.register_plugin(&MISSING_DEPENDENCY_PLUGINS[0]);
+        },
+        ..Plugin::DEFAULT
+    }];
+
+    #[test]
+    fn missing_dependency_is_reported() {
+        let error = Context::try_with_plugins(&MISSING_DEPENDENCY_PLUGINS).err().unwrap();
+        assert_eq!(error, InitError::Config(ConfigError::MissingDependency { plugin: "Orphan", needs: "Nobody" }));
+        assert_eq!(error.to_string(), "plugin `Orphan` depends on `Nobody`, which is not registered");
+    }
+
+    static DISABLED_DEPENDENCY_PLUGINS: [Plugin; 2] = [
+        Plugin{
+            name: "Dependent",
+            description: "Needs a disabled plugin",
+            dependencies: &["Disabled"],
+            constructor: |context| {
+                context.register_plugin(&DISABLED_DEPENDENCY_PLUGINS[0]);
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Disabled",
+            description: "Turned off",
+            enabled: false,
+            constructor: |context| {
+                context.register_plugin(&DISABLED_DEPENDENCY_PLUGINS[1]);
+            },
+            ..Plugin::DEFAULT
+        },
+    ];
+
+    #[test]
+    fn disabled_dependency_is_reported() {
+        let error = Context::try_with_plugins(&DISABLED_DEPENDENCY_PLUGINS).err().unwrap();
+        assert_eq!(
+            error,
+            InitError::Config(ConfigError::DisabledDependency { plugin: "Dependent", needs: "Disabled" })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "plugin `Dependent` depends on `Disabled`, which is disabled")]
+    fn with_plugins_panics_on_invalid_plugins() {
+        Context::with_plugins(&DISABLED_DEPENDENCY_PLUGINS);
+    }
+
+    static VERSIONED_PLUGINS: [Plugin; 3] = [
+        Plugin{
+            name: "Height",
+            description: "Height in cm",
+            version: (1, 4, 2),
+            constructor: |context| {
+                context.register_plugin(&VERSIONED_PLUGINS[0]);
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Bmi",
+            description: "Needs Height 1.2 or later",
+            dependencies: &["Height"],
+            dependency_versions: &[("Height", (1, 2, 0))],
+            constructor: |context| {
+                context.register_plugin(&VERSIONED_PLUGINS[1]);
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Ratio",
+            description: "Needs Height 2.0 or later",
+            enabled: false,
+            dependencies: &["Height"],
+            dependency_versions: &[("Height", (2, 0, 0))],
+            constructor: |context| {
+                context.register_plugin(&VERSIONED_PLUGINS[2]);
+            },
+            ..Plugin::DEFAULT
+        },
+    ];
+
+    #[test]
+    fn satisfied_version_constraint() {
+        let context = Context::with_plugins(&VERSIONED_PLUGINS);
+        assert_eq!(context.names(), vec!["Bmi", "Height"]);
+    }
+
+    #[test]
+    fn unsatisfied_version_constraint() {
+        let error = ContextBuilder::with_plugins(&VERSIONED_PLUGINS).enable("Ratio").build().err().unwrap();
+        assert_eq!(
+            error,
+            InitError::Config(ConfigError::DependencyTooOld {
+                plugin: "Ratio",
+                needs: "Height",
+                found: (1, 4, 2),
+                required: (2, 0, 0),
+            })
+        );
+        assert_eq!(error.to_string(), "plugin `Ratio` needs `Height` version 2.0.0 or later, but found version 1.4.2");
+    }
+
+    static CONFLICTING_PLUGINS: [Plugin; 3] = [
+        Plugin{
+            name: "Urban",
+            description: "Lives in a city",
+            conflicts_with: &["Rural"],
+            constructor: |context| {
+                context.register_plugin(&CONFLICTING_PLUGINS[0]);
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Rural",
+            description: "Lives in the countryside",
+            conflicts_with: &["Urban"],
+            constructor: |context| {
+                context.register_plugin(&CONFLICTING_PLUGINS[1]);
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Suburban",
+            description: "Lives in a suburb",
+            enabled: false,
+            conflicts_with: &["Urban"],
+            constructor: |context| {
+                context.register_plugin(&CONFLICTING_PLUGINS[2]);
+            },
+            ..Plugin::DEFAULT
+        },
+    ];
+
+    #[test]
+    fn conflicting_plugins_cannot_both_be_enabled() {
+        let error = Context::try_with_plugins(&CONFLICTING_PLUGINS).err().unwrap();
+        assert_eq!(error, InitError::Config(ConfigError::Conflict { plugin: "Rural", other: "Urban" }));
+        assert_eq!(error.to_string(), "plugins `Rural` and `Urban` conflict and cannot both be enabled");
+
+        let mut context = ContextBuilder::with_plugins(&CONFLICTING_PLUGINS).disable("Rural").build().unwrap();
+        assert_eq!(context.names(), vec!["Urban"]);
+
+        // Only `Suburban` declares the conflict.
+        let error = ContextBuilder::with_plugins(&CONFLICTING_PLUGINS)
+            .disable("Rural")
+            .enable("Suburban")
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(error, InitError::Config(ConfigError::Conflict { plugin: "Suburban", other: "Urban" }));
+        assert_eq!(
+            context.enable("Suburban"),
+            Err(InitError::Config(ConfigError::Conflict { plugin: "Suburban", other: "Urban" }))
+        );
+        assert_eq!(context.names(), vec!["Urban"]);
+    }
+
+    static ALIASED_PLUGINS: [Plugin; 1] = [
+        Plugin{
+            name: "Mass",
+            aliases: &["Weight", "Heft"],
+            description: "Mass of the person, formerly `Weight`",
+            constructor: |context| {
+                context.register_plugin(&ALIASED_PLUGINS[0]);
+            },
+            ..Plugin::DEFAULT
+        },
+    ];
+
+    static ALIAS_COLLISION_PLUGINS: [Plugin; 2] = [
+        Plugin{
+            name: "Weight",
+            description: "Weight of the person",
+            constructor: |context| {
+                context.register_plugin(&ALIAS_COLLISION_PLUGINS[0]);
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Mass",
+            aliases: &["Weight"],
+            description: "Mass of the person",
+            constructor: |context| {
+                context.register_plugin(&ALIAS_COLLISION_PLUGINS[1]);
+            },
+            ..Plugin::DEFAULT
+        },
+    ];
+
+    #[test]
+    fn aliases_resolve_to_the_renamed_plugin() {
+        let context = Context::with_plugins(&ALIASED_PLUGINS);
+        let mass = context.get_plugin("Mass").unwrap();
+        assert!(std::ptr::eq(context.get_plugin("Weight").unwrap(), mass));
+        assert!(std::ptr::eq(context.get_plugin("Heft").unwrap(), mass));
+        assert!(std::ptr::eq(context.instance("Weight").unwrap().plugin, mass));
+
+        let context = ContextBuilder::with_plugins(&ALIASED_PLUGINS).disable("Weight").build().unwrap();
+        assert!(context.plugins.is_empty());
+    }
+
+    #[test]
+    fn alias_colliding_with_a_name_is_reported() {
+        let error = Context::try_with_plugins(&ALIAS_COLLISION_PLUGINS).err().unwrap();
+        assert_eq!(
+            error,
+            InitError::Config(ConfigError::DuplicateName {
+                namespace: "",
+                name: "Weight",
+                count: 2,
+                origins: vec!["", ""],
+            })
+        );
+    }
+
+    static CYCLE_PLUGINS: [Plugin; 2] = [
+        Plugin{
+            name: "Chicken",
+            description: "Needs the egg",
+            dependencies: &["Egg"],
+            constructor: |context| {
+                context.register_plugin(&CYCLE_PLUGINS[0]);
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Egg",
+            description: "Needs the chicken",
+            dependencies: &["Chicken"],
+            constructor: |context| {
+                context.register_plugin(&CYCLE_PLUGINS[1]);
+            },
+            ..Plugin::DEFAULT
+        },
+    ];
+
+    #[test]
+    fn dependency_cycle_is_reported() {
+        let error = Context::try_with_plugins(&CYCLE_PLUGINS).err().unwrap();
+        assert_eq!(error, InitError::Config(ConfigError::DependencyCycle(vec!["Chicken", "Egg", "Chicken"])));
+        assert_eq!(error.to_string(), "plugin dependencies form a cycle: Chicken -> Egg -> Chicken");
+    }
+
+    // Two disjoint cycles, Rock -> Scissors -> Paper -> Rock and Left <-> Right, plus Bystander, which depends on the
+    // first cycle without being part of it.
+    static LONG_CYCLE_PLUGINS: [Plugin; 6] = [
+        Plugin{
+            name: "Bystander",
+            description: "Needs the rock",
+            dependencies: &["Rock"],
+            priority: -1,
+            constructor: |context| {
+                context.register_plugin(&LONG_CYCLE_PLUGINS[0]);
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Rock",
+            description: "Needs the scissors",
+            dependencies: &["Scissors"],
+            constructor: |context| {
+                context.register_plugin(&LONG_CYCLE_PLUGINS[1]);
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Scissors",
+            description: "Needs the paper",
+            dependencies: &["Paper"],
+            constructor: |context| {
+                context.register_plugin(&LONG_CYCLE_PLUGINS[2]);
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Paper",
+            description: "Needs the rock",
+            dependencies: &["Rock"],
+            constructor: |context| {
+                context.register_plugin(&LONG_CYCLE_PLUGINS[3]);
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Left",
+            description: "Needs the right",
+            dependencies: &["Right"],
+            constructor: |context| {
+                context.register_plugin(&LONG_CYCLE_PLUGINS[4]);
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Right",
+            description: "Needs the left",
+            dependencies: &["Left"],
+            constructor: |context| {
+                context.register_plugin(&LONG_CYCLE_PLUGINS[5]);
+            },
+            ..Plugin::DEFAULT
+        },
+    ];
+
+    #[test]
+    fn three_plugin_cycle_is_reported_as_a_path() {
+        let error = Context::try_with_plugins(&LONG_CYCLE_PLUGINS).err().unwrap();
+        assert_eq!(error, InitError::Config(ConfigError::DependencyCycle(vec!["Rock", "Scissors", "Paper", "Rock"])));
+    }
+
+    #[test]
+    fn remaining_cycle_is_reported_once_another_is_broken() {
+        let error = ContextBuilder::with_plugins(&LONG_CYCLE_PLUGINS)
+            .disable("Bystander")
+            .disable("Rock")
+            .disable("Scissors")
+            .disable("Paper")
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(error, InitError::Config(ConfigError::DependencyCycle(vec!["Left", "Right", "Left"])));
+    }
+
+    static CONTRADICTORY_PLUGINS: [Plugin; 1] = [Plugin{
+        name: "Contradictory",
+        description: "Required, yet disabled",
+        required: true,
+        enabled: false,
+        constructor: |context| {
+            context.register_plugin(&CONTRADICTORY_PLUGINS[0]);
+        },
+        ..Plugin::DEFAULT
+    }];
+
+    #[test]
+    fn required_but_disabled_is_reported() {
+        let error = Context::try_with_plugins(&CONTRADICTORY_PLUGINS).err().unwrap();
+        assert_eq!(error, InitError::Config(ConfigError::RequiredButDisabled("Contradictory")));
+        assert_eq!(error.to_string(), "plugin `Contradictory` is required but not enabled");
+    }
+
+    #[distributed_slice]
+    static DUPLICATE_PLUGINS: [Plugin];
+
+    #[distributed_slice(DUPLICATE_PLUGINS)]
+    static FIRST_AGE_PLUGIN: Plugin = Plugin{
+        name: "Age",
+        description: "Age of the person",
+        required: true,
+        constructor: |context| {
+            context.register_plugin(&FIRST_AGE_PLUGIN);
+        },
+        ..Plugin::DEFAULT
+    };
+
+    #[distributed_slice(DUPLICATE_PLUGINS)]
+    static SECOND_AGE_PLUGIN: Plugin = Plugin{
+        name: "Age",
+        description: "Age of the person, from another crate",
+        required: true,
+        constructor: |context| {
+            context.register_plugin(&SECOND_AGE_PLUGIN);
+        },
+        ..Plugin::DEFAULT
+    };
+
+    #[test]
+    fn check_unique_names_rejects_duplicates() {
+        assert_eq!(
+            check_unique_names(DUPLICATE_PLUGINS.static_slice()),
+            Err(InitError::Config(ConfigError::DuplicateName {
+                namespace: "",
+                name: "Age",
+                count: 2,
+                origins: vec!["", ""],
+            }))
+        );
+        assert_eq!(check_unique_names(PLUGINS.static_slice()), Ok(()));
+    }
+
+    #[test]
+    fn duplicate_names_are_reported() {
+        let error = Context::try_with_plugins(DUPLICATE_PLUGINS.static_slice()).err().unwrap();
+        assert_eq!(
+            error,
+            InitError::Config(ConfigError::DuplicateName {
+                namespace: "",
+                name: "Age",
+                count: 2,
+                origins: vec!["", ""],
+            })
+        );
+        assert_eq!(error.to_string(), "plugin name `Age` is used by 2 plugins");
+    }
+
+    static NAMESPACED_PLUGINS: [Plugin; 3] = [
+        Plugin{
+            name: "Weight",
+            namespace: "anthropometry",
+            description: "Weight of the person in kg",
+            constructor: |context| {
+                context.register_plugin(&NAMESPACED_PLUGINS[0]);
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Weight",
+            namespace: "livestock",
+            description: "Weight of the person's herd in kg",
+            constructor: |context| {
+                context.register_plugin(&NAMESPACED_PLUGINS[1]);
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: "Bmi",
+            namespace: "anthropometry",
+            description: "Body mass index",
+            dependencies: &["anthropometry::Weight"],
+            constructor: |context| {
+                context.register_plugin(&NAMESPACED_PLUGINS[2]);
+            },
+            ..Plugin::DEFAULT
+        },
+    ];
+
+    #[test]
+    fn same_name_in_different_namespaces_coexists() {
+        let context = Context::with_plugins(&NAMESPACED_PLUGINS);
+        assert_eq!(context.names(), vec!["Bmi", "Weight", "Weight"]);
+        assert_eq!(context.plugins[1].qualified_name(), "anthropometry::Weight");
+
+        assert_eq!(context.get_plugin("livestock::Weight").unwrap().description, "Weight of the person's herd in kg");
+        assert_eq!(context.get_plugin("Bmi").unwrap().qualified_name(), "anthropometry::Bmi");
+        assert!(context.get_plugin("Weight").is_none());
+        assert!(context.get_plugin("other::Weight").is_none());
+
+        let init_order: Vec<String> = context.active.iter().map(|plugin| plugin.qualified_name()).collect();
+        assert_eq!(init_order, vec!["anthropometry::Weight", "anthropometry::Bmi", "livestock::Weight"]);
+    }
+
+    #[test]
+    fn bare_overrides_must_be_unambiguous() {
+        let context = ContextBuilder::with_plugins(&NAMESPACED_PLUGINS).disable("livestock::Weight").build().unwrap();
+        assert_eq!(context.names(), vec!["Bmi", "Weight"]);
+
+        let error = ContextBuilder::with_plugins(&NAMESPACED_PLUGINS).disable("Weight").build().err().unwrap();
+        assert_eq!(error, InitError::Config(ConfigError::AmbiguousName("Weight".to_string())));
+        assert_eq!(error.to_string(), "plugin name `Weight` is ambiguous; qualify it as `namespace::Weight`");
+    }
+}
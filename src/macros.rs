@@ -0,0 +1,81 @@
+//! Declarative shorthand for registering a plugin.
+
+/// Declares a `static` `Plugin` registered in the global `PLUGINS` registry, with a `constructor` that registers the
+/// plugin itself before running the optional `constructor` body given after the fields. This saves writing the
+/// `#[distributed_slice(PLUGINS)]` attribute and a constructor that must name the right `static`. The fields are
+/// those of `Plugin` other than `constructor` and `origin`, which is set to the `module_path!()` of the invocation,
+/// and those left out are taken from `Plugin::DEFAULT`.
+///
+/// The macro also generates a `#[test]`, in a module named after the `static`, that fails if `check_unique_names`
+/// finds two plugins with the same name in `PLUGINS`. Since the test binary links every crate that registers plugins,
+/// `cargo test` in any of them catches a collision with another crate.
+///
+/// To declare a plugin only under a Cargo feature, put `#[cfg(feature = "...")]` on the invocation itself rather than
+/// among the attributes of the `static`, so that the generated test is left out along with the plugin. Without the
+/// feature the plugin is absent from `PLUGINS` altogether, and plugins that should still build without it must list it
+/// in `optional_dependencies`, since a missing hard dependency fails validation.
+///
+/// ```standalone_crate
+/// use initialization::{register_plugin, Context, Property};
+///
+/// static HEIGHT: Property<f64> = Property{
+///     name: "Height",
+///     default: 170.0,
+///     initializer: None,
+/// };
+///
+/// register_plugin! {
+///     static HEIGHT_PLUGIN: Plugin{
+///         name: HEIGHT.name,
+///         namespace: env!("CARGO_PKG_NAME"),
+///         description: "Height of the person in cm",
+///         initializer: |context, person_id| HEIGHT.initialize(context, person_id),
+///         batch_initializer: Some(|column, people| HEIGHT.initialize_batch(column, people)),
+///     };
+///     constructor: |context| {
+///         HEIGHT.construct(context);
+///     }
+/// }
+///
+/// let mut context = Context::new();
+/// assert!(std::ptr::eq(context.get_plugin("Height").unwrap(), &HEIGHT_PLUGIN));
+/// let person_id = context.create_person().unwrap();
+/// assert_eq!(context.get::<f64>("Height", person_id), Some(&170.0));
+/// ```
+#[macro_export]
+macro_rules! register_plugin {
+    (
+        $(#[$attribute:meta])*
+        $visibility:vis static $plugin:ident: Plugin{
+            $($field:ident: $value:expr),* $(,)?
+        };
+        $(constructor: |$context:ident| $constructor:block)?
+    ) => {
+        $(#[$attribute])*
+        #[$crate::__linkme::distributed_slice($crate::PLUGINS)]
+        #[linkme(crate = $crate::__linkme)]
+        $visibility static $plugin: $crate::Plugin = $crate::Plugin{
+            $($field: $value,)*
+            origin: module_path!(),
+            constructor: |context| {
+                context.register_plugin(&$plugin);
+                $(
+                    let $context: &mut $crate::Context = context;
+                    $constructor
+                )?
+            },
+            ..$crate::Plugin::DEFAULT
+        };
+
+        #[cfg(test)]
+        #[allow(non_snake_case)]
+        mod $plugin {
+            #[test]
+            fn plugin_names_are_unique() {
+                if let Err(error) = $crate::check_unique_names($crate::PLUGINS.static_slice()) {
+                    panic!("{}", error);
+                }
+            }
+        }
+    };
+}
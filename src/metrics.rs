@@ -0,0 +1,124 @@
+//! Counts and timings collected while a `Context` initializes its plugins.
+
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+use core::time::Duration;
+
+/// What happened while constructing a `Context`, returned by `Context::metrics`. Updated by `Context::enable` and
+/// `Context::disable`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InitMetrics {
+    /// The number of plugins the `Context` was built from.
+    pub seen: usize,
+    /// The number of plugins that are initialized.
+    pub enabled: usize,
+    /// The number of plugins that were not initialized, taking `ContextBuilder` overrides into account.
+    pub skipped: usize,
+    /// How long each call to `plugin.init` took, in the order the calls were made. Excludes the `before_each` and
+    /// `after_each` hooks. Empty without the `std` feature, which has no clock.
+    pub durations: Vec<(&'static str, Duration)>,
+}
+
+impl InitMetrics {
+    /// The total time spent in `plugin.init` calls.
+    pub fn total_duration(&self) -> Duration {
+        self.durations.iter().map(|&(_, duration)| duration).sum()
+    }
+}
+
+/// Why a plugin is not initialized, recorded in its `PluginInstance`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The plugin is `OptOut` with a static `enabled` flag of `false`.
+    Default,
+    /// The plugin is `OptIn` and nothing enabled it.
+    OptIn,
+    /// A `ContextBuilder` override or configuration file disabled the plugin by name.
+    Override,
+    /// A `ContextBuilder` override disabled the plugin's tag.
+    Tag(&'static str),
+    /// `Context::disable` disabled the plugin after construction.
+    Runtime,
+}
+
+impl Display for SkipReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            SkipReason::Default => write!(f, "disabled by default"),
+            SkipReason::OptIn => write!(f, "opt-in and not enabled"),
+            SkipReason::Override => write!(f, "disabled by an override"),
+            SkipReason::Tag(tag) => write!(f, "disabled by tag `{}`", tag),
+            SkipReason::Runtime => write!(f, "disabled at runtime"),
+        }
+    }
+}
+
+/// Whether a plugin is enabled, and if not, what disabled it, returned by `Context::plugin_states`. Coarser than
+/// `SkipReason`, for an overview of the whole configuration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PluginState {
+    /// The plugin is initialized.
+    Enabled,
+    /// The plugin's `default_state` or static `enabled` flag leaves it disabled.
+    DisabledByDefault,
+    /// A `ContextBuilder` override, a tag override or `Context::disable` disabled the plugin.
+    DisabledByOverride,
+    /// The plugin is disabled by default, and could not be enabled on its own either, since one of its `dependencies`
+    /// failed: it is missing or ambiguous, disabled by an override, or disabled by a failed dependency itself. A
+    /// dependency that is merely disabled by default has not failed, since enabling it would do.
+    DisabledByFailedDependency,
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::PluginState;
+    use crate::{Context, ContextBuilder, DefaultState, Plugin};
+
+    #[test]
+    fn counts_reflect_the_enabled_built_in_plugins() {
+        let context = Context::new();
+        let metrics = context.metrics();
+        assert_eq!(metrics.enabled, context.plugins.len());
+        assert_eq!(metrics.enabled, 1);
+        assert_eq!(metrics.seen, 1);
+        assert_eq!(metrics.skipped, 0);
+        let names: Vec<&str> = metrics.durations.iter().map(|&(name, _)| name).collect();
+        assert_eq!(names, vec!["Age"]);
+        assert_eq!(metrics.total_duration(), metrics.durations[0].1);
+
+        let context = ContextBuilder::with_plugins(&STATE_PLUGINS).disable("Schools").build().unwrap();
+        let metrics = context.metrics();
+        assert_eq!((metrics.seen, metrics.enabled, metrics.skipped), (7, 1, 6));
+        let names: Vec<&str> = metrics.durations.iter().map(|&(name, _)| name).collect();
+        assert_eq!(names, vec!["Muted"]);
+    }
+
+    static STATE_PLUGINS: [Plugin; 7] = [
+        Plugin::builder("Census").default_state(DefaultState::OptIn).build(),
+        Plugin::builder("Households").dependencies(&["Census"]).enabled(false).build(),
+        Plugin::builder("Muted").build(),
+        Plugin::builder("Orphans").dependencies(&["Registry"]).enabled(false).build(),
+        Plugin::builder("Reports").dependencies(&["Surveys"]).enabled(false).build(),
+        Plugin::builder("Schools").build(),
+        Plugin::builder("Surveys").dependencies(&["Muted"]).enabled(false).build(),
+    ];
+
+    #[test]
+    fn plugin_states_tell_why_plugins_are_disabled() {
+        let context = ContextBuilder::with_plugins(&STATE_PLUGINS).disable("Muted").build().unwrap();
+        let states: Vec<(&str, PluginState)> = context.plugin_states().into_iter().collect();
+        assert_eq!(
+            states,
+            [
+                ("Census", PluginState::DisabledByDefault),
+                ("Households", PluginState::DisabledByDefault),
+                ("Muted", PluginState::DisabledByOverride),
+                ("Orphans", PluginState::DisabledByFailedDependency),
+                ("Reports", PluginState::DisabledByFailedDependency),
+                ("Schools", PluginState::Enabled),
+                ("Surveys", PluginState::DisabledByFailedDependency),
+            ]
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! The identifier of a person created by a `Context`, and its renumbering by `Context::compact`.
+
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+use core::ops::Range;
+
+/// Identifies a person created by `Context::create_person` or `Context::create_people`. It wraps the index of the
+/// person's slot in every column, so that an id cannot be mixed up with a count or another index by accident. Convert
+/// with `From` when a bare index is needed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PersonId(pub usize);
+
+impl From<usize> for PersonId {
+    fn from(index: usize) -> Self {
+        PersonId(index)
+    }
+}
+
+impl From<PersonId> for usize {
+    fn from(person_id: PersonId) -> Self {
+        person_id.0
+    }
+}
+
+/// The consecutive ids of the people created by `Context::create_people` or added by `Context::merge`, from `start` up
+/// to but excluding `end`. Like `Range`, it iterates over the ids it holds.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PersonRange {
+    pub start: PersonId,
+    pub end: PersonId,
+}
+
+impl PersonRange {
+    /// Whether `person_id` is one of the ids in the range.
+    pub fn contains(&self, person_id: PersonId) -> bool {
+        self.start <= person_id && person_id < self.end
+    }
+}
+
+impl From<Range<usize>> for PersonRange {
+    fn from(indices: Range<usize>) -> Self {
+        PersonRange { start: PersonId(indices.start), end: PersonId(indices.end) }
+    }
+}
+
+impl From<PersonRange> for Range<usize> {
+    fn from(people: PersonRange) -> Self {
+        people.start.0..people.end.0
+    }
+}
+
+impl Iterator for PersonRange {
+    type Item = PersonId;
+
+    fn next(&mut self) -> Option<PersonId> {
+        (self.start < self.end).then(|| {
+            self.start.0 += 1;
+            PersonId(self.start.0 - 1)
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end.0.saturating_sub(self.start.0);
+        (len, Some(len))
+    }
+}
+
+impl DoubleEndedIterator for PersonRange {
+    fn next_back(&mut self) -> Option<PersonId> {
+        (self.start < self.end).then(|| {
+            self.end.0 -= 1;
+            self.end
+        })
+    }
+}
+
+impl ExactSizeIterator for PersonRange {}
+
+/// How `Context::compact` renumbered the people, for updating ids held outside the `Context`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RemapTable {
+    /// The new id of each old id, or `None` for a removed person.
+    new_ids: Vec<Option<PersonId>>,
+}
+
+impl RemapTable {
+    pub(crate) fn new(new_ids: Vec<Option<PersonId>>) -> Self {
+        RemapTable { new_ids }
+    }
+
+    /// The new id of the person who had the id `old`, or `None` if that person was removed or never created.
+    pub fn get(&self, old: PersonId) -> Option<PersonId> {
+        self.new_ids.get(old.0).copied().flatten()
+    }
+
+    /// Each remaining person's old and new id, in id order.
+    pub fn iter(&self) -> impl Iterator<Item = (PersonId, PersonId)> + '_ {
+        self.new_ids
+            .iter()
+            .enumerate()
+            .filter_map(|(old, new)| new.map(|new| (PersonId(old), new)))
+    }
+}
+
+impl Display for PersonId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{PersonId, PersonRange};
+    use crate::Context;
+
+    #[test]
+    fn person_ids_flow_through_the_context() {
+        let mut context = Context::new();
+        let first = context.create_person().unwrap();
+        let second = context.create_person().unwrap();
+        assert_eq!((first, second), (PersonId(0), PersonId(1)));
+        assert_eq!(context.get::<u32>("Age", second), Some(&42));
+
+        context.set::<u32>("Age", second, 30).unwrap();
+        assert_eq!(context.iter_property::<u32>("Age").collect::<Vec<_>>(), vec![(first, &42), (second, &30)]);
+        assert_eq!(usize::from(second), 1);
+        assert_eq!(PersonId::from(1), second);
+        assert_eq!(second.to_string(), "1");
+    }
+
+    #[test]
+    fn create_people_returns_the_new_ids() {
+        let mut context = Context::new();
+        context.create_person().unwrap();
+        let people = context.create_people(2).unwrap();
+        assert_eq!(people, PersonRange::from(1..3));
+        assert_eq!((people.len(), people.contains(PersonId(2)), people.contains(PersonId(0))), (2, true, false));
+        assert_eq!(people.clone().rev().collect::<Vec<_>>(), vec![PersonId(2), PersonId(1)]);
+        assert_eq!(core::ops::Range::<usize>::from(people), 1..3);
+    }
+
+    #[test]
+    fn entity_ids_skip_removed_people() {
+        let mut context = Context::new();
+        assert_eq!(context.entity_count(), 0);
+        context.create_people(3).unwrap();
+        context.remove_person(PersonId(1)).unwrap();
+        assert_eq!(context.entity_count(), 2);
+        assert_eq!(context.entity_ids().collect::<Vec<_>>(), vec![PersonId(0), PersonId(2)]);
+
+        // The freed id is reused.
+        context.create_person().unwrap();
+        assert_eq!(context.entity_count(), 3);
+        assert_eq!(context.entity_ids().collect::<Vec<_>>(), vec![PersonId(0), PersonId(1), PersonId(2)]);
+    }
+}
@@ -0,0 +1,199 @@
+//! A `const` builder for `Plugin`, as an alternative to writing out every field.
+
+use crate::{BatchInitializer, Context, DefaultState, InitError, PersonId, Plugin, PropertySpec, Validator, Version};
+
+/// Builds a `Plugin` field by field, starting from defaults, so a plugin only spells out what it needs. Every method
+/// is a `const fn`, which is what lets the result initialize the `static` of a distributed slice:
+///
+/// ```
+/// use initialization::{Context, Plugin, Property};
+///
+/// static HEIGHT: Property<f64> = Property{
+///     name: "Height",
+///     default: 170.0,
+///     initializer: None,
+/// };
+///
+/// // Normally this would also be annotated with `#[distributed_slice(PLUGINS)]`.
+/// static HEIGHT_PLUGIN: Plugin = Plugin::builder(HEIGHT.name)
+///     .description("Height of the person in cm")
+///     .required(true)
+///     .initializer(|context, person_id| HEIGHT.initialize(context, person_id))
+///     .constructor(|context| HEIGHT.construct(context))
+///     .build();
+///
+/// let mut context = Context::with_plugins(std::slice::from_ref(&HEIGHT_PLUGIN));
+/// let person_id = context.create_person().unwrap();
+/// assert_eq!(context.get::<f64>("Height", person_id), Some(&170.0));
+/// ```
+///
+/// The `constructor` need not register the plugin itself, since the `Context` registers every plugin before calling
+/// its constructor, so it only registers the plugin's columns. It defaults to doing nothing.
+pub struct PluginBuilder {
+    plugin: Plugin,
+}
+
+impl PluginBuilder {
+    /// A builder for a plugin named `name`, with every other field as in `Plugin::DEFAULT`.
+    pub const fn new(name: &'static str) -> Self {
+        PluginBuilder {
+            plugin: Plugin{ name, ..Plugin::DEFAULT },
+        }
+    }
+
+    pub const fn namespace(mut self, namespace: &'static str) -> Self {
+        self.plugin.namespace = namespace;
+        self
+    }
+
+    pub const fn origin(mut self, origin: &'static str) -> Self {
+        self.plugin.origin = origin;
+        self
+    }
+
+    pub const fn aliases(mut self, aliases: &'static [&'static str]) -> Self {
+        self.plugin.aliases = aliases;
+        self
+    }
+
+    pub const fn description(mut self, description: &'static str) -> Self {
+        self.plugin.description = description;
+        self
+    }
+
+    pub const fn unit(mut self, unit: &'static str) -> Self {
+        self.plugin.unit = Some(unit);
+        self
+    }
+
+    pub const fn required(mut self, required: bool) -> Self {
+        self.plugin.required = required;
+        self
+    }
+
+    pub const fn immutable(mut self, immutable: bool) -> Self {
+        self.plugin.immutable = immutable;
+        self
+    }
+
+    pub const fn enabled(mut self, enabled: bool) -> Self {
+        self.plugin.enabled = enabled;
+        self
+    }
+
+    pub const fn initializer(
+        mut self,
+        initializer: fn(&mut Context, person_id: PersonId) -> Result<(), InitError>,
+    ) -> Self {
+        self.plugin.initializer = initializer;
+        self
+    }
+
+    pub const fn dependencies(mut self, dependencies: &'static [&'static str]) -> Self {
+        self.plugin.dependencies = dependencies;
+        self
+    }
+
+    pub const fn optional_dependencies(mut self, optional_dependencies: &'static [&'static str]) -> Self {
+        self.plugin.optional_dependencies = optional_dependencies;
+        self
+    }
+
+    pub const fn dependency_versions(mut self, dependency_versions: &'static [(&'static str, Version)]) -> Self {
+        self.plugin.dependency_versions = dependency_versions;
+        self
+    }
+
+    pub const fn conflicts_with(mut self, conflicts_with: &'static [&'static str]) -> Self {
+        self.plugin.conflicts_with = conflicts_with;
+        self
+    }
+
+    pub const fn priority(mut self, priority: i32) -> Self {
+        self.plugin.priority = priority;
+        self
+    }
+
+    pub const fn version(mut self, version: Version) -> Self {
+        self.plugin.version = version;
+        self
+    }
+
+    pub const fn tags(mut self, tags: &'static [&'static str]) -> Self {
+        self.plugin.tags = tags;
+        self
+    }
+
+    pub const fn default_state(mut self, default_state: DefaultState) -> Self {
+        self.plugin.default_state = default_state;
+        self
+    }
+
+    pub const fn batch_initializer(mut self, batch_initializer: BatchInitializer) -> Self {
+        self.plugin.batch_initializer = Some(batch_initializer);
+        self
+    }
+
+    pub const fn constructor(mut self, constructor: fn(&mut Context)) -> Self {
+        self.plugin.constructor = constructor;
+        self
+    }
+
+    pub const fn teardown(mut self, teardown: fn(&mut Context)) -> Self {
+        self.plugin.teardown = Some(teardown);
+        self
+    }
+
+    pub const fn on_person_created(mut self, on_person_created: fn(&mut Context, person_id: PersonId)) -> Self {
+        self.plugin.on_person_created = Some(on_person_created);
+        self
+    }
+
+    pub const fn validator(mut self, validator: Validator) -> Self {
+        self.plugin.validator = Some(validator);
+        self
+    }
+
+    pub const fn properties(mut self, properties: &'static [PropertySpec]) -> Self {
+        self.plugin.properties = properties;
+        self
+    }
+
+    pub const fn build(self) -> Plugin {
+        self.plugin
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::{Context, DefaultState, Plugin, Property};
+
+    static SCORE: Property<i32> = Property{
+        name: "Score",
+        default: 10,
+        initializer: None,
+    };
+
+    static BUILT_PLUGINS: [Plugin; 2] = [
+        Plugin::builder("Base").required(true).default_state(DefaultState::Unconditional).build(),
+        Plugin::builder(SCORE.name)
+            .namespace("games")
+            .description("A score")
+            .dependencies(&["Base"])
+            .initializer(|context, person_id| SCORE.initialize(context, person_id))
+            .constructor(|context| SCORE.construct(context))
+            .build(),
+    ];
+
+    #[test]
+    fn built_plugins_register_themselves() {
+        let mut context = Context::with_plugins(&BUILT_PLUGINS);
+        assert_eq!(context.init_order(), ["Base", "Score"]);
+        assert_eq!(context.get_plugin("games::Score").unwrap().description, "A score");
+        assert!(context.get_plugin("Base").unwrap().required);
+
+        let person_id = context.create_person().unwrap();
+        assert_eq!(context.get::<i32>("Score", person_id), Some(&10));
+    }
+}
@@ -0,0 +1,384 @@
+//! Typed per-entity properties layered over the untyped `Plugin`.
+
+use alloc::vec::Vec;
+use core::any::Any;
+use core::ops::Range;
+
+use crate::column::store_mut;
+use crate::{Context, InitError, PersonId};
+
+/// A per-entity property whose values have type `T`. A `Property` is declared as a `static` next to the `Plugin` that
+/// registers it, and the plugin's `constructor` and `initializer` delegate to `Property::construct` and
+/// `Property::initialize`:
+///
+/// ```
+/// use initialization::{Plugin, Property};
+///
+/// static HEIGHT: Property<f64> = Property{
+///     name: "Height",
+///     default: 170.0,
+///     initializer: None,
+/// };
+///
+/// // Normally this would also be annotated with `#[distributed_slice(PLUGINS)]`.
+/// static HEIGHT_PLUGIN: Plugin = Plugin{
+///     name: HEIGHT.name,
+///     description: "Height of the person in cm",
+///     required: true,
+///     initializer: |context, person_id| HEIGHT.initialize(context, person_id),
+///     batch_initializer: Some(|column, people| HEIGHT.initialize_batch(column, people)),
+///     constructor: |context| {
+///         context.register_plugin(&HEIGHT_PLUGIN);
+///         HEIGHT.construct(context);
+///     },
+///     ..Plugin::DEFAULT
+/// };
+/// ```
+pub struct Property<T: 'static> {
+    /// The name of the plugin whose column holds the values, so the plugin's `name` is usually set to this, or the
+    /// name of a `PropertySpec` among the plugin's `properties`.
+    pub name: &'static str,
+    /// The first value assigned to an entity when there is no `initializer`.
+    pub default: T,
+    /// Computes the first value assigned to an entity, possibly from the values of the plugin's dependencies. Falls
+    /// back to `default` when `None`.
+    pub initializer: Option<fn(&mut Context, person_id: PersonId) -> T>,
+}
+
+/// One of several properties that a single plugin contributes, listed in `Plugin::properties`, such as the first and
+/// the last name. Each has its own columns, looked up by the spec's `name` rather than the plugin's, even when they
+/// hold values of the same type. The `Context` calls `constructor` right after the plugin's `constructor`, and
+/// `initializer` for each new person right after the plugin's `initializer`, so the functions usually delegate to a
+/// `Property` of the same name:
+///
+/// ```
+/// use initialization::{Context, Plugin, Property, PropertySpec};
+///
+/// static FIRST_NAME: Property<&str> = Property{ name: "FirstName", default: "Ada", initializer: None };
+/// static LAST_NAME: Property<&str> = Property{ name: "LastName", default: "Lovelace", initializer: None };
+///
+/// static NAME_PLUGINS: [Plugin; 1] = [Plugin::builder("Names")
+///     .properties(&[
+///         PropertySpec{
+///             name: FIRST_NAME.name,
+///             constructor: |context| FIRST_NAME.construct(context),
+///             initializer: |context, person_id| FIRST_NAME.initialize(context, person_id),
+///         },
+///         PropertySpec{
+///             name: LAST_NAME.name,
+///             constructor: |context| LAST_NAME.construct(context),
+///             initializer: |context, person_id| LAST_NAME.initialize(context, person_id),
+///         },
+///     ])
+///     .build()];
+///
+/// let mut context = Context::with_plugins(&NAME_PLUGINS);
+/// let person_id = context.create_person().unwrap();
+/// assert_eq!(context.get::<&str>("LastName", person_id), Some(&"Lovelace"));
+/// ```
+///
+/// The properties belong to the plugin: they are enabled, disabled and validated with it, and their names are not
+/// plugin names, so they cannot be passed to `Context::enable`, listed as dependencies or observed. Their columns are
+/// saved in checkpoints like the plugin's own, given a codec, but not exported to CSV.
+#[derive(Clone, Copy)]
+pub struct PropertySpec {
+    /// The name the property's columns are looked up by, which must differ from the names of the enabled plugins.
+    pub name: &'static str,
+    /// Registers the property's columns under `name`, like a plugin's `constructor`.
+    pub constructor: fn(&mut Context),
+    /// Stores the property's first value for a new person, like a plugin's `initializer`.
+    pub initializer: fn(&mut Context, person_id: PersonId) -> Result<(), InitError>,
+}
+
+impl<T: Clone + Send + Sync + 'static> Property<T> {
+    /// Registers the column holding this property's values, owned by the plugin named `name`, with the `default` as
+    /// the blank that replaces the value of a removed person. See `Context::register_reset`.
+    pub fn construct(&self, context: &mut Context) {
+        context.register_property::<T>(self.name);
+        context.register_reset::<T>(self.name, self.default.clone());
+    }
+
+    /// Computes the first value of this property for `person_id` and stores it in the property's column, overwriting
+    /// the value of a removed person whose id is reused. Matches the signature of `Plugin::initializer`, but never
+    /// fails.
+    pub fn initialize(&self, context: &mut Context, person_id: PersonId) -> Result<(), InitError> {
+        let value = match self.initializer {
+            Some(initializer) => initializer(context, person_id),
+            None => self.default.clone(),
+        };
+
+        let column = context
+            .store_mut::<T>(self.name)
+            .unwrap_or_else(|| panic!("the column for property `{}` is not registered", self.name));
+        if person_id.0 < column.len() {
+            column.set(person_id.0, value);
+        } else {
+            debug_assert_eq!(column.len(), person_id.0);
+            column.push(value);
+        }
+        Ok(())
+    }
+
+    /// Stores the `default` value for every person in `people` in the property's `column`. Matches the signature of
+    /// `Plugin::batch_initializer`, but never fails.
+    ///
+    /// Panics if the property has an `initializer`, which needs the whole `Context`.
+    pub fn initialize_batch(&self, column: &mut dyn Any, people: Range<usize>) -> Result<(), InitError> {
+        assert!(
+            self.initializer.is_none(),
+            "property `{}` has an initializer, so it cannot be initialized in a batch",
+            self.name
+        );
+
+        if let Some(column) = column.downcast_mut::<Vec<T>>() {
+            debug_assert_eq!(column.len(), people.start);
+            column.resize(people.end, self.default.clone());
+            return Ok(());
+        }
+        let column = store_mut::<T>(column)
+            .unwrap_or_else(|| panic!("the column for property `{}` has the wrong type", self.name));
+        debug_assert_eq!(column.len(), people.start);
+        for _ in people {
+            column.push(self.default.clone());
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use crate::{Context, PersonId, Plugin, Property, PropertySpec};
+
+    static NICKNAME: Property<String> = Property{
+        name: "Nickname",
+        default: String::new(),
+        initializer: Some(|_context, person_id| format!("Person {}", person_id)),
+    };
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Cohort(u16);
+
+    static COHORT: Property<Cohort> = Property{
+        name: "Cohort",
+        default: Cohort(1990),
+        initializer: None,
+    };
+
+    static PROPERTY_PLUGINS: [Plugin; 2] = [
+        Plugin{
+            name: NICKNAME.name,
+            description: "What the person is called",
+            initializer: |context, person_id| NICKNAME.initialize(context, person_id),
+            constructor: |context| {
+                context.register_plugin(&PROPERTY_PLUGINS[0]);
+                NICKNAME.construct(context);
+            },
+            ..Plugin::DEFAULT
+        },
+        Plugin{
+            name: COHORT.name,
+            description: "Birth cohort of the person",
+            required: true,
+            initializer: |context, person_id| COHORT.initialize(context, person_id),
+            batch_initializer: Some(|column, people| COHORT.initialize_batch(column, people)),
+            constructor: |context| {
+                context.register_plugin(&PROPERTY_PLUGINS[1]);
+                COHORT.construct(context);
+            },
+            ..Plugin::DEFAULT
+        },
+    ];
+
+    static WEIGHT: Property<u32> = Property{
+        name: "Weight",
+        default: 81,
+        initializer: None,
+    };
+
+    static HEIGHT: Property<u32> = Property{
+        name: "Height",
+        default: 180,
+        initializer: None,
+    };
+
+    // Computed from the person's weight in kg and height in cm, which the dependencies guarantee are already set.
+    static BMI: Property<f64> = Property{
+        name: "BMI",
+        default: 0.0,
+        initializer: Some(|context, person_id| {
+            let weight = *context.get::<u32>("Weight", person_id).unwrap() as f64;
+            let height = *context.get::<u32>("Height", person_id).unwrap() as f64 / 100.0;
+            weight / (height * height)
+        }),
+    };
+
+    // BMI is declared first, so only the dependencies put it after Weight and Height.
+    static COMPUTED_PLUGINS: [Plugin; 3] = [
+        Plugin::builder(BMI.name)
+            .dependencies(&["Weight", "Height"])
+            .initializer(|context, person_id| BMI.initialize(context, person_id))
+            .constructor(|context| BMI.construct(context))
+            .build(),
+        Plugin::builder(WEIGHT.name)
+            .initializer(|context, person_id| WEIGHT.initialize(context, person_id))
+            .constructor(|context| WEIGHT.construct(context))
+            .build(),
+        Plugin::builder(HEIGHT.name)
+            .initializer(|context, person_id| HEIGHT.initialize(context, person_id))
+            .constructor(|context| HEIGHT.construct(context))
+            .build(),
+    ];
+
+    #[test]
+    fn initializer_reads_dependency_values() {
+        let mut context = Context::with_plugins(&COMPUTED_PLUGINS);
+        assert_eq!(context.init_order().last(), Some(&"BMI"));
+
+        let person_id = context.create_person().unwrap();
+        assert_eq!(context.get::<f64>("BMI", person_id), Some(&25.0));
+    }
+
+    // Unlike WEIGHT, differs from person to person, so each BMI must come from the same person's weight.
+    static VARYING_WEIGHT: Property<u32> = Property{
+        name: "Weight",
+        default: 0,
+        initializer: Some(|_context, person_id| 60 + 10 * person_id.0 as u32),
+    };
+
+    static VARYING_PLUGINS: [Plugin; 3] = [
+        Plugin::builder(BMI.name)
+            .dependencies(&["Weight", "Height"])
+            .initializer(|context, person_id| BMI.initialize(context, person_id))
+            .constructor(|context| BMI.construct(context))
+            .build(),
+        Plugin::builder(VARYING_WEIGHT.name)
+            .initializer(|context, person_id| VARYING_WEIGHT.initialize(context, person_id))
+            .constructor(|context| VARYING_WEIGHT.construct(context))
+            .build(),
+        Plugin::builder(HEIGHT.name)
+            .initializer(|context, person_id| HEIGHT.initialize(context, person_id))
+            .constructor(|context| HEIGHT.construct(context))
+            .build(),
+    ];
+
+    #[test]
+    fn each_person_is_computed_from_their_own_values() {
+        let mut context = Context::with_plugins(&VARYING_PLUGINS);
+        let people: Vec<PersonId> = (0..3).map(|_| context.create_person().unwrap()).collect();
+
+        for person_id in people {
+            let weight = *context.get::<u32>("Weight", person_id).unwrap();
+            assert_eq!(weight, 60 + 10 * person_id.0 as u32);
+            let expected = weight as f64 / (1.8 * 1.8);
+            assert_eq!(context.get::<f64>("BMI", person_id), Some(&expected));
+        }
+    }
+
+    #[test]
+    fn typed_defaults_land_in_columns() {
+        let mut context = Context::with_plugins(&PROPERTY_PLUGINS);
+        context.create_person().unwrap();
+        context.create_person().unwrap();
+
+        assert_eq!(context.property::<Cohort>("Cohort"), Some(&vec![Cohort(1990), Cohort(1990)]));
+        assert_eq!(
+            context.property::<String>("Nickname"),
+            Some(&vec!["Person 0".to_string(), "Person 1".to_string()])
+        );
+
+        context.create_people(2).unwrap();
+        assert_eq!(context.property::<Cohort>("Cohort").unwrap().len(), 4);
+        assert_eq!(context.property::<String>("Nickname").unwrap()[3], "Person 3");
+    }
+
+    static DROPPED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    // A `String` that records itself in `DROPPED` when it is dropped, unless it is empty like the blank.
+    #[derive(Clone, Debug, PartialEq)]
+    struct Motto(String);
+
+    impl Drop for Motto {
+        fn drop(&mut self) {
+            if !self.0.is_empty() {
+                DROPPED.lock().unwrap().push(self.0.clone());
+            }
+        }
+    }
+
+    static MOTTO: Property<Motto> = Property{
+        name: "Motto",
+        default: Motto(String::new()),
+        initializer: Some(|_context, person_id| Motto(format!("Motto of person {}", person_id))),
+    };
+
+    static MOTTO_PLUGINS: [Plugin; 1] = [Plugin::builder(MOTTO.name)
+        .initializer(|context, person_id| MOTTO.initialize(context, person_id))
+        .constructor(|context| MOTTO.construct(context))
+        .build()];
+
+    #[test]
+    fn removing_a_person_drops_their_values() {
+        let mut context = Context::with_plugins(&MOTTO_PLUGINS);
+        let first = context.create_person().unwrap();
+        let second = context.create_person().unwrap();
+        context.remove_person(first).unwrap();
+
+        assert_eq!(*DROPPED.lock().unwrap(), vec!["Motto of person 0".to_string()]);
+        assert_eq!(context.property::<Motto>("Motto").unwrap()[0], MOTTO.default);
+        assert_eq!(context.get::<Motto>("Motto", second), Some(&Motto("Motto of person 1".to_string())));
+
+        assert_eq!(context.create_person().unwrap(), first);
+        assert_eq!(context.get::<Motto>("Motto", first), Some(&Motto("Motto of person 0".to_string())));
+    }
+
+    static FIRST_NAME: Property<String> = Property{
+        name: "FirstName",
+        default: String::new(),
+        initializer: Some(|_context, person_id| format!("Given {}", person_id)),
+    };
+
+    static LAST_NAME: Property<String> = Property{
+        name: "LastName",
+        default: String::new(),
+        initializer: Some(|context, person_id| {
+            let first_name = context.get::<String>("FirstName", person_id).unwrap();
+            first_name.replace("Given", "Family")
+        }),
+    };
+
+    static NAME_PLUGINS: [Plugin; 1] = [Plugin::builder("Names")
+        .properties(&[
+            PropertySpec{
+                name: FIRST_NAME.name,
+                constructor: |context| FIRST_NAME.construct(context),
+                initializer: |context, person_id| FIRST_NAME.initialize(context, person_id),
+            },
+            PropertySpec{
+                name: LAST_NAME.name,
+                constructor: |context| LAST_NAME.construct(context),
+                initializer: |context, person_id| LAST_NAME.initialize(context, person_id),
+            },
+        ])
+        .build()];
+
+    #[test]
+    fn one_plugin_contributes_several_properties() {
+        let mut context = Context::with_plugins(&NAME_PLUGINS);
+        context.create_person().unwrap();
+        context.create_people(1).unwrap();
+        let first_names = vec!["Given 0".to_string(), "Given 1".to_string()];
+        assert_eq!(context.property::<String>("FirstName"), Some(&first_names));
+        let last_names = vec!["Family 0".to_string(), "Family 1".to_string()];
+        assert_eq!(context.property::<String>("LastName"), Some(&last_names));
+
+        context.set("LastName", PersonId(1), "Byron".to_string()).unwrap();
+        assert_eq!(context.get::<String>("LastName", PersonId(1)), Some(&"Byron".to_string()));
+        assert_eq!(context.get::<String>("FirstName", PersonId(1)), Some(&"Given 1".to_string()));
+
+        context.disable("Names").unwrap();
+        assert_eq!(context.property::<String>("FirstName"), None);
+    }
+}
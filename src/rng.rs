@@ -0,0 +1,103 @@
+//! A small seedable random number generator for randomized initial values.
+
+use core::ops::Range;
+
+/// The SplitMix64 generator, owned by each `Context` and reachable from initializers through `Context::rng`. It is
+/// fast and statistically adequate for drawing initial values, but not cryptographically secure.
+///
+/// The same seed always gives the same sequence, so a `Context` built with the same `ContextBuilder::seed` and the same
+/// plugins creates the same people.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// A generator whose sequence is determined by `seed`.
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    /// The state that `Rng::new` restores, for saving the generator in a checkpoint.
+    #[cfg(feature = "std")]
+    pub(crate) fn state(&self) -> u64 {
+        self.state
+    }
+
+    /// A number uniformly distributed over all of `u64`.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number uniformly distributed in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        // The top 53 bits fill the mantissa exactly.
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A number uniformly distributed in `range`.
+    ///
+    /// Panics if `range` is empty.
+    pub fn range(&mut self, range: Range<u64>) -> u64 {
+        assert!(!range.is_empty(), "cannot draw from the empty range {:?}", range);
+        let width = range.end - range.start;
+        // Reject the top values that would make some results more likely than others.
+        let limit = u64::MAX - u64::MAX % width;
+        loop {
+            let value = self.next_u64();
+            if value < limit {
+                return range.start + value % width;
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::Rng;
+    use crate::{ContextBuilder, Plugin, Property};
+
+    #[test]
+    fn matches_the_reference_sequence() {
+        let mut rng = Rng::new(0);
+        assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(rng.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+    }
+
+    #[test]
+    fn draws_stay_in_range() {
+        let mut rng = Rng::new(1);
+        for _ in 0..1000 {
+            assert!((10..20).contains(&rng.range(10..20)));
+            assert!((0.0..1.0).contains(&rng.next_f64()));
+        }
+    }
+
+    static LUCK: Property<u64> = Property{
+        name: "Luck",
+        default: 0,
+        initializer: Some(|context, _person_id| context.rng().range(0..1000)),
+    };
+
+    static RANDOM_PLUGINS: [Plugin; 1] = [Plugin::builder(LUCK.name)
+        .initializer(|context, person_id| LUCK.initialize(context, person_id))
+        .constructor(|context| LUCK.construct(context))
+        .build()];
+
+    fn luck_with_seed(seed: u64) -> Vec<u64> {
+        let mut context = ContextBuilder::with_plugins(&RANDOM_PLUGINS).seed(seed).build().unwrap();
+        context.create_people(5).unwrap();
+        context.property::<u64>("Luck").unwrap().clone()
+    }
+
+    #[test]
+    fn seeded_contexts_create_the_same_people() {
+        assert_eq!(luck_with_seed(7), luck_with_seed(7));
+        assert_ne!(luck_with_seed(7), luck_with_seed(8));
+    }
+}
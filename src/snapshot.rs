@@ -0,0 +1,145 @@
+//! A record of the effective plugin configuration of a `Context`, for reproducing and auditing runs.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use crate::Plugin;
+
+/// The configuration of every plugin a `Context` was built from, sorted by name. Returned by
+/// `Context::config_snapshot`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigSnapshot {
+    pub plugins: Vec<PluginConfig>,
+}
+
+/// The effective configuration of one plugin.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PluginConfig {
+    pub name: &'static str,
+    /// Whether the plugin was initialized, taking `ContextBuilder` overrides into account.
+    pub enabled: bool,
+    pub required: bool,
+    pub description: &'static str,
+}
+
+impl ConfigSnapshot {
+    /// Serializes the snapshot as a JSON array of `{"name", "enabled", "required", "description"}` objects.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("[");
+        for (index, plugin) in self.plugins.iter().enumerate() {
+            if index > 0 {
+                json.push(',');
+            }
+            json.push_str("{\"name\":");
+            write_json_string(&mut json, plugin.name);
+            write!(json, ",\"enabled\":{},\"required\":{}", plugin.enabled, plugin.required).unwrap();
+            json.push_str(",\"description\":");
+            write_json_string(&mut json, plugin.description);
+            json.push('}');
+        }
+        json.push(']');
+        json
+    }
+}
+
+/// A 64-bit FNV-1a hash of the namespace, name, version, `required` flag and priority of each of the `enabled`
+/// plugins, in the given order. FNV-1a is written out rather than taken from `std::hash`, whose hashers may change
+/// between Rust releases, so that fingerprints can be compared across runs and builds.
+pub(crate) fn fingerprint(enabled: &[&'static Plugin]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    let mut hash = OFFSET_BASIS;
+    let mut write = |bytes: &[u8]| {
+        for &byte in bytes {
+            hash = (hash ^ u64::from(byte)).wrapping_mul(PRIME);
+        }
+    };
+    for plugin in enabled {
+        // Strings end in a byte that UTF-8 never contains, so that adjacent fields cannot run into each other.
+        for text in [plugin.namespace, plugin.name] {
+            write(text.as_bytes());
+            write(&[0xff]);
+        }
+        let (major, minor, patch) = plugin.version;
+        for part in [major, minor, patch] {
+            write(&part.to_le_bytes());
+        }
+        write(&[u8::from(plugin.required)]);
+        write(&plugin.priority.to_le_bytes());
+    }
+    hash
+}
+
+/// Appends `value` to `json` as a quoted JSON string.
+pub(crate) fn write_json_string(json: &mut String, value: &str) {
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{write_json_string, ConfigSnapshot, PluginConfig};
+    use crate::{Context, ContextBuilder, Plugin};
+
+    #[test]
+    fn snapshot_reflects_overrides() {
+        let enabled = Context::new().config_snapshot();
+        assert_eq!(
+            enabled.to_json(),
+            r#"[{"name":"Age","enabled":true,"required":true,"description":"Age of the person"}]"#
+        );
+
+        let disabled = ContextBuilder::with_plugins(&FINGERPRINTED_PLUGINS).disable("Height").build().unwrap();
+        let disabled = disabled.config_snapshot();
+        let height = PluginConfig{ name: "Height", enabled: false, required: false, description: "" };
+        assert_eq!(disabled.plugins[0], height);
+        assert!(disabled.to_json().contains(r#""name":"Height","enabled":false"#));
+    }
+
+    static FINGERPRINTED_PLUGINS: [Plugin; 2] = [
+        Plugin{ name: "Weight", version: (2, 1, 0), ..Plugin::DEFAULT },
+        Plugin{ name: "Height", ..Plugin::DEFAULT },
+    ];
+
+    static REORDERED_PLUGINS: [Plugin; 2] = [
+        Plugin{ name: "Height", ..Plugin::DEFAULT },
+        Plugin{ name: "Weight", version: (2, 1, 0), ..Plugin::DEFAULT },
+    ];
+
+    #[test]
+    fn fingerprint_follows_the_effective_configuration() {
+        let fingerprint = |builder: ContextBuilder| builder.build().unwrap().config_fingerprint();
+        let both = fingerprint(ContextBuilder::with_plugins(&FINGERPRINTED_PLUGINS));
+        assert_eq!(both, fingerprint(ContextBuilder::with_plugins(&FINGERPRINTED_PLUGINS).enable("Height")));
+        assert_eq!(both, fingerprint(ContextBuilder::with_plugins(&REORDERED_PLUGINS)));
+        assert_eq!(Context::new().config_fingerprint(), Context::new().config_fingerprint());
+
+        let without_height = fingerprint(ContextBuilder::with_plugins(&FINGERPRINTED_PLUGINS).disable("Height"));
+        assert_ne!(both, without_height);
+    }
+
+    #[test]
+    fn strings_are_escaped() {
+        let mut json = String::new();
+        write_json_string(&mut json, "say \"hi\"\\\n\u{1}");
+        assert_eq!(json, r#""say \"hi\"\\\n\u0001""#);
+    }
+
+    #[test]
+    fn empty_snapshot_is_an_empty_array() {
+        assert_eq!(ConfigSnapshot{ plugins: Vec::new() }.to_json(), "[]");
+    }
+}
@@ -0,0 +1,189 @@
+//! The storage behind a property column, for plugins that keep their values somewhere other than in memory.
+
+use alloc::vec::Vec;
+
+/// The per-entity values of one property column, indexed by `PersonId`. Columns are `Vec<T>`s unless a plugin
+/// registers another store with `Context::register_property_store`, such as one backed by a memory-mapped file for a
+/// population too large for memory. `Context::get`, `Context::set`, `Context::iter_property`, `Property` and the
+/// codecs, formatters and resets work with any store, while `Context::property` and `Context::property_mut` only
+/// return `Vec<T>` columns.
+///
+/// ```
+/// use initialization::{Context, Plugin, PropertyStore};
+///
+/// /// Stores a value once for every run of equal values.
+/// struct RunLengthStore(Vec<(u8, usize)>);
+///
+/// impl PropertyStore<u8> for RunLengthStore {
+///     fn len(&self) -> usize {
+///         self.0.iter().map(|(_, count)| count).sum()
+///     }
+///
+///     fn get(&self, mut index: usize) -> Option<&u8> {
+///         for (value, count) in &self.0 {
+///             if index < *count {
+///                 return Some(value);
+///             }
+///             index -= count;
+///         }
+///         None
+///     }
+///
+///     fn set(&mut self, index: usize, value: u8) {
+///         let mut values: Vec<u8> = (0..self.len()).map(|index| *self.get(index).unwrap()).collect();
+///         values[index] = value;
+///         self.0.clear();
+///         values.into_iter().for_each(|value| self.push(value));
+///     }
+///
+///     fn push(&mut self, value: u8) {
+///         match self.0.last_mut() {
+///             Some((last, count)) if *last == value => *count += 1,
+///             _ => self.0.push((value, 1)),
+///         }
+///     }
+///
+///     fn pop(&mut self) -> Option<u8> {
+///         let (value, count) = self.0.last_mut()?;
+///         let value = *value;
+///         *count -= 1;
+///         if *count == 0 {
+///             self.0.pop();
+///         }
+///         Some(value)
+///     }
+/// }
+///
+/// static LEVEL_PLUGINS: [Plugin; 1] = [Plugin::builder("Level")
+///     .constructor(|context| context.register_property_store::<u8>("Level", RunLengthStore(Vec::new())))
+///     .initializer(|context, person_id| {
+///         context.store_mut::<u8>("Level").unwrap().push(if person_id.0 < 2 { 1 } else { 2 });
+///         Ok(())
+///     })
+///     .build()];
+///
+/// let mut context = Context::with_plugins(&LEVEL_PLUGINS);
+/// context.create_people(3).unwrap();
+/// assert_eq!(context.get::<u8>("Level", 2.into()), Some(&2));
+/// assert!(context.property::<u8>("Level").is_none());
+/// ```
+pub trait PropertyStore<T> {
+    /// The number of values stored, which is the number of people that have a slot.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The value at `index`, or `None` if `index` is not less than `len`.
+    fn get(&self, index: usize) -> Option<&T>;
+
+    /// Replaces the value at `index`, which the `Context` only passes when it is less than `len`.
+    fn set(&mut self, index: usize, value: T);
+
+    /// Appends the value for the next person.
+    fn push(&mut self, value: T);
+
+    /// Removes and returns the last value, or `None` if the store is empty.
+    fn pop(&mut self) -> Option<T>;
+
+    /// Drops every value at index `len` or later. Pops values one by one unless the store overrides it.
+    fn truncate(&mut self, len: usize) {
+        while self.len() > len {
+            self.pop();
+        }
+    }
+}
+
+impl<T> PropertyStore<T> for Vec<T> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn get(&self, index: usize) -> Option<&T> {
+        <[T]>::get(self, index)
+    }
+
+    fn set(&mut self, index: usize, value: T) {
+        self[index] = value;
+    }
+
+    fn push(&mut self, value: T) {
+        Vec::push(self, value);
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        Vec::pop(self)
+    }
+
+    fn truncate(&mut self, len: usize) {
+        Vec::truncate(self, len);
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::PropertyStore;
+    use crate::{Context, PersonId, Plugin, Property};
+
+    static PUSHES: AtomicUsize = AtomicUsize::new(0);
+
+    /// Keeps the values in a `Vec` of its own, counting the values pushed.
+    struct CountingStore(Vec<u32>);
+
+    impl PropertyStore<u32> for CountingStore {
+        fn len(&self) -> usize {
+            self.0.len()
+        }
+
+        fn get(&self, index: usize) -> Option<&u32> {
+            self.0.get(index)
+        }
+
+        fn set(&mut self, index: usize, value: u32) {
+            self.0[index] = value;
+        }
+
+        fn push(&mut self, value: u32) {
+            PUSHES.fetch_add(1, Ordering::Relaxed);
+            self.0.push(value);
+        }
+
+        fn pop(&mut self) -> Option<u32> {
+            self.0.pop()
+        }
+    }
+
+    static SCORE: Property<u32> = Property{ name: "Score", default: 10, initializer: None };
+
+    static SCORE_PLUGINS: [Plugin; 1] = [Plugin::builder("Score")
+        .constructor(|context| {
+            context.register_property_store::<u32>("Score", CountingStore(Vec::new()));
+            context.register_reset::<u32>("Score", 0);
+        })
+        .initializer(|context, person_id| SCORE.initialize(context, person_id))
+        .batch_initializer(|column, people| SCORE.initialize_batch(column, people))
+        .build()];
+
+    #[test]
+    fn a_custom_store_backs_the_column() {
+        let mut context = Context::with_plugins(&SCORE_PLUGINS);
+        context.create_person().unwrap();
+        context.create_people(2).unwrap();
+        assert_eq!(PUSHES.load(Ordering::Relaxed), 3);
+        assert!(context.property::<u32>("Score").is_none());
+
+        context.set::<u32>("Score", PersonId(1), 25).unwrap();
+        context.remove_person(PersonId(0)).unwrap();
+        assert_eq!(context.store::<u32>("Score").unwrap().get(0), Some(&0));
+        let scores: Vec<(PersonId, &u32)> = context.iter_property::<u32>("Score").collect();
+        assert_eq!(scores, vec![(PersonId(1), &25), (PersonId(2), &10)]);
+
+        context.compact();
+        assert_eq!(context.get::<u32>("Score", PersonId(0)), Some(&25));
+        assert_eq!(context.store::<u32>("Score").unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,52 @@
+//! Non-fatal problems noticed while constructing a `Context`.
+
+use alloc::string::String;
+use core::fmt::{Display, Formatter};
+use core::time::Duration;
+
+/// A configuration problem that does not prevent a `Context` from being constructed, returned by
+/// `Context::warnings`. Callers that want to be strict can treat any warning as an error.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Warning {
+    /// The enabled plugin `plugin` lists `dependency` in `optional_dependencies`, and `dependency` is registered but
+    /// disabled.
+    OptionalDependencyDisabled { plugin: &'static str, dependency: &'static str },
+    /// A `ContextBuilder` override named a plugin that is not registered, and was ignored because of
+    /// `UnknownPlugins::Warn`.
+    UnknownPlugin(String),
+    /// A `ContextBuilder` tag override named a tag that no registered plugin carries, and was ignored because of
+    /// `UnknownPlugins::Warn`.
+    UnknownTag(String),
+    /// The environment variable read by `ContextBuilder::apply_env` names no registered plugin, and was ignored.
+    UnknownEnvPlugin(String),
+    /// The environment variable `variable` read by `ContextBuilder::apply_env` is set to `value` rather than `0` or
+    /// `1`, and was ignored.
+    InvalidEnvValue { variable: String, value: String },
+    /// `plugin.init` took `duration`, longer than the `threshold` set by `ContextBuilder::warn_slow`.
+    SlowInit { plugin: &'static str, duration: Duration, threshold: Duration },
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Warning::OptionalDependencyDisabled { plugin, dependency } => {
+                write!(f, "plugin `{}` optionally depends on `{}`, which is disabled", plugin, dependency)
+            }
+            Warning::UnknownPlugin(name) => {
+                write!(f, "ignoring override for unknown plugin `{}`", name)
+            }
+            Warning::UnknownTag(tag) => {
+                write!(f, "ignoring override for unknown tag `{}`", tag)
+            }
+            Warning::UnknownEnvPlugin(variable) => {
+                write!(f, "ignoring environment variable `{}`, which names no registered plugin", variable)
+            }
+            Warning::InvalidEnvValue { variable, value } => {
+                write!(f, "ignoring environment variable `{}` set to `{}` rather than `0` or `1`", variable, value)
+            }
+            Warning::SlowInit { plugin, duration, threshold } => {
+                write!(f, "plugin `{}` took {:?} to initialize, longer than {:?}", plugin, duration, threshold)
+            }
+        }
+    }
+}
@@ -0,0 +1,38 @@
+//! `#[derive(Plugin)]` registers plugins in the crate that uses it, so it is tested from outside `initialization`.
+
+use initialization::{Context, Plugin};
+
+#[derive(Plugin)]
+#[plugin(
+    name = "Income",
+    value = u64,
+    default = 30_000,
+    description = "Yearly income in dollars",
+    unit = "dollars",
+    required
+)]
+struct Income;
+
+#[derive(Plugin)]
+#[plugin(value = bool, default = false, dependencies(Income, Age))]
+struct HasLoan;
+
+#[test]
+fn derived_plugins_are_registered() {
+    let mut context = Context::new();
+    assert_eq!(context.names(), vec!["Age", "HasLoan", "Income"]);
+
+    let income = context.require(Income::NAME);
+    assert!(income.required);
+    assert_eq!(income.description, "Yearly income in dollars");
+    assert_eq!(income.unit, Some("dollars"));
+    assert_eq!(context.require(HasLoan::NAME).unit, None);
+    assert_eq!(income.namespace, env!("CARGO_PKG_NAME"));
+    assert_eq!(context.require(HasLoan::NAME).dependencies, ["Income", "Age"]);
+
+    let person_id = context.create_person().unwrap();
+    assert_eq!(context.get::<u64>(Income::NAME, person_id), Some(&30_000));
+    assert_eq!(context.get::<bool>(HasLoan::NAME, person_id), Some(&false));
+    context.create_people(2).unwrap();
+    assert_eq!(context.property::<u64>(Income::NAME), Some(&vec![30_000; 3]));
+}
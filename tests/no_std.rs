@@ -0,0 +1,17 @@
+//! The crate must keep building without its default `std` feature, which the other tests never do.
+
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn builds_without_std() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let output = Command::new(env!("CARGO"))
+        .args(["check", "--lib", "--no-default-features", "--package", "initialization"])
+        .arg("--target-dir")
+        .arg(manifest_dir.join("target").join("no_std"))
+        .current_dir(manifest_dir)
+        .output()
+        .expect("cargo runs");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}